@@ -0,0 +1,384 @@
+//! Probe arguments shared by pt and poke, flattened into each binary's arguments.
+
+use port_tester::connectors::banner::{self, Banner};
+use port_tester::connectors::dns::{self, Dns, RecordType, Transport};
+use port_tester::connectors::http::{BodyMatch, Http, parse_status_range};
+use port_tester::connectors::script::Script;
+#[cfg(feature = "tls")]
+use port_tester::connectors::tls::Tls;
+use port_tester::connectors::udp::{self, Udp, parse_hex};
+use port_tester::connectors::{Registry, port_open};
+use port_tester::{CODE_OPTIONS_ERROR, Error, SourceError};
+use regex::Regex;
+use std::path::PathBuf;
+
+use clap::{Args, value_parser};
+
+const DEFAULT_EXPECT_STATUS: &str = "200-399";
+const DEFAULT_METHOD: &str = "GET";
+const DEFAULT_PATH: &str = "/";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Args)]
+#[command(next_help_heading = "Probe Options")]
+pub struct ProbeArgs {
+    /// Maximum number of banner bytes to read. Used by the banner probe.
+    #[arg(long, value_parser = value_parser!(u32).range(1..), default_value_t = banner::DEFAULT_BYTES as u32)]
+    pub banner_bytes: u32,
+    /// Require the DNS answers to include this value. May be repeated. Used by the dns probe.
+    #[arg(long)]
+    pub expect_answer: Vec<String>,
+    /// Require the banner to match this regular expression. Used by the banner probe.
+    #[arg(long, value_parser = validate_regex)]
+    pub expect_banner: Option<String>,
+    /// Require the HTTP response body to contain this text. Used by the http and https probes.
+    #[arg(long, conflicts_with = "expect_body_regex")]
+    pub expect_body: Option<String>,
+    /// Require the HTTP response body to match this regular expression. Used by the http and https
+    /// probes.
+    #[arg(long, value_parser = validate_regex)]
+    pub expect_body_regex: Option<String>,
+    /// Require the UDP reply to match this regular expression. Used by the udp probe.
+    #[arg(long, value_parser = validate_bytes_regex)]
+    pub expect_reply: Option<String>,
+    /// Accepted HTTP response status codes as a code, range or class, like 200, 200-299 or 2xx.
+    /// Used by the http and https probes.
+    #[arg(long, value_parser = validate_status, default_value = DEFAULT_EXPECT_STATUS)]
+    pub expect_status: String,
+    /// HTTP request header as 'Name: value'. May be repeated. Used by the http and https probes.
+    #[arg(short = 'H', long, value_parser = validate_header)]
+    pub header: Vec<String>,
+    /// Maximum time in milliseconds for an HTTP attempt to complete before it fails. Used by the
+    /// http and https probes.
+    #[arg(long, value_parser = value_parser!(u64).range(1..))]
+    pub max_time: Option<u64>,
+    /// HTTP request method. Used by the http and https probes.
+    #[arg(long, default_value = DEFAULT_METHOD)]
+    pub method: String,
+    /// HTTP request path. Used by the http and https probes.
+    #[arg(long, default_value = DEFAULT_PATH)]
+    pub path: String,
+    /// Read the UDP payload to send from this file. Used by the udp probe.
+    #[arg(long, conflicts_with = "payload_hex")]
+    pub payload_file: Option<PathBuf>,
+    /// UDP payload to send as hex, like '0d0a' or 'de:ad:be:ef'. Used by the udp probe.
+    #[arg(long, value_parser = validate_hex)]
+    pub payload_hex: Option<String>,
+    /// Probe to run for each attempt.
+    #[arg(long, default_value = port_open::NAME)]
+    pub probe: String,
+    /// Name to query. Used by the dns probe.
+    #[arg(long, default_value = dns::DEFAULT_QUERY_NAME)]
+    pub query_name: String,
+    /// Send the query over TCP instead of UDP. Used by the dns probe.
+    #[arg(long, default_value_t = false)]
+    pub query_tcp: bool,
+    /// Record type to query: A, AAAA, CNAME, MX, NS, PTR, SOA, SRV or TXT. Used by the dns probe.
+    #[arg(long, value_parser = validate_record_type, default_value_t = dns::DEFAULT_RECORD_TYPE.to_string())]
+    pub query_type: String,
+    /// Send/expect script to run after connecting. Used by the script probe.
+    /// One step per line: 'send <text>', 'send-hex <hex>', 'expect <regex>', 'timeout <ms>' or
+    /// 'close'.
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+    /// Send a UDP datagram and wait for a reply instead of opening a TCP connection. Same as
+    /// '--probe udp'.
+    #[arg(long, conflicts_with = "probe", default_value_t = false)]
+    pub udp: bool,
+}
+
+impl ProbeArgs {
+    /// Build the probe registry, configuring the built-in probes from the arguments.
+    pub fn registry(&self) -> port_tester::Result<Registry> {
+        let mut r = Registry::default();
+        r.register(Box::new(self.http(Http::new())?));
+        #[cfg(feature = "tls")]
+        r.register(Box::new(self.http(Http::https(Tls::new()))?));
+        r.register(Box::new(self.udp()?));
+        r.register(Box::new(self.dns()?));
+        r.register(Box::new(self.banner()?));
+        r.register(Box::new(self.script()?));
+        Ok(r)
+    }
+
+    /// Returns the name of the probe to run.
+    pub fn probe(&self) -> &str {
+        if self.udp { udp::NAME } else { &self.probe }
+    }
+
+    fn banner(&self) -> port_tester::Result<Banner> {
+        let mut banner = Banner::new().set_bytes(self.banner_bytes as usize);
+        if let Some(re) = &self.expect_banner {
+            banner = banner.set_expect(regex(re)?);
+        }
+        Ok(banner)
+    }
+
+    fn dns(&self) -> port_tester::Result<Dns> {
+        let mut dns = Dns::new()
+            .set_name(&self.query_name)
+            .set_record_type(self.query_type.parse::<RecordType>()?);
+        if self.query_tcp {
+            dns = dns.set_transport(Transport::Tcp);
+        }
+        for answer in &self.expect_answer {
+            dns = dns.set_expect(answer);
+        }
+        Ok(dns)
+    }
+
+    fn http(&self, mut http: Http) -> port_tester::Result<Http> {
+        http = http
+            .set_method(&self.method)
+            .set_path(&self.path)
+            .set_status(parse_status_range(&self.expect_status)?);
+        for h in &self.header {
+            if let Some((name, value)) = h.split_once(':') {
+                http = http.set_header(name.trim(), value.trim());
+            }
+        }
+        if let Some(body) = &self.expect_body {
+            http = http.set_body(BodyMatch::Contains(body.clone()));
+        }
+        if let Some(re) = &self.expect_body_regex {
+            http = http.set_body(BodyMatch::Regex(regex(re)?));
+        }
+        if let Some(ms) = self.max_time {
+            http = http.set_max_time(chrono::TimeDelta::milliseconds(ms as i64));
+        }
+        Ok(http)
+    }
+
+    fn script(&self) -> port_tester::Result<Script> {
+        match &self.script {
+            Some(path) => read_file(path)
+                .map(|b| String::from_utf8_lossy(&b).into_owned())?
+                .parse(),
+            None => Ok(Script::new()),
+        }
+    }
+
+    fn udp(&self) -> port_tester::Result<Udp> {
+        let mut udp = Udp::new();
+        if let Some(hex) = &self.payload_hex {
+            udp = udp.set_payload(parse_hex(hex)?);
+        }
+        if let Some(path) = &self.payload_file {
+            udp = udp.set_payload(read_file(path)?);
+        }
+        if let Some(re) = &self.expect_reply {
+            let re = regex::bytes::Regex::new(re).map_err(|e| {
+                Error::new(SourceError::Msg(e.to_string())).set_code(CODE_OPTIONS_ERROR)
+            })?;
+            udp = udp.set_expect(re);
+        }
+        Ok(udp)
+    }
+}
+
+/// Read a file named in the arguments.
+pub fn read_file(path: &std::path::Path) -> port_tester::Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| {
+        Error::new(SourceError::Io(e))
+            .set_context(&format!("Failed to read file: {}", path.display()))
+            .set_code(CODE_OPTIONS_ERROR)
+    })
+}
+
+/// Compile a regular expression from the arguments.
+fn regex(re: &str) -> port_tester::Result<Regex> {
+    Regex::new(re)
+        .map_err(|e| Error::new(SourceError::Msg(e.to_string())).set_code(CODE_OPTIONS_ERROR))
+}
+
+fn validate_hex(hex: &str) -> Result<String, String> {
+    parse_hex(hex)
+        .map(|_| hex.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_bytes_regex(re: &str) -> Result<String, String> {
+    regex::bytes::Regex::new(re)
+        .map(|_| re.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_header(header: &str) -> Result<String, String> {
+    match header.split_once(':') {
+        Some((name, _)) if !name.trim().is_empty() && !name.contains(char::is_whitespace) => {
+            Ok(header.to_string())
+        }
+        _ => Err(String::from("Header must be in the form 'Name: value'")),
+    }
+}
+
+fn validate_record_type(record_type: &str) -> Result<String, String> {
+    record_type
+        .parse::<RecordType>()
+        .map(|_| record_type.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_regex(re: &str) -> Result<String, String> {
+    Regex::new(re)
+        .map(|_| re.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_status(status: &str) -> Result<String, String> {
+    parse_status_range(status)
+        .map(|_| status.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    /// Parses the probe arguments on their own, as both binaries flatten them.
+    #[derive(Debug, Parser)]
+    struct Test {
+        #[command(flatten)]
+        probe_args: ProbeArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<ProbeArgs, clap::Error> {
+        Test::try_parse_from(std::iter::once("probe").chain(args.iter().copied()))
+            .map(|t| t.probe_args)
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.probe(), port_open::NAME);
+        assert_eq!(args.query_name, dns::DEFAULT_QUERY_NAME);
+        assert_eq!(args.query_type, "NS");
+        assert_eq!(args.expect_status, DEFAULT_EXPECT_STATUS);
+    }
+
+    #[test]
+    fn test_validate_header() {
+        assert!(validate_header("Accept: text/html").is_ok());
+        assert!(validate_header("X-Empty:").is_ok());
+        assert!(validate_header("Accept text/html").is_err());
+        assert!(validate_header(": value").is_err());
+        assert!(validate_header("Bad Name: value").is_err());
+    }
+
+    #[test]
+    fn test_validate_status() {
+        assert!(validate_status("200").is_ok());
+        assert!(validate_status("200-299").is_ok());
+        assert!(validate_status("2xx").is_ok());
+        assert!(validate_status("abc").is_err());
+        assert!(validate_regex("^ok$").is_ok());
+        assert!(validate_regex("(").is_err());
+    }
+
+    #[test]
+    fn test_registry() {
+        let args = parse(&[
+            "--probe",
+            "http",
+            "--path",
+            "/healthz",
+            "-H",
+            "Accept: application/json",
+            "--expect-status",
+            "2xx",
+            "--expect-body-regex",
+            "up|ok",
+        ])
+        .unwrap();
+        assert_eq!(args.header, vec!["Accept: application/json".to_string()]);
+        let r = args.registry().unwrap();
+        assert!(r.get(port_tester::connectors::http::NAME).is_some());
+
+        // Only one body expectation may be given.
+        assert!(parse(&["--expect-body", "ok", "--expect-body-regex", "ok"]).is_err());
+    }
+
+    #[test]
+    fn test_banner() {
+        let args = parse(&[
+            "--probe",
+            "banner",
+            "--banner-bytes",
+            "64",
+            "--expect-banner",
+            "^SSH-2\\.0-",
+        ])
+        .unwrap();
+        assert_eq!(args.banner_bytes, 64);
+        assert!(args.registry().unwrap().get(banner::NAME).is_some());
+
+        assert!(parse(&["--banner-bytes", "0"]).is_err());
+        assert!(parse(&["--expect-banner", "("]).is_err());
+    }
+
+    #[test]
+    fn test_max_time() {
+        assert_eq!(parse(&["--max-time", "500"]).unwrap().max_time, Some(500));
+        assert!(parse(&["--max-time", "0"]).is_err());
+        assert!(parse(&["--max-time=-5"]).is_err());
+    }
+
+    #[test]
+    fn test_dns() {
+        let args = parse(&[
+            "--probe",
+            "dns",
+            "--query-name",
+            "example.com",
+            "--query-type",
+            "aaaa",
+            "--query-tcp",
+            "--expect-answer",
+            "2001:db8::1",
+            "--expect-answer",
+            "2001:db8::2",
+        ])
+        .unwrap();
+        assert_eq!(args.expect_answer.len(), 2);
+        assert!(args.registry().unwrap().get(dns::NAME).is_some());
+
+        assert!(parse(&["--query-type", "ANY"]).is_err());
+    }
+
+    #[test]
+    fn test_script() {
+        let path = std::env::temp_dir().join(format!("probe-test-script-{}", std::process::id()));
+        std::fs::write(&path, "expect ^220\nsend QUIT\\r\\n\nclose\n").unwrap();
+        let args = parse(&["--probe", "script", "--script", path.to_str().unwrap()]).unwrap();
+        let r = args.registry();
+        std::fs::write(&path, "bogus\n").unwrap();
+        let bad = args.registry();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(
+            r.unwrap()
+                .get(port_tester::connectors::script::NAME)
+                .is_some()
+        );
+        assert_eq!(bad.err().unwrap().code(), Some(CODE_OPTIONS_ERROR));
+    }
+
+    #[test]
+    fn test_udp() {
+        let args = parse(&["--udp", "--payload-hex", "0d0a", "--expect-reply", "^ok"]).unwrap();
+        assert_eq!(args.probe(), udp::NAME);
+        assert!(args.registry().unwrap().get(udp::NAME).is_some());
+        assert_eq!(parse(&["--probe", "udp"]).unwrap().probe(), udp::NAME);
+
+        // --udp selects the probe, so it may not be combined with --probe.
+        assert!(parse(&["--udp", "--probe", "tls"]).is_err());
+        assert!(parse(&["--payload-hex", "0"]).is_err());
+        assert!(parse(&["--payload-hex", "00", "--payload-file", "payload.bin"]).is_err());
+
+        let args = parse(&["--udp", "--payload-file", "/nonexistent/payload.bin"]).unwrap();
+        assert_eq!(
+            args.registry().err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
+        );
+    }
+}
@@ -1,8 +1,13 @@
 use hostname_validator::is_valid as is_valid_hostname;
 use log::debug;
 use port_tester::Verbosity;
+use port_tester::connectors::Registry;
+use port_tester::core::host::AddrFamily;
+use port_tester::core::resolver::{self, Override, Resolver};
+use port_tester::core::scan::{DEFAULT_CONCURRENCY, Scan, parse_ports};
 use std::net::IpAddr;
 
+use crate::probe_args::ProbeArgs;
 use clap::{ArgAction, CommandFactory, Parser, value_parser};
 
 const DEFAULT_PORT: u16 = 443;
@@ -35,16 +40,36 @@ pub struct Args {
     /// Target host to connect to.
     #[arg(value_parser = validate_host)]
     pub host: String,
-    /// Port number to connect to.
-    #[arg(value_parser = value_parser!(u16).range(1..), default_value_t = DEFAULT_PORT)]
-    pub port: u16,
+    /// Ports to connect to as a port, list or range like 22,80,443 or 8000-8100.
+    #[arg(value_parser = validate_ports, default_value_t = DEFAULT_PORT.to_string())]
+    pub port: String,
 
     // Options
+    /// Maximum number of ports to attempt at the same time.
+    #[arg(long, value_parser = value_parser!(u32).range(1..), default_value_t = DEFAULT_CONCURRENCY as u32)]
+    pub concurrency: u32,
+    /// Resolve the host by querying this DNS server, an IP address with an optional port, instead
+    /// of the system resolver.
+    #[arg(long, value_parser = validate_dns_server)]
+    pub dns_server: Option<String>,
+    /// Resolve the targets to IPv4 addresses only.
+    #[arg(short = '4', long, conflicts_with = "ipv6", default_value_t = false)]
+    pub ipv4: bool,
+    /// Resolve the targets to IPv6 addresses only.
+    #[arg(short = '6', long, default_value_t = false)]
+    pub ipv6: bool,
     /// Quiet mode.
     /// Suppress per-attempt output and attempt errors only showing sequence numbers and each result
     /// as 'ok' or 'fail'.
     #[arg(short, long, group = "verbosity", default_value_t = false)]
     pub quiet: bool,
+    /// Maximum number of port attempts to start per second. 0 for no limit.
+    #[arg(long, default_value_t = 0)]
+    pub rate: u32,
+    /// Resolve HOST on PORT to the given addresses instead of looking it up, like curl's
+    /// --resolve. Format is HOST:PORT:ADDR[,ADDR...]. May be repeated.
+    #[arg(long, value_name = "HOST:PORT:ADDR", value_parser = validate_resolve)]
+    pub resolve: Vec<String>,
     /// Silent mode.
     /// Suppress output except for errors and final report.
     #[arg(short, long, group = "verbosity", default_value_t = false)]
@@ -59,6 +84,10 @@ pub struct Args {
     /// 3 = trace
     #[arg(short, long, group = "verbosity", action = ArgAction::Count, default_value_t = 0)]
     pub verbose: u8,
+
+    // Probe Options
+    #[command(flatten)]
+    pub probe_args: ProbeArgs,
 }
 
 /*
@@ -103,6 +132,52 @@ impl Cli {
         c
     }
 
+    /// Returns the ports to connect to, in the order given.
+    pub fn ports(&self) -> port_tester::Result<Vec<u16>> {
+        parse_ports(&self.args.port)
+    }
+
+    /// Returns the [`Scan`] used to run the attempt against each port.
+    pub fn scan(&self) -> Scan {
+        Scan::new()
+            .set_concurrency(self.args.concurrency as usize)
+            .set_rate(self.args.rate)
+    }
+
+    /// Build the probe registry, configuring the built-in probes from the arguments.
+    pub fn registry(&self) -> port_tester::Result<Registry> {
+        self.args.probe_args.registry()
+    }
+
+    /// Returns the [`AddrFamily`] to resolve the targets to.
+    pub fn family(&self) -> AddrFamily {
+        match (self.args.ipv4, self.args.ipv6) {
+            (true, _) => AddrFamily::Ipv4,
+            (_, true) => AddrFamily::Ipv6,
+            _ => AddrFamily::Any,
+        }
+    }
+
+    /// Returns the [`Resolver`] used to resolve the host, with the address family, overrides and
+    /// DNS server given.
+    pub fn resolver(&self) -> port_tester::Result<Resolver> {
+        let mut r = Resolver::new()
+            .set_family(self.family())
+            .set_timeout(self.args.timeout);
+        for o in &self.args.resolve {
+            r = r.set_override(o.parse::<Override>()?);
+        }
+        if let Some(server) = &self.args.dns_server {
+            r = r.set_server(resolver::parse_server(server)?);
+        }
+        Ok(r)
+    }
+
+    /// Returns the name of the probe to run.
+    pub fn probe(&self) -> &str {
+        self.args.probe_args.probe()
+    }
+
     pub fn print_help() {
         let _ = Args::command().print_help();
     }
@@ -120,6 +195,12 @@ impl Cli {
     }
 }
 
+fn validate_ports(ports: &str) -> Result<String, String> {
+    parse_ports(ports)
+        .map(|_| ports.to_string())
+        .map_err(|e| e.to_string())
+}
+
 fn validate_host(host: &str) -> Result<String, String> {
     if host.trim().is_empty() {
         Err(String::from("Host cannot be empty"))
@@ -130,9 +211,23 @@ fn validate_host(host: &str) -> Result<String, String> {
     }
 }
 
+fn validate_dns_server(server: &str) -> Result<String, String> {
+    resolver::parse_server(server)
+        .map(|_| server.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_resolve(resolve: &str) -> Result<String, String> {
+    resolve
+        .parse::<Override>()
+        .map(|_| resolve.to_string())
+        .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use port_tester::connectors::port_open;
 
     #[test]
     fn test_count_true() {
@@ -146,6 +241,57 @@ mod test {
         assert_eq!(c, 2);
     }
 
+    #[test]
+    fn test_ports() {
+        let cli = Cli::new(Args::try_parse_from(vec!["poke", "1.1.1.1"]).unwrap());
+        assert_eq!(cli.ports().unwrap(), vec![DEFAULT_PORT]);
+        assert_eq!(cli.args.concurrency, DEFAULT_CONCURRENCY as u32);
+
+        let args = Args::try_parse_from(vec![
+            "poke",
+            "--concurrency",
+            "8",
+            "--rate",
+            "100",
+            "1.1.1.1",
+            "22,80,8000-8002",
+        ])
+        .unwrap();
+        let cli = Cli::new(args);
+        assert_eq!(cli.ports().unwrap(), vec![22, 80, 8000, 8001, 8002]);
+        let _ = cli.scan();
+
+        assert!(Args::try_parse_from(vec!["poke", "1.1.1.1", "0"]).is_err());
+        assert!(Args::try_parse_from(vec!["poke", "1.1.1.1", "443-80"]).is_err());
+        assert!(Args::try_parse_from(vec!["poke", "--concurrency", "0", "1.1.1.1"]).is_err());
+    }
+
+    #[test]
+    fn test_family() {
+        let cli = Cli::new(Args::try_parse_from(vec!["poke", "example.com"]).unwrap());
+        assert_eq!(cli.family(), AddrFamily::Any);
+        let cli = Cli::new(Args::try_parse_from(vec!["poke", "-6", "example.com"]).unwrap());
+        assert_eq!(cli.family(), AddrFamily::Ipv6);
+        assert!(Args::try_parse_from(vec!["poke", "-4", "-6", "example.com"]).is_err());
+    }
+
+    #[test]
+    fn test_resolver() {
+        let args = Args::try_parse_from(vec![
+            "poke",
+            "--resolve",
+            "example.com:22:127.0.0.1",
+            "--dns-server",
+            "[::1]:5353",
+            "example.com",
+        ])
+        .unwrap();
+        let r = Cli::new(args).resolver().unwrap();
+        assert_eq!(r.overrides().len(), 1);
+        assert_eq!(r.server(), Some("[::1]:5353".parse().unwrap()));
+        assert!(Args::try_parse_from(vec!["poke", "--resolve", "x:y:z", "example.com"]).is_err());
+    }
+
     #[test]
     fn test_validate_host() {
         let empty = "";
@@ -165,6 +311,7 @@ mod test {
         assert!(args.is_ok());
         let mut cli = Cli::new(args.unwrap());
         assert_eq!(cli.args.host, "1.1.1.1".to_string());
+        assert_eq!(cli.args.probe_args.probe, port_open::NAME.to_string());
 
         args = Args::try_parse_from(vec!["pt", "1.1.1.1", "--silent"]);
         assert!(args.is_ok());
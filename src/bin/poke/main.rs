@@ -1,12 +1,16 @@
 use cli::{Args, Cli};
-use port_tester::connectors::port_open::*;
+use port_tester::connectors::Connector;
 use port_tester::core::error::*;
+use port_tester::core::scan::PortState;
 use port_tester::{Host, Verbosity};
 
 use env_logger::Env;
 use log::{debug, info};
+use std::sync::Mutex;
 
 mod cli;
+#[path = "../common/probe_args.rs"]
+mod probe_args;
 
 const DEFAULT_LOG_LEVEL: &str = "error";
 
@@ -26,7 +30,25 @@ fn main() {
         std::process::exit(1);
     }
 
-    let mut host = match Host::new(&cli.args.host, cli.args.port) {
+    let registry = match cli.registry() {
+        Ok(r) => r,
+        Err(e) => exit_handler(&e),
+    };
+    let connector = match registry.select(cli.probe()) {
+        Ok(c) => c,
+        Err(e) => exit_handler(&e),
+    };
+    info!("probe: {}", connector.name());
+
+    let ports = match cli.ports() {
+        Ok(p) => p,
+        Err(e) => exit_handler(&e.print_help()),
+    };
+    let resolver = match cli.resolver() {
+        Ok(r) => r,
+        Err(e) => exit_handler(&e),
+    };
+    let mut host = match Host::with_resolver(&cli.args.host, ports[0], resolver) {
         Ok(h) => h,
         Err(e) => exit_handler(&e),
     };
@@ -38,6 +60,14 @@ fn main() {
     })
     .expect("Error setting Ctrl-C handler");
 
+    if ports.len() > 1 {
+        let hosts: Vec<Mutex<Host>> = ports
+            .iter()
+            .map(|p| Mutex::new(host.for_port(*p)))
+            .collect();
+        scan(&cli, verbose, connector, &hosts);
+    }
+
     debug!(
         "attempt: {}, ip: {}, port: {}, timeout: {}",
         1,
@@ -47,7 +77,7 @@ fn main() {
     );
 
     // Connect to the target and record metrics.
-    connect(1, &mut host, cli.args.timeout);
+    connector.connect(1, &mut host, cli.args.timeout);
 
     let status = host.metrics().result(1).unwrap().status();
     if !cli.args.silent {
@@ -61,6 +91,56 @@ fn main() {
     }
 }
 
+/// Attempt each port once, print a table of port states and exit with 1 if any port was not open.
+fn scan(cli: &Cli, verbose: &Verbosity, connector: &dyn Connector, hosts: &[Mutex<Host>]) -> ! {
+    cli.scan().attempt(connector, 1, hosts, cli.args.timeout);
+
+    let mut counts = [0; 4];
+    let mut lines = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        let h = host.lock().unwrap();
+        let mr = h.metrics().result(1).unwrap();
+        let state = PortState::from(mr);
+        match state {
+            PortState::Open => counts[0] += 1,
+            PortState::Closed => counts[1] += 1,
+            PortState::Filtered => counts[2] += 1,
+            _ => counts[3] += 1,
+        }
+        lines.push(match verbose {
+            Verbosity::Verbose(_) if mr.is_err() => format!(
+                "{:<5}  {:<8}  {}",
+                h.port(),
+                state,
+                mr.status().to_string_with_verbosity(verbose)
+            ),
+            _ => format!("{:<5}  {}", h.port(), state),
+        });
+    }
+
+    if !cli.args.silent {
+        for line in lines {
+            println!("{}", line);
+        }
+        let mut summary = format!(
+            "{} ports: {} open, {} closed, {} filtered",
+            hosts.len(),
+            counts[0],
+            counts[1],
+            counts[2]
+        );
+        if counts[3] > 0 {
+            summary.push_str(&format!(", {} unknown", counts[3]));
+        }
+        println!("{}", summary);
+    }
+
+    match counts[0] == hosts.len() {
+        true => std::process::exit(0),
+        false => std::process::exit(1),
+    }
+}
+
 fn setup_logger(level: &Option<Verbosity>) {
     // Prioritize log levels: cli flag > env var > default
     let env = Env::default()
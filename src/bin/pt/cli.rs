@@ -1,14 +1,26 @@
 use hostname_validator::is_valid as is_valid_hostname;
 use log::debug;
-use port_tester::Verbosity;
-use std::net::IpAddr;
+use port_tester::connectors::Registry;
+use port_tester::core::host::{AddrFamily, AddrStrategy, ResolveEvery};
+use port_tester::core::metrics::parse_buckets;
+use port_tester::core::outage::DEFAULT_OUTAGE_THRESHOLD;
+use port_tester::core::resolver::{self, Override, Resolver, is_srv_name};
+use port_tester::core::scan::{DEFAULT_CONCURRENCY, Scan, is_port_spec, parse_ports};
+use port_tester::{CODE_OPTIONS_ERROR, Error, SourceError, Verbosity};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 
+use crate::probe_args::{ProbeArgs, read_file};
 use clap::{ArgAction, CommandFactory, Parser, value_parser};
 
 const DEFAULT_COUNT: u32 = 0;
+#[cfg(feature = "tls")]
+const DEFAULT_CRIT_DAYS: u32 = 7;
 const DEFAULT_INTERVAL: u64 = 1;
 const DEFAULT_PORT: u16 = 443;
 const DEFAULT_TIMEOUT: u64 = 5;
+#[cfg(feature = "tls")]
+const DEFAULT_WARN_DAYS: u32 = 30;
 
 #[macro_export]
 macro_rules! count_true_u8 {
@@ -34,38 +46,107 @@ fn _count_true(vec: std::vec::Vec<bool>) -> usize {
 #[command(version, about, long_about = None)]
 pub struct Args {
     // Positional Arguments
-    /// Target host to connect to.
-    #[arg(value_parser = validate_host)]
-    pub host: String,
-    /// Port number to connect to.
-    #[arg(value_parser = value_parser!(u16).range(1..), default_value_t = DEFAULT_PORT)]
-    pub port: u16,
+    /// Targets to connect to as HOST, HOST:PORT or [IPv6]:PORT. PORT may be a list or range like
+    /// 22,80,443 or 8000-8100. A PORT given after a HOST applies to that HOST. Targets without a
+    /// port use --port. An SRV name like _ldap._tcp.example.com expands to the hosts and ports of
+    /// its SRV records.
+    #[arg(value_name = "TARGET", required_unless_present = "targets_file")]
+    pub targets: Vec<String>,
 
     // Options
+    /// How to use the addresses a host resolves to: sequential (try each in order until one
+    /// passes), round-robin (the next address on each attempt), all (every address on each
+    /// attempt), random or happy-eyeballs (race IPv6 and IPv4 with a 250ms stagger).
+    #[arg(long, value_parser = validate_addr_strategy, default_value_t = AddrStrategy::default().to_string())]
+    pub addr_strategy: String,
+    /// Certificate expiry mode.
+    /// Perform a single TLS handshake, report the leaf certificate and exit with 0 (ok),
+    /// 4 (warning), 5 (critical) or 6 (expired).
+    #[cfg(feature = "tls")]
+    #[arg(long, conflicts_with_all = ["count", "interval", "probe", "report_interval", "udp"], default_value_t = false)]
+    pub cert_expiry: bool,
+    /// Maximum number of targets to attempt at the same time.
+    #[arg(long, value_parser = value_parser!(u32).range(1..), default_value_t = DEFAULT_CONCURRENCY as u32)]
+    pub concurrency: u32,
     /// Count of connection attempts to perform.
     /// 0 for infinite.
     #[arg(short, long, default_value_t = DEFAULT_COUNT)]
     pub count: u32,
+    /// Days before certificate expiry to report critical. Requires --cert-expiry.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "cert_expiry", default_value_t = DEFAULT_CRIT_DAYS)]
+    pub crit_days: u32,
+    /// Resolve the targets by querying this DNS server, an IP address with an optional port,
+    /// instead of the system resolver.
+    #[arg(long, value_parser = validate_dns_server)]
+    pub dns_server: Option<String>,
+    /// Upper edges of the latency histogram buckets in milliseconds, like 1,5,10,50. The histogram
+    /// is shown in the final report of a single target and in --json output.
+    #[arg(long, value_parser = validate_buckets)]
+    pub histogram_buckets: Option<String>,
     /// Interval between attempts in seconds.
     #[arg(short, long, default_value_t = DEFAULT_INTERVAL)]
     pub interval: u64,
-    /// Produce all output in JSON on exit. Output is held until all tests are complete.
-    #[arg(long, conflicts_with_all = ["verbosity", "report_interval"], default_value_t = false)]
+    /// Resolve the targets to IPv4 addresses only.
+    #[arg(short = '4', long, conflicts_with = "ipv6", default_value_t = false)]
+    pub ipv4: bool,
+    /// Resolve the targets to IPv6 addresses only.
+    #[arg(short = '6', long, default_value_t = false)]
+    pub ipv6: bool,
+    /// Produce all output in JSON on exit. Output is held until all tests are complete. Same as
+    /// '--output json'.
+    #[arg(long, conflicts_with_all = ["verbosity", "report_interval", "output"], default_value_t = false)]
     pub json: bool,
+    /// Number of consecutive failed attempts that count as an outage.
+    #[arg(long, value_parser = value_parser!(u32).range(1..), default_value_t = DEFAULT_OUTAGE_THRESHOLD)]
+    pub outage_threshold: u32,
+    /// Output format: text, json (all output on exit, like --json), ndjson (a JSON object per
+    /// line for each attempt as it completes, a summary every --report-interval attempts and a
+    /// final summary), csv or tsv (a header row and a row for each attempt as it completes).
+    #[arg(long, value_parser = validate_output, default_value_t = Output::default().to_string())]
+    pub output: String,
+    /// Ports to connect to for targets given without one, as a port, list or range like 22,80,443
+    /// or 8000-8100.
+    #[arg(short, long, value_parser = validate_ports, default_value_t = DEFAULT_PORT.to_string())]
+    pub port: String,
     /// Quiet mode.
     /// Suppress per-attempt output and attempt errors only showing sequence numbers and each result
     /// as 'ok' or 'fail'.
     #[arg(short, long, group = "verbosity", default_value_t = false)]
     pub quiet: bool,
+    /// Maximum number of target attempts to start per second. 0 for no limit.
+    #[arg(long, default_value_t = 0)]
+    pub rate: u32,
     /// Interval to output intermediate reports.
     /// Default is 0 (no intermediate reports).
     /// If set to N, a report will be printed every N attempts.
     #[arg(short, long, default_value_t = 0)]
     pub report_interval: u32,
+    /// Resolve HOST on PORT to the given addresses instead of looking it up, like curl's
+    /// --resolve. Format is HOST:PORT:ADDR[,ADDR...]. May be repeated.
+    #[arg(long, value_name = "HOST:PORT:ADDR", value_parser = validate_resolve)]
+    pub resolve: Vec<String>,
+    /// Resolve the targets again every N attempts or after a duration like 30s, 5m or 1h, and
+    /// report when their addresses change.
+    #[arg(long, value_parser = validate_resolve_every)]
+    pub resolve_every: Option<String>,
+    /// Serve the metrics of the targets in the Prometheus text format on http://ADDR/metrics
+    /// while probing, like 127.0.0.1:9100.
+    #[arg(long, value_name = "ADDR", value_parser = validate_serve_metrics)]
+    pub serve_metrics: Option<String>,
     /// Silent mode.
     /// Suppress output except for errors and final report.
     #[arg(short, long, group = "verbosity", default_value_t = false)]
     pub silent: bool,
+    /// Read targets from a file in addition to any given as arguments. Targets are separated by
+    /// whitespace or new lines and '#' starts a comment.
+    #[arg(long)]
+    pub targets_file: Option<PathBuf>,
+    /// Write the metrics of the targets in the Prometheus text format to PATH for the node
+    /// exporter's textfile collector. The file is replaced after every attempt, or every
+    /// --report-interval attempts if set.
+    #[arg(long, value_name = "PATH")]
+    pub textfile: Option<PathBuf>,
     /// Connection attempt timeout in seconds.
     #[arg(short, long, default_value_t = DEFAULT_TIMEOUT)]
     pub timeout: u64,
@@ -76,6 +157,14 @@ pub struct Args {
     /// 3 = trace
     #[arg(short, long, group = "verbosity", action = ArgAction::Count, default_value_t = 0)]
     pub verbose: u8,
+    /// Days before certificate expiry to report a warning. Requires --cert-expiry.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "cert_expiry", default_value_t = DEFAULT_WARN_DAYS)]
+    pub warn_days: u32,
+
+    // Probe Options
+    #[command(flatten)]
+    pub probe_args: ProbeArgs,
 }
 
 /*
@@ -92,10 +181,73 @@ impl Args {
     }
 }
 
+/// Format of pt's output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Output {
+    /// Human readable lines and reports.
+    #[default]
+    Text,
+    /// A single JSON document of every target on exit.
+    Json,
+    /// A JSON object per line for each attempt as it completes, and for each summary.
+    Ndjson,
+    /// A header row and a comma separated row for each attempt as it completes.
+    Csv,
+    /// A header row and a tab separated row for each attempt as it completes.
+    Tsv,
+}
+
+impl std::fmt::Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Output {
+    type Err = Error;
+
+    fn from_str(s: &str) -> port_tester::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Output::Text),
+            "json" => Ok(Output::Json),
+            "ndjson" => Ok(Output::Ndjson),
+            "csv" => Ok(Output::Csv),
+            "tsv" => Ok(Output::Tsv),
+            _ => Err(Error::new(SourceError::Msg(format!(
+                "Unsupported output: {} (supported: text, json, ndjson, csv, tsv)",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)),
+        }
+    }
+}
+
+impl Output {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Output::Text => "text",
+            Output::Json => "json",
+            Output::Ndjson => "ndjson",
+            Output::Csv => "csv",
+            Output::Tsv => "tsv",
+        }
+    }
+
+    /// Returns the field delimiter of the delimited text formats, or `None` for the others.
+    pub fn delimiter(&self) -> Option<char> {
+        match self {
+            Output::Csv => Some(','),
+            Output::Tsv => Some('\t'),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Cli {
     pub args: Args,
     pub verbose: Option<Verbosity>,
+    pub output: Output,
 }
 
 impl Cli {
@@ -104,6 +256,7 @@ impl Cli {
         let mut c = Cli {
             args,
             verbose: None,
+            output: Output::default(),
         };
 
         let is_verbose = !matches!(&c.args.verbose, 0);
@@ -115,6 +268,13 @@ impl Cli {
             std::process::exit(3);
         }
 
+        #[cfg(feature = "tls")]
+        if c.args.cert_expiry && c.args.crit_days > c.args.warn_days {
+            eprintln!("--crit-days may not be greater than --warn-days");
+            let _ = Args::command().print_help();
+            std::process::exit(3);
+        }
+
         /*
         // Return an error of blob_report was called without a count. Required a count to reduce
         // the chance of filling up memory.
@@ -127,13 +287,118 @@ impl Cli {
 
         // Set verbosity so we know how much to print.
         c.verbose = c.verbosity();
+        c.output = c.output_format();
+
+        // JSON output is held until exit, so intermediate reports can't be given.
+        if c.output == Output::Json && c.args.report_interval > 0 {
+            eprintln!("--report-interval may not be used with JSON output, use '--output ndjson'");
+            let _ = Args::command().print_help();
+            std::process::exit(3);
+        }
         c
     }
 
+    /// Returns the targets to connect to as host and port pairs, in the order given. Targets from
+    /// --targets-file follow those given as arguments. SRV names are returned once with port 0,
+    /// as their ports come from their records.
+    pub fn targets(&self) -> port_tester::Result<Vec<(String, u16)>> {
+        let mut args = self.args.targets.clone();
+        if let Some(path) = &self.args.targets_file {
+            let text = String::from_utf8_lossy(&read_file(path)?).into_owned();
+            for line in text.lines() {
+                let line = line.split('#').next().unwrap_or_default();
+                args.extend(line.split_whitespace().map(str::to_string));
+            }
+        }
+        parse_targets(&args, &parse_ports(&self.args.port)?)
+    }
+
+    /// Returns the [`AddrStrategy`] used to pick each target's addresses for each attempt.
+    pub fn addr_strategy(&self) -> port_tester::Result<AddrStrategy> {
+        self.args.addr_strategy.parse()
+    }
+
+    /// Returns the latency histogram bucket edges, if given.
+    pub fn buckets(&self) -> port_tester::Result<Option<Vec<chrono::TimeDelta>>> {
+        self.args
+            .histogram_buckets
+            .as_deref()
+            .map(parse_buckets)
+            .transpose()
+    }
+
+    /// Returns when to resolve the targets again, if given.
+    pub fn resolve_every(&self) -> port_tester::Result<Option<ResolveEvery>> {
+        self.args
+            .resolve_every
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+    }
+
+    /// Returns the address to serve the metrics on, if given.
+    pub fn serve_metrics(&self) -> port_tester::Result<Option<SocketAddr>> {
+        self.args
+            .serve_metrics
+            .as_deref()
+            .map(parse_socket_addr)
+            .transpose()
+    }
+
+    /// Returns the [`Scan`] used to run each attempt against the targets.
+    pub fn scan(&self) -> Scan {
+        Scan::new()
+            .set_concurrency(self.args.concurrency as usize)
+            .set_rate(self.args.rate)
+    }
+
+    /// Build the probe registry, configuring the built-in probes from the arguments.
+    pub fn registry(&self) -> port_tester::Result<Registry> {
+        self.args.probe_args.registry()
+    }
+
+    /// Returns the [`AddrFamily`] to resolve the targets to.
+    pub fn family(&self) -> AddrFamily {
+        match (self.args.ipv4, self.args.ipv6) {
+            (true, _) => AddrFamily::Ipv4,
+            (_, true) => AddrFamily::Ipv6,
+            _ => AddrFamily::Any,
+        }
+    }
+
+    /// Returns the [`Resolver`] used to resolve the targets, with the address family, overrides
+    /// and DNS server given.
+    pub fn resolver(&self) -> port_tester::Result<Resolver> {
+        let mut r = Resolver::new()
+            .set_family(self.family())
+            .set_timeout(self.args.timeout);
+        for o in &self.args.resolve {
+            r = r.set_override(o.parse::<Override>()?);
+        }
+        if let Some(server) = &self.args.dns_server {
+            r = r.set_server(resolver::parse_server(server)?);
+        }
+        Ok(r)
+    }
+
+    /// Returns the name of the probe to run.
+    pub fn probe(&self) -> &str {
+        self.args.probe_args.probe()
+    }
+
     pub fn print_help() {
         let _ = Args::command().print_help();
     }
 
+    /// Returns the [`Output`] format, which is JSON when --json is given.
+    pub fn output_format(&self) -> Output {
+        match self.args.json {
+            true => Output::Json,
+            // Validated when parsing the arguments.
+            false => self.args.output.parse().unwrap_or_default(),
+        }
+    }
+
     pub fn verbosity(&self) -> Option<Verbosity> {
         if self.args.quiet {
             Some(Verbosity::Quiet)
@@ -147,19 +412,161 @@ impl Cli {
     }
 }
 
+/// Parse an address to listen on from the arguments.
+fn parse_socket_addr(addr: &str) -> port_tester::Result<SocketAddr> {
+    addr.parse().map_err(|_| {
+        Error::new(SourceError::Msg(format!(
+            "Invalid address: {} (expected IP:PORT)",
+            addr
+        )))
+        .set_code(CODE_OPTIONS_ERROR)
+    })
+}
+
+fn validate_serve_metrics(addr: &str) -> Result<String, String> {
+    parse_socket_addr(addr)
+        .map(|_| addr.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_output(output: &str) -> Result<String, String> {
+    output
+        .parse::<Output>()
+        .map(|_| output.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_buckets(buckets: &str) -> Result<String, String> {
+    parse_buckets(buckets)
+        .map(|_| buckets.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_ports(ports: &str) -> Result<String, String> {
+    parse_ports(ports)
+        .map(|_| ports.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Parse target arguments into host and port pairs. A port, port list or port range on its own
+/// applies to the preceding host if that host was given without one.
+fn parse_targets(
+    args: &[String],
+    default_ports: &[u16],
+) -> port_tester::Result<Vec<(String, u16)>> {
+    let err = |msg: String| Error::new(SourceError::Msg(msg)).set_code(CODE_OPTIONS_ERROR);
+    let mut targets: Vec<(String, Option<Vec<u16>>)> = Vec::new();
+
+    for arg in args {
+        if is_port_spec(arg) {
+            match targets.last_mut() {
+                Some((host, None)) if is_srv_name(host) => {
+                    return Err(err(format!(
+                        "Port {} given for SRV name {}, which takes its ports from its records",
+                        arg, host
+                    )));
+                }
+                Some((_, p @ None)) => *p = Some(parse_ports(arg)?),
+                _ => return Err(err(format!("Port {} given without a host", arg))),
+            }
+            continue;
+        }
+        targets.push(split_target(arg).map_err(|e| err(format!("Invalid target {}: {}", arg, e)))?);
+    }
+
+    if targets.is_empty() {
+        return Err(err("No targets given".to_string()));
+    }
+    Ok(targets
+        .into_iter()
+        .flat_map(|(host, ports)| {
+            let default_ports = match is_srv_name(&host) {
+                true => vec![0],
+                false => default_ports.to_vec(),
+            };
+            ports
+                .unwrap_or(default_ports)
+                .into_iter()
+                .map(move |p| (host.clone(), p))
+        })
+        .collect())
+}
+
+/// Split a single target into its host and optional ports.
+fn split_target(target: &str) -> Result<(String, Option<Vec<u16>>), String> {
+    let (host, ports) = if let Some(rest) = target.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((ip, "")) => (ip, None),
+            Some((ip, ports)) => match ports.strip_prefix(':') {
+                Some(ports) => (ip, Some(ports)),
+                None => return Err(String::from("Expected :PORT after ]")),
+            },
+            None => return Err(String::from("Missing ]")),
+        }
+    } else if target.parse::<IpAddr>().is_ok() {
+        (target, None)
+    } else if is_srv_name(target) {
+        if target.contains(':') {
+            return Err(String::from(
+                "SRV names take their ports from their records",
+            ));
+        }
+        (target, None)
+    } else {
+        match target.rsplit_once(':') {
+            Some((host, ports)) => (host, Some(ports)),
+            None => (target, None),
+        }
+    };
+
+    let ports = match ports {
+        Some(p) => Some(parse_ports(p).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    Ok((validate_host(host)?, ports))
+}
+
 fn validate_host(host: &str) -> Result<String, String> {
     if host.trim().is_empty() {
         Err(String::from("Host cannot be empty"))
-    } else if host.parse::<IpAddr>().is_ok() || is_valid_hostname(host) {
+    } else if host.parse::<IpAddr>().is_ok() || is_valid_hostname(host) || is_srv_name(host) {
         Ok(host.to_string())
     } else {
         Err(String::from("Invalid host format"))
     }
 }
 
+fn validate_addr_strategy(strategy: &str) -> Result<String, String> {
+    strategy
+        .parse::<AddrStrategy>()
+        .map(|_| strategy.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_dns_server(server: &str) -> Result<String, String> {
+    resolver::parse_server(server)
+        .map(|_| server.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_resolve(resolve: &str) -> Result<String, String> {
+    resolve
+        .parse::<Override>()
+        .map(|_| resolve.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn validate_resolve_every(every: &str) -> Result<String, String> {
+    every
+        .parse::<ResolveEvery>()
+        .map(|_| every.to_string())
+        .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use port_tester::connectors::port_open;
 
     #[test]
     fn test_count_true() {
@@ -173,6 +580,251 @@ mod test {
         assert_eq!(c, 2);
     }
 
+    #[test]
+    fn test_parse_targets() {
+        let t = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            parse_targets(&args, &[443])
+        };
+
+        assert_eq!(
+            t(&["example.com"]).unwrap(),
+            vec![("example.com".to_string(), 443)]
+        );
+        assert_eq!(
+            t(&["example.com", "22"]).unwrap(),
+            vec![("example.com".to_string(), 22)]
+        );
+        assert_eq!(
+            t(&[
+                "a.example.com:80",
+                "1.1.1.1",
+                "53",
+                "::1",
+                "[2001:db8::1]:8443"
+            ])
+            .unwrap(),
+            vec![
+                ("a.example.com".to_string(), 80),
+                ("1.1.1.1".to_string(), 53),
+                ("::1".to_string(), 443),
+                ("2001:db8::1".to_string(), 8443),
+            ]
+        );
+        assert_eq!(t(&["[::1]"]).unwrap(), vec![("::1".to_string(), 443)]);
+
+        // Port lists and ranges expand to a target per port.
+        let ports = |host: &str, ports: &[u16]| -> Vec<(String, u16)> {
+            ports.iter().map(|p| (host.to_string(), *p)).collect()
+        };
+        assert_eq!(
+            t(&["example.com", "22,80,8000-8002"]).unwrap(),
+            ports("example.com", &[22, 80, 8000, 8001, 8002])
+        );
+        assert_eq!(t(&["[::1]:22,80"]).unwrap(), ports("::1", &[22, 80]));
+        assert_eq!(
+            t(&["10.0.0.1:1-3", "10.0.0.2"]).unwrap(),
+            [ports("10.0.0.1", &[1, 2, 3]), ports("10.0.0.2", &[443])].concat()
+        );
+        assert_eq!(
+            parse_targets(&["a.example.com".to_string()], &[22, 80]).unwrap(),
+            ports("a.example.com", &[22, 80])
+        );
+        assert!(t(&["example.com", "80-22"]).is_err());
+        assert!(t(&["example.com:22,http"]).is_err());
+
+        // A port only applies to a preceding host without one.
+        assert!(t(&["example.com:80", "22"]).is_err());
+        assert!(t(&["example.com", "0"]).is_err());
+        assert!(t(&["example.com:0"]).is_err());
+        assert!(t(&["example.com:http"]).is_err());
+        assert!(t(&["[::1]80"]).is_err());
+        assert!(t(&["-a.com"]).is_err());
+        assert_eq!(t(&[]).err().unwrap().code(), Some(CODE_OPTIONS_ERROR));
+
+        // SRV names are given once, without a port.
+        assert_eq!(
+            parse_targets(&["_ldap._tcp.example.com".to_string()], &[22, 80]).unwrap(),
+            vec![("_ldap._tcp.example.com".to_string(), 0)]
+        );
+        assert!(t(&["_ldap._tcp.example.com:389"]).is_err());
+        assert!(t(&["_ldap._tcp.example.com", "389"]).is_err());
+    }
+
+    #[test]
+    fn test_targets_file() {
+        let path = std::env::temp_dir().join(format!("pt-test-targets-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "# dependencies\ndb.example.com:5432\n\ncache.example.com 6379 # redis\n",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(vec![
+            "pt",
+            "--targets-file",
+            path.to_str().unwrap(),
+            "-p",
+            "8080",
+            "api.example.com",
+        ])
+        .unwrap();
+        let targets = Cli::new(args).targets();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            targets.unwrap(),
+            vec![
+                ("api.example.com".to_string(), 8080),
+                ("db.example.com".to_string(), 5432),
+                ("cache.example.com".to_string(), 6379),
+            ]
+        );
+        assert!(Args::try_parse_from(vec!["pt"]).is_err());
+        assert!(Args::try_parse_from(vec!["pt", "-p", "22,0", "1.1.1.1"]).is_err());
+    }
+
+    #[test]
+    fn test_buckets() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "1.1.1.1"]).unwrap());
+        assert_eq!(cli.buckets().unwrap(), None);
+
+        let args =
+            Args::try_parse_from(vec!["pt", "--histogram-buckets", "0.5,10", "1.1.1.1"]).unwrap();
+        assert_eq!(
+            Cli::new(args).buckets().unwrap(),
+            Some(vec![
+                chrono::TimeDelta::microseconds(500),
+                chrono::TimeDelta::try_milliseconds(10).unwrap()
+            ])
+        );
+        assert!(Args::try_parse_from(vec!["pt", "--histogram-buckets", "0", "1.1.1.1"]).is_err());
+    }
+
+    #[test]
+    fn test_addr_strategy() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "1.1.1.1"]).unwrap());
+        assert_eq!(cli.addr_strategy().unwrap(), AddrStrategy::Sequential);
+
+        let args =
+            Args::try_parse_from(vec!["pt", "--addr-strategy", "round-robin", "1.1.1.1"]).unwrap();
+        assert_eq!(
+            Cli::new(args).addr_strategy().unwrap(),
+            AddrStrategy::RoundRobin
+        );
+        assert!(Args::try_parse_from(vec!["pt", "--addr-strategy", "first", "1.1.1.1"]).is_err());
+    }
+
+    #[test]
+    fn test_family() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "example.com"]).unwrap());
+        assert_eq!(cli.family(), AddrFamily::Any);
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "-4", "example.com"]).unwrap());
+        assert_eq!(cli.family(), AddrFamily::Ipv4);
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "--ipv6", "example.com"]).unwrap());
+        assert_eq!(cli.family(), AddrFamily::Ipv6);
+        assert!(Args::try_parse_from(vec!["pt", "-4", "-6", "example.com"]).is_err());
+    }
+
+    #[test]
+    fn test_output() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "example.com"]).unwrap());
+        assert_eq!(cli.output, Output::Text);
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "--json", "example.com"]).unwrap());
+        assert_eq!(cli.output, Output::Json);
+        let args =
+            Args::try_parse_from(vec!["pt", "--output", "ndjson", "-r", "10", "example.com"]);
+        assert_eq!(Cli::new(args.unwrap()).output, Output::Ndjson);
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "--output", "TSV", "x"]).unwrap());
+        assert_eq!(cli.output, Output::Tsv);
+        assert_eq!(cli.output.delimiter(), Some('\t'));
+        assert_eq!(Output::Csv.delimiter(), Some(','));
+        assert_eq!(Output::Ndjson.delimiter(), None);
+
+        assert!(Args::try_parse_from(vec!["pt", "--output", "xml", "example.com"]).is_err());
+        assert!(
+            Args::try_parse_from(vec!["pt", "--json", "--output", "ndjson", "example.com"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_serve_metrics() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "example.com"]).unwrap());
+        assert!(cli.serve_metrics().unwrap().is_none());
+        let args = Args::try_parse_from(vec!["pt", "--serve-metrics", "[::1]:9100", "x"]);
+        assert_eq!(
+            Cli::new(args.unwrap()).serve_metrics().unwrap(),
+            Some("[::1]:9100".parse().unwrap())
+        );
+        assert!(Args::try_parse_from(vec!["pt", "--serve-metrics", "9100", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_resolver() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "example.com"]).unwrap());
+        let r = cli.resolver().unwrap();
+        assert!(r.overrides().is_empty());
+        assert!(r.server().is_none());
+
+        let args = Args::try_parse_from(vec![
+            "pt",
+            "-4",
+            "--resolve",
+            "example.com:443:192.0.2.1",
+            "--resolve",
+            "example.com:80:[2001:db8::1]",
+            "--dns-server",
+            "192.0.2.53",
+            "example.com",
+        ])
+        .unwrap();
+        let r = Cli::new(args).resolver().unwrap();
+        assert_eq!(r.family(), AddrFamily::Ipv4);
+        assert_eq!(r.overrides().len(), 2);
+        assert_eq!(r.server(), Some("192.0.2.53:53".parse().unwrap()));
+
+        assert!(Args::try_parse_from(vec!["pt", "--resolve", "example.com:443", "x"]).is_err());
+        assert!(Args::try_parse_from(vec!["pt", "--dns-server", "dns.example", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_every() {
+        let cli = Cli::new(Args::try_parse_from(vec!["pt", "example.com"]).unwrap());
+        assert!(cli.resolve_every().unwrap().is_none());
+
+        let args =
+            Args::try_parse_from(vec!["pt", "--resolve-every", "10", "example.com"]).unwrap();
+        assert_eq!(
+            Cli::new(args).resolve_every().unwrap(),
+            Some(ResolveEvery::Attempts(10))
+        );
+        assert!(Args::try_parse_from(vec!["pt", "--resolve-every", "5d", "example.com"]).is_err());
+    }
+
+    #[test]
+    fn test_scan() {
+        let args = Args::try_parse_from(vec!["pt", "1.1.1.1"]).unwrap();
+        assert_eq!(args.concurrency, DEFAULT_CONCURRENCY as u32);
+        assert_eq!(args.rate, 0);
+
+        let args = Args::try_parse_from(vec![
+            "pt",
+            "--concurrency",
+            "8",
+            "--rate",
+            "100",
+            "-p",
+            "22,80,443",
+            "1.1.1.1",
+        ])
+        .unwrap();
+        let cli = Cli::new(args);
+        assert_eq!(cli.targets().unwrap().len(), 3);
+        let _ = cli.scan();
+
+        assert!(Args::try_parse_from(vec!["pt", "--concurrency", "0", "1.1.1.1"]).is_err());
+    }
+
     #[test]
     fn test_validate_host() {
         let empty = "";
@@ -191,7 +843,12 @@ mod test {
         let mut args = Args::try_parse_from(vec!["pt", "1.1.1.1"]);
         assert!(args.is_ok());
         let mut cli = Cli::new(args.unwrap());
-        assert_eq!(cli.args.host, "1.1.1.1".to_string());
+        assert_eq!(cli.args.targets, vec!["1.1.1.1".to_string()]);
+        assert_eq!(
+            cli.targets().unwrap(),
+            vec![("1.1.1.1".to_string(), DEFAULT_PORT)]
+        );
+        assert_eq!(cli.args.probe_args.probe, port_open::NAME.to_string());
 
         args = Args::try_parse_from(vec!["pt", "1.1.1.1", "--silent"]);
         assert!(args.is_ok());
@@ -199,6 +856,33 @@ mod test {
         assert_eq!(cli.verbose.unwrap(), Verbosity::Silent);
     }
 
+    #[test]
+    #[cfg(feature = "tls")]
+    fn test_cert_expiry() {
+        let args = Args::try_parse_from(vec!["pt", "1.1.1.1"]).unwrap();
+        assert!(!args.cert_expiry);
+        assert_eq!(args.warn_days, DEFAULT_WARN_DAYS);
+        assert_eq!(args.crit_days, DEFAULT_CRIT_DAYS);
+
+        let args = Args::try_parse_from(vec![
+            "pt",
+            "--cert-expiry",
+            "--warn-days",
+            "14",
+            "--crit-days",
+            "3",
+            "1.1.1.1",
+        ])
+        .unwrap();
+        assert!(args.cert_expiry);
+        assert_eq!(args.warn_days, 14);
+        assert_eq!(args.crit_days, 3);
+
+        // Thresholds require --cert-expiry and conflict with repeated attempts.
+        assert!(Args::try_parse_from(vec!["pt", "--warn-days", "14", "1.1.1.1"]).is_err());
+        assert!(Args::try_parse_from(vec!["pt", "--cert-expiry", "-c", "3", "1.1.1.1"]).is_err());
+    }
+
     #[test]
     fn test_verbosity() {
         let mut args = Args::try_parse_from(vec!["pt", "1.1.1.1"]);
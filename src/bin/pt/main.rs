@@ -1,13 +1,26 @@
-use cli::{Args, Cli};
-use port_tester::connectors::port_open::*;
+use cli::{Args, Cli, Output};
+#[cfg(feature = "tls")]
+use port_tester::connectors::Connector;
+#[cfg(feature = "tls")]
+use port_tester::connectors::tls::Tls;
+#[cfg(feature = "tls")]
+use port_tester::core::cert::Expiry;
 use port_tester::core::error::*;
+use port_tester::core::host::{HostEventJSON, ResolveEvery};
+use port_tester::core::metrics::{CSV_COLUMNS, FailureKind, MetricsResult, Status, delimited_line};
+use port_tester::core::prometheus::{Exporter, METRICS_PATH, Snapshot, write_textfile};
+use port_tester::core::resolver::is_srv_name;
+use port_tester::core::scan::PortState;
 use port_tester::{Host, Verbosity};
 
 use env_logger::Env;
-use log::{debug, info};
+use log::{debug, info, warn};
+use std::io::{IsTerminal, Write};
 use std::sync::{Arc, Mutex};
 
 mod cli;
+#[path = "../common/probe_args.rs"]
+mod probe_args;
 
 const DEFAULT_LOG_LEVEL: &str = "error";
 
@@ -21,28 +34,107 @@ fn main() {
     }
     debug!("verbosity: {}", verbose);
 
-    if cli.args.host.is_empty() {
-        eprintln!("Error: Host is required.");
-        Cli::print_help();
-        std::process::exit(1);
+    let targets = match cli.targets() {
+        Ok(t) => t,
+        Err(e) => exit_handler(&e.print_help()),
+    };
+
+    let registry = match cli.registry() {
+        Ok(r) => r,
+        Err(e) => exit_handler(&e),
+    };
+    let connector = match registry.select(cli.probe()) {
+        Ok(c) => c,
+        Err(e) => exit_handler(&e),
+    };
+    info!("probe: {}", connector.name());
+
+    let buckets = match cli.buckets() {
+        Ok(b) => b,
+        Err(e) => exit_handler(&e),
+    };
+    let strategy = match cli.addr_strategy() {
+        Ok(s) => s,
+        Err(e) => exit_handler(&e),
+    };
+    let resolve_every = match cli.resolve_every() {
+        Ok(r) => r,
+        Err(e) => exit_handler(&e),
+    };
+    let resolver = match cli.resolver() {
+        Ok(r) => r,
+        Err(e) => exit_handler(&e),
+    };
+    let mut hosts: Vec<Mutex<Host>> = Vec::with_capacity(targets.len());
+    for (name, port) in &targets {
+        let found = if is_srv_name(name) {
+            Host::from_srv(name, &resolver)
+        } else {
+            // Reuse the addresses of the previous target when only the port differs.
+            match hosts.last().map(|h| h.lock().unwrap()) {
+                Some(h) if h.name() == name => Ok(vec![h.for_port(*port)]),
+                _ => Host::with_resolver(name, *port, resolver.clone()).map(|h| vec![h]),
+            }
+        };
+        match found {
+            Ok(found) => {
+                for mut h in found {
+                    info!("host: {}:{}", h.name(), h.port());
+                    h.set_addr_strategy(strategy);
+                    h.metrics_mut()
+                        .set_outage_threshold(cli.args.outage_threshold);
+                    if let Some(b) = &buckets {
+                        h.metrics_mut().set_buckets(b.clone());
+                    }
+                    hosts.push(Mutex::new(h));
+                }
+            }
+            Err(e) => exit_handler(&e),
+        }
     }
+    let hosts = Arc::new(hosts);
 
-    // Set up Ctrl-C handler to print report on interrupt. We need to create the host object first
-    // so we can access its metrics in the handler.
-    let host = Arc::new(Mutex::new(match Host::new(&cli.args.host, cli.args.port) {
-        Ok(h) => h,
+    let serve_metrics = match cli.serve_metrics() {
+        Ok(a) => a,
+        Err(e) => exit_handler(&e),
+    };
+    // The exporter serves a snapshot published after each attempt, as probes lock their hosts.
+    let snapshot = serve_metrics.map(|addr| match Exporter::bind(addr) {
+        Ok(exporter) => {
+            info!("metrics: http://{}{}", addr, METRICS_PATH);
+            let snapshot = Snapshot::new();
+            snapshot.publish(&hosts);
+            exporter.spawn(snapshot.clone());
+            snapshot
+        }
         Err(e) => exit_handler(&e),
-    }));
-    info!("host: {}", host.lock().unwrap().name());
+    });
+    // Write the textfile once up front so an unwritable path fails before probing.
+    if let Some(path) = &cli.args.textfile {
+        if let Err(e) = write_textfile(path, &hosts) {
+            exit_handler(&e);
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    if cli.args.cert_expiry {
+        if hosts.len() > 1 {
+            let e = Error::new(SourceError::Msg(
+                "--cert-expiry accepts a single target".to_string(),
+            ));
+            exit_handler(&e.set_code(CODE_OPTIONS_ERROR));
+        }
+        check_cert_expiry(&cli, verbose, &mut hosts[0].lock().unwrap());
+    }
 
-    // Clone metrics so we have access to it in the ctrlc handler.
-    // let metrics_clone = Arc::clone(&host.metrics);
+    // Set up Ctrl-C handler to print report on interrupt. We need to create the host objects first
+    // so we can access their metrics in the handler.
     let cli_clone = cli.clone();
-    let host_clone = Arc::clone(&host);
+    let hosts_clone = Arc::clone(&hosts);
     // Create a handler that will attempt to print a metrics report when we receive a Ctrl-C.
     ctrlc::set_handler(move || {
         //println!("\nInterrupted! Generating report...");
-        print_report(&cli_clone, &host_clone.lock().unwrap());
+        print_report(&cli_clone, &hosts_clone);
         std::process::exit(0);
     })
     .expect("Error setting Ctrl-C handler");
@@ -57,37 +149,36 @@ fn main() {
         std::iter::repeat(()).take(c)
     };
 
+    let scan = cli.scan();
+    let mut live = Live::new(&cli, verbose, &hosts);
+    if let Some(delimiter) = cli.output.delimiter() {
+        let header: Vec<&str> = ["host", "port"].into_iter().chain(CSV_COLUMNS).collect();
+        println!("{}", delimited_line(&header, delimiter));
+    }
     for i in iter.enumerate().map(|(i, _)| i as u32 + 1) {
-        debug!(
-            "attempt: {}, ip: {}, port: {}, timeout: {}",
-            i,
-            host.lock().unwrap().ip(),
-            host.lock().unwrap().port(),
-            cli.args.timeout
-        );
-
-        // Connect to the target and record metrics.
-        connect(i, &mut host.lock().unwrap(), cli.args.timeout);
-
-        // Use a block so the MutexGuard is dropped before the intermediate report and sleep,
-        // otherwise those sites deadlock trying to re-acquire the same lock.
-        let (display_str, is_err) = {
-            let h = host.lock().unwrap();
-            let mr = h.metrics().result(i).unwrap();
-            let status = mr.status();
-            let display = match cli.args.count {
-                1 => status.to_string_with_verbosity(verbose),
-                _ => mr.to_string_with_verbosity(verbose),
-            };
-            (display, status.is_err())
-        };
+        if let Some(every) = &resolve_every {
+            if resolve(&cli, &hosts, every, i) {
+                live.detach();
+            }
+        }
 
-        if !cli.args.silent && !cli.args.json {
-            println!("{}", display_str);
+        // Connect to the targets and record metrics.
+        scan.attempt(connector, i, &hosts, cli.args.timeout);
+        let failed = live.update(i);
+        if let Some(snapshot) = &snapshot {
+            snapshot.publish(&hosts);
+        }
+        // The last attempt is always written, as the early exit below skips the final report.
+        if cli.args.report_interval == 0 || i % cli.args.report_interval == 0 || i == cli.args.count
+        {
+            update_textfile(&cli, &hosts);
         }
 
-        if cli.args.count == 1 && !cli.args.json {
-            if is_err {
+        if cli.args.count == 1 && cli.output == Output::Text {
+            if hosts.len() > 1 && !cli.args.silent {
+                println!("{}", summary(&hosts));
+            }
+            if failed {
                 std::process::exit(1);
             } else {
                 std::process::exit(0);
@@ -96,13 +187,23 @@ fn main() {
 
         // Print intermediate report if report_interval is set.
         // If the count is reached, the final report will be printed after the loop.
-        if !cli.args.json
+        if matches!(cli.output, Output::Text | Output::Ndjson)
             && cli.args.report_interval > 0
             && i % cli.args.report_interval == 0
             && (cli.args.count == 0 || i < cli.args.count)
         {
-            print!("Intermediate report: ");
-            println!("{}", host.lock().unwrap().metrics().report());
+            if cli.output == Output::Ndjson {
+                for host in hosts.iter() {
+                    print_event(host.lock().unwrap().summary_json());
+                }
+            } else {
+                print!("Intermediate report: ");
+                if hosts.len() > 1 {
+                    println!();
+                }
+                println!("{}", report(&hosts));
+                live.detach();
+            }
         }
 
         // Sleep between attempts unless this is the last attempt.
@@ -112,26 +213,362 @@ fn main() {
         }
     }
 
-    print_report(&cli, &host.lock().unwrap());
+    print_report(&cli, &hosts);
 }
 
-fn print_report(cli: &Cli, host: &Host) {
-    debug!("connection attempts complete, print final report");
-    if cli.args.json {
-        let h_json_string = match host.to_json_string() {
-            Ok(j) => j,
+/// Resolve the targets that are due again before attempt `seq` and print any change of their
+/// addresses. A target that fails to resolve keeps its addresses and attempt `seq` is recorded as
+/// failed without probing it. Returns `true` if anything was printed.
+fn resolve(cli: &Cli, hosts: &[Mutex<Host>], every: &ResolveEvery, seq: u32) -> bool {
+    let mut printed = false;
+    for host in hosts {
+        let mut h = host.lock().unwrap();
+        if !every.is_due(seq, h.resolved_at()) {
+            continue;
+        }
+
+        let label = label(&h);
+        match h.resolve().map(|c| c.cloned()) {
+            Ok(Some(change)) => {
+                info!("{}: {}", label, change.report());
+                if !cli.args.silent && cli.output == Output::Text {
+                    println!("{} {} {}", change.timestamp(), label, change.report());
+                    printed = true;
+                }
+            }
+            Ok(None) => debug!("{}: addresses unchanged", label),
             Err(e) => {
-                exit_handler(&e);
+                warn!("{}: {}", label, e);
+                let took = h.resolve_time().unwrap_or_default();
+                let start = h.resolved_at() - took;
+                let mr = MetricsResult::new(seq, start, took, Status::Failure(Some(e)))
+                    .with_failure_kind(FailureKind::Resolve);
+                h.record_result(mr);
             }
-        };
-        println!("{}", h_json_string);
+        }
+    }
+    printed
+}
+
+/// Prints the result of each attempt.
+///
+/// A single target prints one line per attempt. Multiple targets print one line per target
+/// prefixed with the target, redrawn in place after each attempt when writing to a terminal. For a
+/// single attempt against multiple targets, each line shows the port state instead.
+struct Live<'a> {
+    cli: &'a Cli,
+    verbose: &'a Verbosity,
+    hosts: &'a [Mutex<Host>],
+    labels: Vec<String>,
+    in_place: bool,
+    /// Number of lines drawn by the previous update that will be redrawn.
+    drawn: usize,
+}
+
+impl<'a> Live<'a> {
+    fn new(cli: &'a Cli, verbose: &'a Verbosity, hosts: &'a [Mutex<Host>]) -> Self {
+        let labels = hosts.iter().map(|h| label(&h.lock().unwrap())).collect();
+        // Log lines on stderr would break the redraw, so only redraw without them.
+        let in_place = hosts.len() > 1
+            && matches!(verbose, Verbosity::Normal | Verbosity::Quiet)
+            && std::io::stdout().is_terminal();
+        Live {
+            cli,
+            verbose,
+            hosts,
+            labels,
+            in_place,
+            drawn: 0,
+        }
+    }
+
+    /// Print the results of attempt `seq`. Returns `true` if any of the attempts failed.
+    fn update(&mut self, seq: u32) -> bool {
+        let width = self.labels.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut failed = false;
+        let mut lines = Vec::with_capacity(self.hosts.len());
+        for (host, label) in self.hosts.iter().zip(&self.labels) {
+            let h = host.lock().unwrap();
+            let mr = h.metrics().result(seq).unwrap();
+            failed |= mr.is_err();
+            let line = match (self.cli.args.count, self.hosts.len()) {
+                (1, 1) => mr.status().to_string_with_verbosity(self.verbose),
+                (1, _) => state(mr, self.verbose),
+                _ => mr.to_string_with_verbosity(self.verbose),
+            };
+            lines.push(match self.hosts.len() {
+                1 => line,
+                _ => format!("{:<width$}  {}", label, line),
+            });
+        }
+
+        if self.cli.output == Output::Ndjson {
+            for host in self.hosts {
+                if let Some(event) = host.lock().unwrap().attempt_json(seq) {
+                    print_event(event);
+                }
+            }
+        }
+        if let Some(delimiter) = self.cli.output.delimiter() {
+            for host in self.hosts {
+                let h = host.lock().unwrap();
+                let mr = h.metrics().result(seq).unwrap();
+                let mut row = vec![h.name().to_string(), h.port().to_string()];
+                row.extend(mr.csv_fields());
+                println!("{}", delimited_line(&row, delimiter));
+            }
+        }
+        if self.cli.args.silent || self.cli.output != Output::Text {
+            return failed;
+        }
+
+        let mut out = std::io::stdout().lock();
+        if self.in_place && self.drawn > 0 {
+            let _ = write!(out, "\x1b[{}A", self.drawn);
+        }
+        let mut drawn = 0;
+        for line in lines.iter().flat_map(|l| l.lines()) {
+            if self.in_place {
+                let _ = write!(out, "\x1b[2K");
+            }
+            let _ = writeln!(out, "{}", line);
+            drawn += 1;
+        }
+        // Clear any lines left over from a longer previous update.
+        if self.in_place && self.drawn > drawn {
+            for _ in drawn..self.drawn {
+                let _ = writeln!(out, "\x1b[2K");
+            }
+            let _ = write!(out, "\x1b[{}A", self.drawn - drawn);
+        }
+        let _ = out.flush();
+        self.drawn = drawn;
+        failed
+    }
+
+    /// Leave the current lines on screen and draw the next update below them.
+    fn detach(&mut self) {
+        self.drawn = 0;
+    }
+}
+
+/// Returns the target label of `host` as HOST:PORT.
+fn label(host: &Host) -> String {
+    match host.name().contains(':') {
+        true => format!("[{}]:{}", host.name(), host.port()),
+        false => format!("{}:{}", host.name(), host.port()),
+    }
+}
+
+/// Returns the port state of an attempt, followed by the error when verbose.
+fn state(mr: &MetricsResult, verbose: &Verbosity) -> String {
+    let state = PortState::from(mr);
+    match verbose {
+        Verbosity::Verbose(_) if mr.is_err() => {
+            format!(
+                "{:<8}  {}",
+                state,
+                mr.status().to_string_with_verbosity(verbose)
+            )
+        }
+        _ => state.to_string(),
+    }
+}
+
+/// Returns the number of hosts in each port state as of their latest attempt.
+fn summary(hosts: &[Mutex<Host>]) -> String {
+    let mut counts = [0; 4];
+    for host in hosts {
+        let h = host.lock().unwrap();
+        if let Some(mr) = h.metrics().iter().last() {
+            match PortState::from(mr) {
+                PortState::Open => counts[0] += 1,
+                PortState::Closed => counts[1] += 1,
+                PortState::Filtered => counts[2] += 1,
+                _ => counts[3] += 1,
+            }
+        }
+    }
+    let mut summary = format!(
+        "{} targets: {} open, {} closed, {} filtered",
+        hosts.len(),
+        counts[0],
+        counts[1],
+        counts[2]
+    );
+    if counts[3] > 0 {
+        summary.push_str(&format!(", {} unknown", counts[3]));
+    }
+    summary
+}
+
+/// Returns the report of a single host, or a table of each host's latest port state and report
+/// for multiple hosts followed by a summary of the port states.
+fn report(hosts: &[Mutex<Host>]) -> String {
+    if let [host] = hosts {
+        return host.lock().unwrap().metrics().report();
+    }
+
+    let rows: Vec<(String, String, String)> = hosts
+        .iter()
+        .map(|h| {
+            let h = h.lock().unwrap();
+            let state = match h.metrics().iter().last() {
+                Some(mr) => PortState::from(mr).to_string(),
+                None => "-".to_string(),
+            };
+            (label(&h), state, h.metrics().report())
+        })
+        .collect();
+    let width = rows.iter().map(|(l, _, _)| l.len()).max().unwrap_or(0);
+    let mut table = Vec::with_capacity(rows.len() + 1);
+    for (label, state, report) in &rows {
+        // Align any further report lines under the first.
+        let mut lines = report.lines();
+        let first = lines.next().unwrap_or_default();
+        table.push(format!("{:<width$}  {:<8}  {}", label, state, first));
+        for line in lines {
+            table.push(format!("{:<indent$}{}", "", line, indent = width + 12));
+        }
+    }
+    table.push(summary(hosts));
+    table.join("\n")
+}
+
+/// Prints `event` as a line of NDJSON output.
+fn print_event(event: HostEventJSON) {
+    match event.to_json_string() {
+        Ok(j) => println!("{}", j),
+        Err(e) => exit_handler(&e),
+    }
+}
+
+/// Replaces the --textfile, if any, with the current metrics. A failed write is logged and
+/// retried on the next update rather than stopping the probes.
+fn update_textfile(cli: &Cli, hosts: &[Mutex<Host>]) {
+    if let Some(path) = &cli.args.textfile {
+        if let Err(e) = write_textfile(path, hosts) {
+            warn!("{}", e);
+        }
+    }
+}
+
+fn print_report(cli: &Cli, hosts: &[Mutex<Host>]) {
+    debug!("connection attempts complete, print final report");
+    update_textfile(cli, hosts);
+    if cli.output == Output::Ndjson {
+        for host in hosts {
+            print_event(host.lock().unwrap().final_json());
+        }
+        return;
+    }
+    if cli.output.delimiter().is_some() {
+        return;
+    }
+    if cli.output == Output::Json {
+        let mut json = Vec::with_capacity(hosts.len());
+        for host in hosts {
+            match host.lock().unwrap().to_json_string() {
+                Ok(j) => json.push(j),
+                Err(e) => exit_handler(&e),
+            }
+        }
+        match hosts.len() {
+            1 => println!("{}", json[0]),
+            _ => println!("[{}]", json.join(",")),
+        }
         return;
     }
 
     // Do not give the final report for a single attempt.
-    if !cli.args.json && cli.args.count != 1 {
-        println!("{}", host.metrics().report());
+    if cli.args.count != 1 {
+        println!("{}", report(hosts));
+        for host in hosts {
+            let h = host.lock().unwrap();
+            let m = h.metrics();
+            let mut sections = Vec::new();
+            if let Some(srv) = h.srv() {
+                sections.push(srv.report());
+            }
+            if m.addr_summaries().len() > 1 {
+                sections.push(m.addr_report());
+            }
+            if m.races().races() > 0 {
+                sections.push(m.races().report());
+            }
+            if !h.addr_changes().is_empty() {
+                let mut changes = String::from("address changes:");
+                for c in h.addr_changes() {
+                    changes.push_str(&format!("\n  {}", c));
+                }
+                sections.push(changes);
+            }
+            if !m.outages().is_empty() {
+                sections.push(m.outages().report());
+            }
+            for section in sections {
+                match hosts.len() {
+                    1 => println!("\n{}", section),
+                    _ => println!("\n{} {}", label(&h), section),
+                }
+            }
+        }
+        // Only a single target has room for the latency histogram.
+        if let [host] = hosts {
+            let h = host.lock().unwrap();
+            if h.metrics().latency().is_some() {
+                println!("\n{}", h.metrics().histogram().report());
+            }
+        }
+    }
+}
+
+/// Perform a single TLS handshake, report the host's leaf certificate and exit with a code for its
+/// expiry classification.
+#[cfg(feature = "tls")]
+fn check_cert_expiry(cli: &Cli, verbose: &Verbosity, host: &mut Host) -> ! {
+    debug!(
+        "cert expiry: warn_days: {}, crit_days: {}",
+        cli.args.warn_days, cli.args.crit_days
+    );
+    Tls::new().connect(1, host, cli.args.timeout);
+    let status = host.metrics().result(1).unwrap().status();
+    let now = chrono::Utc::now();
+
+    let code = match host.certificate() {
+        // A broken chain is still a failure when the expiry alone would pass.
+        Some(cert) => match cert.expiry(now, cli.args.warn_days, cli.args.crit_days) {
+            e @ (Expiry::Ok | Expiry::Warning) if status.is_err() => {
+                debug!("expiry: {}", e);
+                CODE_RUNTIME_ERROR
+            }
+            e => e.code(),
+        },
+        None => CODE_RUNTIME_ERROR,
+    };
+
+    if matches!(cli.output, Output::Json | Output::Ndjson) {
+        match host.to_json_string() {
+            Ok(j) => println!("{}", j),
+            Err(e) => exit_handler(&e),
+        }
+    } else if !cli.args.silent {
+        match host.certificate() {
+            Some(cert) => {
+                let expiry = cert.expiry(now, cli.args.warn_days, cli.args.crit_days);
+                if *verbose != Verbosity::Quiet {
+                    println!("{}", cert.report(now));
+                    if status.is_err() {
+                        println!("{}", status.to_string_with_verbosity(verbose));
+                    }
+                }
+                println!("expiry: {}", expiry);
+            }
+            None => println!("{}", status.to_string_with_verbosity(verbose)),
+        }
     }
+
+    std::process::exit(code);
 }
 
 fn setup_logger(level: &Option<Verbosity>) {
@@ -0,0 +1,272 @@
+use chrono::Local;
+use regex::Regex;
+
+use crate::Host;
+use crate::connectors::{Connector, Reached, port_open, probe_addrs};
+use crate::core::error::*;
+use crate::core::metrics::{MetricsResult, Status, Timings};
+
+use std::io::{ErrorKind, Read};
+use std::net::SocketAddr;
+
+/// Name used to select the [`Banner`] probe.
+pub const NAME: &str = "banner";
+/// Number of banner bytes read by default.
+pub const DEFAULT_BYTES: usize = 512;
+
+/// [`Connector`] that connects and reads the banner the server sends first, as SSH, SMTP, FTP,
+/// POP3 and IMAP servers do.
+///
+/// Reading stops once the byte limit is reached, the server closes the connection, the
+/// expectation set with [`Banner::set_expect`] matches, or, with no expectation, a complete line
+/// has been received. Reaching the timeout after some bytes have arrived ends the read with what
+/// was received. The attempt fails if nothing arrives within the timeout or if the banner does not
+/// match the expectation. The banner is attached to the attempt's [`MetricsResult`] either way and
+/// the time from connecting to its first byte is recorded as [`Timings::first_byte`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use regex::Regex;
+/// use port_tester::Host;
+/// use port_tester::connectors::Connector;
+/// use port_tester::connectors::banner::Banner;
+///
+/// let probe = Banner::new().set_expect(Regex::new("^SSH-2\\.0-").unwrap());
+/// let mut host = Host::new("127.0.0.1", 22).unwrap();
+/// probe.connect(1, &mut host, 5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Banner {
+    bytes: usize,
+    expect: Option<Regex>,
+}
+
+/// Defaults to reading up to [`DEFAULT_BYTES`] bytes with no expectation.
+impl Default for Banner {
+    fn default() -> Self {
+        Banner {
+            bytes: DEFAULT_BYTES,
+            expect: None,
+        }
+    }
+}
+
+impl Connector for Banner {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut timings = Timings::default();
+        let mut banner = None;
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            timings = Timings::default();
+            banner = None;
+            let res = self.grab(addr, timeout, &mut timings).and_then(|grabbed| {
+                let res = self.check(&grabbed);
+                banner = Some(grabbed);
+                res
+            });
+            reached.note(&res, timings.connect().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+
+        let mut result = reached.apply(
+            MetricsResult::new(seq, start, dur, status)
+                .with_timings(timings)
+                .with_addrs(addrs),
+        );
+        if let Some(banner) = banner {
+            result = result.with_banner(banner);
+        }
+        host.record_result(result);
+    }
+}
+
+impl Banner {
+    /// Create a new [`Banner`] probe. See [`Banner::default`].
+    pub fn new() -> Self {
+        Banner::default()
+    }
+
+    /// Set the maximum number of bytes to read. A limit of 0 is treated as 1.
+    pub fn set_bytes(mut self, bytes: usize) -> Self {
+        self.bytes = bytes.max(1);
+        self
+    }
+
+    /// Require the banner to match `expect`.
+    pub fn set_expect(mut self, expect: Regex) -> Self {
+        self.expect = Some(expect);
+        self
+    }
+
+    /// Connect to `addr` and read the banner.
+    fn grab(&self, addr: &SocketAddr, timeout: u64, timings: &mut Timings) -> Result<String> {
+        let io_err = |e| Error::new(SourceError::Io(e));
+
+        let start = Local::now();
+        let mut sock = port_open::open_addr(addr, timeout).map_err(io_err)?;
+        let connected = Local::now();
+        *timings = timings.set_connect(connected - start);
+        sock.set_read_timeout(Some(std::time::Duration::from_secs(timeout)))
+            .map_err(io_err)?;
+
+        let mut buf = vec![0; self.bytes];
+        let mut n = 0;
+        while n < buf.len() {
+            match sock.read(&mut buf[n..]) {
+                Ok(0) => break,
+                Ok(read) => {
+                    if n == 0 {
+                        *timings = timings.set_first_byte(Local::now() - connected);
+                    }
+                    n += read;
+                    if self.done(&buf[..n]) {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e)
+                    if n > 0 && matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    break;
+                }
+                Err(e) => return Err(io_err(e)),
+            }
+        }
+
+        if n == 0 {
+            return Err(Error::new(SourceError::Msg(
+                "connection closed before banner".to_string(),
+            )));
+        }
+        Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+    }
+
+    /// Returns `true` if enough of the banner has been read.
+    fn done(&self, banner: &[u8]) -> bool {
+        match &self.expect {
+            Some(re) => re.is_match(&String::from_utf8_lossy(banner)),
+            None => banner.ends_with(b"\n"),
+        }
+    }
+
+    /// Check the banner against the expectation.
+    fn check(&self, banner: &str) -> Result<()> {
+        match &self.expect {
+            Some(re) if !re.is_match(banner) => Err(Error::new(SourceError::Msg(format!(
+                "banner did not match /{}/",
+                re.as_str()
+            )))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// Start a server that writes each of `chunks` to the first connection, pausing between
+    /// them, then holds the connection open for `hold` milliseconds. Returns the port.
+    fn serve(chunks: &'static [&'static [u8]], hold: u64) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            for c in chunks {
+                s.write_all(c).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(hold));
+        });
+        port
+    }
+
+    fn probe(banner: Banner, port: u16) -> Host {
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+        banner.connect(1, &mut host, 1);
+        host
+    }
+
+    #[test]
+    fn test_connect_success() {
+        let banner = Banner::new();
+        assert_eq!(banner.name(), NAME);
+
+        // Reading stops at the end of the first line, well before the server lets go.
+        let host = probe(banner, serve(&[b"SSH-2.0-", b"OpenSSH_9.6\r\n"], 3000));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert_eq!(mr.banner(), Some("SSH-2.0-OpenSSH_9.6\r\n"));
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().first_byte().is_some());
+        assert!(mr.duration() < chrono::TimeDelta::try_milliseconds(900).unwrap());
+    }
+
+    #[test]
+    fn test_connect_expect() {
+        let re = Regex::new("(?m)^220 ").unwrap();
+        let port = serve(&[b"220-mail.example.com\r\n", b"220 ESMTP ready\r\n"], 0);
+        let host = probe(Banner::new().set_expect(re), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert_eq!(
+            mr.banner(),
+            Some("220-mail.example.com\r\n220 ESMTP ready\r\n")
+        );
+
+        let re = Regex::new("^SSH-").unwrap();
+        let port = serve(&[b"+OK POP3 ready\r\n"], 0);
+        let host = probe(Banner::new().set_expect(re), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("banner did not match"), "{}", mr);
+        assert_eq!(mr.banner(), Some("+OK POP3 ready\r\n"));
+    }
+
+    #[test]
+    fn test_connect_bytes() {
+        let host = probe(
+            Banner::new().set_bytes(4),
+            serve(&[b"* OK IMAP4rev1\r\n"], 0),
+        );
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert_eq!(mr.banner(), Some("* OK"));
+    }
+
+    #[test]
+    fn test_connect_partial() {
+        // A banner without a line ending is returned when the read times out.
+        let host = probe(Banner::new(), serve(&[b"220 ready"], 2000));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert_eq!(mr.banner(), Some("220 ready"));
+    }
+
+    #[test]
+    fn test_connect_silent() {
+        // The server accepts but never sends anything.
+        let host = probe(Banner::new(), serve(&[], 2000));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert_eq!(mr.banner(), None);
+
+        let host = probe(Banner::new(), serve(&[], 0));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("closed before banner"), "{}", mr);
+    }
+}
@@ -0,0 +1,629 @@
+use chrono::Local;
+use simple_dns::rdata::RData;
+use simple_dns::{CLASS, Name, Packet, PacketFlag, QCLASS, Question, RCODE, TYPE};
+
+use crate::Host;
+use crate::connectors::{Connector, Reached, probe_addrs};
+use crate::core::error::*;
+use crate::core::metrics::{FailureKind, MetricsResult, Status, Timings};
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Name used to select the [`Dns`] probe.
+pub const NAME: &str = "dns";
+/// Name queried by default: the root zone, which any recursive resolver can answer.
+pub const DEFAULT_QUERY_NAME: &str = ".";
+/// Record type queried by default.
+pub const DEFAULT_RECORD_TYPE: RecordType = RecordType::NS;
+
+/// Largest DNS message over UDP or TCP.
+const MAX_MESSAGE: usize = 65535;
+
+/// DNS record types that can be queried.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum RecordType {
+    A,
+    AAAA,
+    CNAME,
+    MX,
+    NS,
+    PTR,
+    SOA,
+    SRV,
+    TXT,
+}
+
+impl RecordType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordType::A => "A",
+            RecordType::AAAA => "AAAA",
+            RecordType::CNAME => "CNAME",
+            RecordType::MX => "MX",
+            RecordType::NS => "NS",
+            RecordType::PTR => "PTR",
+            RecordType::SOA => "SOA",
+            RecordType::SRV => "SRV",
+            RecordType::TXT => "TXT",
+        }
+    }
+
+    fn to_type(self) -> TYPE {
+        match self {
+            RecordType::A => TYPE::A,
+            RecordType::AAAA => TYPE::AAAA,
+            RecordType::CNAME => TYPE::CNAME,
+            RecordType::MX => TYPE::MX,
+            RecordType::NS => TYPE::NS,
+            RecordType::PTR => TYPE::PTR,
+            RecordType::SOA => TYPE::SOA,
+            RecordType::SRV => TYPE::SRV,
+            RecordType::TXT => TYPE::TXT,
+        }
+    }
+
+    /// Normalize an answer value so it can be compared with the answers of a query. Addresses
+    /// are reformatted and names are lowercased without the trailing dot.
+    fn normalize(&self, value: &str) -> String {
+        match self {
+            RecordType::A | RecordType::AAAA => value
+                .parse::<IpAddr>()
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|_| value.to_owned()),
+            RecordType::TXT => value.to_owned(),
+            _ => value.trim_end_matches('.').to_lowercase(),
+        }
+    }
+}
+
+impl std::fmt::Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "A" => Ok(RecordType::A),
+            "AAAA" => Ok(RecordType::AAAA),
+            "CNAME" => Ok(RecordType::CNAME),
+            "MX" => Ok(RecordType::MX),
+            "NS" => Ok(RecordType::NS),
+            "PTR" => Ok(RecordType::PTR),
+            "SOA" => Ok(RecordType::SOA),
+            "SRV" => Ok(RecordType::SRV),
+            "TXT" => Ok(RecordType::TXT),
+            _ => Err(Error::new(SourceError::Msg(format!(
+                "Unsupported record type: {} (supported: A, AAAA, CNAME, MX, NS, PTR, SOA, SRV, TXT)",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)),
+        }
+    }
+}
+
+/// Transport used to send DNS queries.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Transport {
+    /// UDP, retrying over TCP if the response is truncated.
+    #[default]
+    Udp,
+    /// TCP only.
+    Tcp,
+}
+
+/// [`Connector`] that sends a DNS query to the host and checks that it answers.
+///
+/// The attempt fails if no response arrives within the timeout, if the server responds with an
+/// error such as `SERVFAIL` or `REFUSED`, or if the answers do not include every value set with
+/// [`Dns::set_expect`]. `NXDOMAIN` counts as an answer, so it only fails the attempt when answers
+/// are expected. Each resolved address is tried in sequence until one passes. The time from
+/// sending the query to receiving the response is recorded as [`Timings::first_byte`]. A failed
+/// response is recorded as [`FailureKind::Dns`].
+///
+/// Answers are formatted as text for comparison: addresses for `A` and `AAAA`, names for `CNAME`,
+/// `NS` and `PTR`, `<preference> <exchange>` for `MX`, `<priority> <weight> <port> <target>` for
+/// `SRV`, `<mname> <rname> <serial>` for `SOA` and the joined strings for `TXT`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::connectors::Connector;
+/// use port_tester::connectors::dns::{Dns, RecordType};
+///
+/// let probe = Dns::new()
+///     .set_name("example.com")
+///     .set_record_type(RecordType::A)
+///     .set_expect("93.184.215.14");
+///
+/// let mut host = Host::new("8.8.8.8", 53).unwrap();
+/// probe.connect(1, &mut host, 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Dns {
+    name: String,
+    record_type: RecordType,
+    transport: Transport,
+    expect: Vec<String>,
+}
+
+/// Defaults to a [`DEFAULT_RECORD_TYPE`] query for [`DEFAULT_QUERY_NAME`] over UDP with no
+/// expected answers.
+impl Default for Dns {
+    fn default() -> Self {
+        Dns {
+            name: DEFAULT_QUERY_NAME.to_string(),
+            record_type: DEFAULT_RECORD_TYPE,
+            transport: Transport::default(),
+            expect: Vec::new(),
+        }
+    }
+}
+
+impl Connector for Dns {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut timings = Timings::default();
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            timings = Timings::default();
+            let res = self
+                .query(addr, timeout, &mut timings)
+                .and_then(|a| self.check(&a));
+            // Only a TCP query connects before it fails.
+            reached.note(&res, timings.connect().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        // A failure that isn't an I/O error came from the server's response.
+        let answered = matches!(&res, Err(e) if !matches!(e.source(), SourceError::Io(_)));
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+        let mut result = reached.apply(
+            MetricsResult::new(seq, start, dur, status)
+                .with_timings(timings)
+                .with_addrs(addrs),
+        );
+        if answered {
+            result = result.with_failure_kind(FailureKind::Dns);
+        }
+        host.record_result(result);
+    }
+}
+
+impl Dns {
+    /// Create a new [`Dns`] probe. See [`Dns::default`].
+    pub fn new() -> Self {
+        Dns::default()
+    }
+
+    /// Set the name to query, like `example.com`.
+    pub fn set_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Set the record type to query.
+    pub fn set_record_type(mut self, record_type: RecordType) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// Set the transport used to send the query.
+    pub fn set_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Require the answers to include `answer`. May be called more than once to require several
+    /// answers. Answers not set here are ignored.
+    pub fn set_expect(mut self, answer: &str) -> Self {
+        self.expect.push(answer.to_owned());
+        self
+    }
+
+    /// Send the query to the DNS server at `addr` and return the formatted answers of the
+    /// queried type. `timeout` is in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no response arrives within the timeout, the response is malformed, or
+    /// the server responds with an error other than `NXDOMAIN`.
+    pub fn query(
+        &self,
+        addr: &SocketAddr,
+        timeout: u64,
+        timings: &mut Timings,
+    ) -> Result<Vec<String>> {
+        let id = query_id();
+        let mut packet = Packet::new_query(id);
+        packet.set_flags(PacketFlag::RECURSION_DESIRED);
+        let name = Name::new(&self.name).map_err(|e| {
+            Error::new(SourceError::Msg(format!(
+                "Invalid query name: {}: {}",
+                self.name, e
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        })?;
+        packet.questions.push(Question::new(
+            name,
+            self.record_type.to_type().into(),
+            QCLASS::CLASS(CLASS::IN),
+            false,
+        ));
+        let msg = packet.build_bytes_vec().map_err(invalid)?;
+
+        let timeout = Duration::from_secs(timeout);
+        let sent = Local::now();
+        let mut resp = match self.transport {
+            Transport::Udp => send_udp(addr, &msg, id, timeout)?,
+            Transport::Tcp => send_tcp(addr, &msg, timeout, timings)?,
+        };
+        if self.transport == Transport::Udp && truncated(&resp) {
+            resp = send_tcp(addr, &msg, timeout, timings)?;
+        }
+        *timings = timings.set_first_byte(Local::now() - sent);
+
+        let reply = Packet::parse(&resp).map_err(invalid)?;
+        if reply.id() != id {
+            return Err(Error::new(SourceError::Msg(format!(
+                "response id {} did not match query id {}",
+                reply.id(),
+                id
+            ))));
+        }
+
+        match reply.rcode() {
+            RCODE::NoError | RCODE::NameError => {}
+            rcode => {
+                return Err(Error::new(SourceError::Msg(format!(
+                    "{} responded {} for {} {}",
+                    addr,
+                    rcode_str(rcode),
+                    self.name,
+                    self.record_type
+                ))));
+            }
+        }
+
+        let want = self.record_type.to_type();
+        Ok(reply
+            .answers
+            .iter()
+            .filter(|rr| rr.rdata.type_code() == want)
+            .filter_map(|rr| format_rdata(&rr.rdata))
+            .collect())
+    }
+
+    /// Check the answers against the expected answers.
+    fn check(&self, answers: &[String]) -> Result<()> {
+        let got: Vec<String> = answers
+            .iter()
+            .map(|a| self.record_type.normalize(a))
+            .collect();
+        match self
+            .expect
+            .iter()
+            .map(|e| self.record_type.normalize(e))
+            .find(|e| !got.contains(e))
+        {
+            Some(missing) => Err(Error::new(SourceError::Msg(format!(
+                "{} {} answer missing {} (got {})",
+                self.name,
+                self.record_type,
+                missing,
+                if got.is_empty() {
+                    "none".to_string()
+                } else {
+                    got.join(", ")
+                }
+            )))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid(e: simple_dns::SimpleDnsError) -> Error {
+    Error::new(SourceError::Msg(format!("invalid DNS message: {}", e)))
+}
+
+/// Returns a query id. Ids only need to be unpredictable enough to match responses to queries.
+fn query_id() -> u16 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_u128(Local::now().timestamp_nanos_opt().unwrap_or_default() as u128);
+    h.finish() as u16
+}
+
+/// Returns `true` if the truncation flag is set in the response header.
+fn truncated(resp: &[u8]) -> bool {
+    resp.len() > 2 && resp[2] & 0x02 != 0
+}
+
+/// Send `msg` over UDP and wait for the response with the matching `id`.
+fn send_udp(addr: &SocketAddr, msg: &[u8], id: u16, timeout: Duration) -> Result<Vec<u8>> {
+    let io_err = |e| Error::new(SourceError::Io(e));
+    let bind: SocketAddr = if addr.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+
+    let sock = UdpSocket::bind(bind).map_err(io_err)?;
+    sock.connect(addr).map_err(io_err)?;
+    sock.set_read_timeout(Some(timeout)).map_err(io_err)?;
+    sock.send(msg).map_err(io_err)?;
+
+    // Ignore stray datagrams, like late responses to an earlier attempt.
+    let mut buf = vec![0; MAX_MESSAGE];
+    loop {
+        let n = match sock.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(io_err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("no response from {}", addr),
+                )));
+            }
+            Err(e) => return Err(io_err(e)),
+        };
+        if n >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            buf.truncate(n);
+            return Ok(buf);
+        }
+    }
+}
+
+/// Send `msg` over TCP with its two byte length prefix and read the response.
+fn send_tcp(
+    addr: &SocketAddr,
+    msg: &[u8],
+    timeout: Duration,
+    timings: &mut Timings,
+) -> Result<Vec<u8>> {
+    let io_err = |e| Error::new(SourceError::Io(e));
+    let start = Local::now();
+    let mut stream = TcpStream::connect_timeout(addr, timeout).map_err(io_err)?;
+    *timings = timings.set_connect(Local::now() - start);
+    stream.set_read_timeout(Some(timeout)).map_err(io_err)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_err)?;
+
+    let mut out = (msg.len() as u16).to_be_bytes().to_vec();
+    out.extend_from_slice(msg);
+    stream.write_all(&out).map_err(io_err)?;
+
+    let mut len = [0; 2];
+    stream.read_exact(&mut len).map_err(io_err)?;
+    let mut resp = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut resp).map_err(io_err)?;
+    Ok(resp)
+}
+
+fn rcode_str(rcode: RCODE) -> String {
+    match rcode {
+        RCODE::NoError => "NOERROR".to_string(),
+        RCODE::FormatError => "FORMERR".to_string(),
+        RCODE::ServerFailure => "SERVFAIL".to_string(),
+        RCODE::NameError => "NXDOMAIN".to_string(),
+        RCODE::NotImplemented => "NOTIMP".to_string(),
+        RCODE::Refused => "REFUSED".to_string(),
+        rcode => format!("{:?}", rcode).to_uppercase(),
+    }
+}
+
+fn format_rdata(rdata: &RData) -> Option<String> {
+    Some(match rdata {
+        RData::A(a) => std::net::Ipv4Addr::from(a.address).to_string(),
+        RData::AAAA(a) => std::net::Ipv6Addr::from(a.address).to_string(),
+        RData::CNAME(n) => n.0.to_string(),
+        RData::NS(n) => n.0.to_string(),
+        RData::PTR(n) => n.0.to_string(),
+        RData::MX(mx) => format!("{} {}", mx.preference, mx.exchange),
+        RData::SRV(srv) => format!(
+            "{} {} {} {}",
+            srv.priority, srv.weight, srv.port, srv.target
+        ),
+        RData::SOA(soa) => format!("{} {} {}", soa.mname, soa.rname, soa.serial),
+        RData::TXT(txt) => String::try_from(txt.clone()).ok()?,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simple_dns::ResourceRecord;
+    use simple_dns::rdata::A;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    /// Build a response to `query` with `rcode` and an `A` record for each of `answers`.
+    fn respond(query: &[u8], rcode: RCODE, answers: &[Ipv4Addr], tc: bool) -> Vec<u8> {
+        let q = Packet::parse(query).unwrap();
+        let mut r = Packet::new_reply(q.id());
+        *r.rcode_mut() = rcode;
+        if tc {
+            r.set_flags(PacketFlag::TRUNCATION);
+        }
+        let name = q.questions[0].qname.clone();
+        r.questions.push(q.questions[0].clone());
+        for ip in answers {
+            r.answers.push(ResourceRecord::new(
+                name.clone(),
+                CLASS::IN,
+                60,
+                RData::A(A {
+                    address: u32::from(*ip),
+                }),
+            ));
+        }
+        r.build_bytes_vec().unwrap()
+    }
+
+    /// Start a UDP server that answers a single query. Returns the port.
+    fn serve_udp(rcode: RCODE, answers: &'static [Ipv4Addr], tc: bool) -> u16 {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = sock.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (n, peer) = sock.recv_from(&mut buf).unwrap();
+            let _ = sock.send_to(&respond(&buf[..n], rcode, answers, tc), peer);
+        });
+        port
+    }
+
+    /// Start a TCP server on `port`, or any port if 0, that answers a single query.
+    fn serve_tcp(port: u16, answers: &'static [Ipv4Addr]) -> u16 {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut len = [0; 2];
+            s.read_exact(&mut len).unwrap();
+            let mut query = vec![0; u16::from_be_bytes(len) as usize];
+            s.read_exact(&mut query).unwrap();
+            let resp = respond(&query, RCODE::NoError, answers, false);
+            s.write_all(&(resp.len() as u16).to_be_bytes()).unwrap();
+            s.write_all(&resp).unwrap();
+        });
+        port
+    }
+
+    fn probe(dns: Dns, port: u16) -> Host {
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+        dns.connect(1, &mut host, 1);
+        host
+    }
+
+    const ANSWERS: &[Ipv4Addr] = &[Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)];
+
+    #[test]
+    fn test_connect_success() {
+        let dns = Dns::new()
+            .set_name("example.com")
+            .set_record_type(RecordType::A)
+            .set_expect("192.0.2.2");
+        assert_eq!(dns.name(), NAME);
+
+        let host = probe(dns.clone(), serve_udp(RCODE::NoError, ANSWERS, false));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().first_byte().is_some());
+
+        let dns = dns.set_transport(Transport::Tcp);
+        let host = probe(dns, serve_tcp(0, ANSWERS));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().connect().is_some());
+    }
+
+    #[test]
+    fn test_connect_truncated() {
+        // Bind TCP first so the UDP server can share its port.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sock = UdpSocket::bind(("127.0.0.1", port)).unwrap();
+        drop(listener);
+        serve_tcp(port, ANSWERS);
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (n, peer) = sock.recv_from(&mut buf).unwrap();
+            let _ = sock.send_to(&respond(&buf[..n], RCODE::NoError, &[], true), peer);
+        });
+
+        let dns = Dns::new()
+            .set_name("example.com")
+            .set_record_type(RecordType::A)
+            .set_expect("192.0.2.1");
+        let host = probe(dns, port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+    }
+
+    #[test]
+    fn test_connect_rcode() {
+        let host = probe(Dns::new(), serve_udp(RCODE::ServerFailure, &[], false));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("SERVFAIL"), "{}", mr);
+        assert_eq!(mr.failure_kind(), Some(FailureKind::Dns));
+
+        let host = probe(Dns::new(), serve_udp(RCODE::Refused, &[], false));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("REFUSED"), "{}", mr);
+
+        // NXDOMAIN is an answer, so it only fails when answers are expected.
+        let host = probe(Dns::new(), serve_udp(RCODE::NameError, &[], false));
+        assert!(!host.metrics().result(1).unwrap().is_err());
+        let dns = Dns::new()
+            .set_record_type(RecordType::A)
+            .set_expect("192.0.2.1");
+        let host = probe(dns, serve_udp(RCODE::NameError, &[], false));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(
+            mr.to_string().contains("missing 192.0.2.1 (got none)"),
+            "{}",
+            mr
+        );
+    }
+
+    #[test]
+    fn test_connect_mismatch() {
+        let dns = Dns::new()
+            .set_name("example.com")
+            .set_record_type(RecordType::A)
+            .set_expect("192.0.2.1")
+            .set_expect("192.0.2.3");
+        let host = probe(dns, serve_udp(RCODE::NoError, ANSWERS, false));
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(
+            mr.to_string()
+                .contains("example.com A answer missing 192.0.2.3 (got 192.0.2.1, 192.0.2.2)"),
+            "{}",
+            mr
+        );
+    }
+
+    #[test]
+    fn test_connect_timeout() {
+        // A bound socket that never answers.
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let host = probe(Dns::new(), sock.local_addr().unwrap().port());
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("no response"), "{}", mr);
+        assert_eq!(mr.failure_kind(), Some(FailureKind::Timeout));
+    }
+
+    #[test]
+    fn test_record_type() {
+        assert_eq!("aaaa".parse::<RecordType>().unwrap(), RecordType::AAAA);
+        assert_eq!("SRV".parse::<RecordType>().unwrap().to_string(), "SRV");
+        assert_eq!(
+            "ANY".parse::<RecordType>().err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
+        );
+
+        assert_eq!(RecordType::AAAA.normalize("2001:DB8:0::1"), "2001:db8::1");
+        assert_eq!(
+            RecordType::NS.normalize("NS1.Example.com."),
+            "ns1.example.com"
+        );
+        assert_eq!(RecordType::TXT.normalize("v=spf1 -all"), "v=spf1 -all");
+    }
+}
@@ -0,0 +1,784 @@
+use chrono::Local;
+use regex::Regex;
+
+use crate::Host;
+use crate::connectors::{Connector, Reached, port_open, probe_addrs};
+use crate::core::error::*;
+use crate::core::metrics::{MetricsResult, Status, Timings};
+
+#[cfg(feature = "tls")]
+use crate::connectors::tls::{Handshake, Tls};
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+/// Name used to select the plain text [`Http`] probe.
+pub const NAME: &str = "http";
+/// Name used to select the [`Http`] probe over TLS.
+#[cfg(feature = "tls")]
+pub const HTTPS_NAME: &str = "https";
+
+/// Status codes accepted by default: any success or redirect.
+pub const DEFAULT_STATUS: RangeInclusive<u16> = 200..=399;
+/// Maximum number of response body bytes read for body expectations.
+const BODY_LIMIT: u64 = 1024 * 1024;
+/// Maximum length of the status line or a single header line.
+const LINE_LIMIT: u64 = 64 * 1024;
+
+/// Expectation on the response body.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum BodyMatch {
+    /// The body must contain this substring.
+    Contains(String),
+    /// The body must match this regular expression.
+    Regex(Regex),
+}
+
+impl BodyMatch {
+    /// Returns `true` if `body` satisfies this expectation.
+    pub fn is_match(&self, body: &str) -> bool {
+        match self {
+            BodyMatch::Contains(s) => body.contains(s.as_str()),
+            BodyMatch::Regex(re) => re.is_match(body),
+        }
+    }
+}
+
+impl std::fmt::Display for BodyMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyMatch::Contains(s) => write!(f, "{:?}", s),
+            BodyMatch::Regex(re) => write!(f, "/{}/", re.as_str()),
+        }
+    }
+}
+
+/// A parsed HTTP response.
+#[derive(Clone, Debug, Default)]
+struct Response {
+    status: u16,
+    body: Option<String>,
+}
+
+/// [`Connector`] that sends an HTTP/1.1 request over the connection and checks the response.
+///
+/// The attempt passes when the response status is in the expected range, the body matches the
+/// optional [`BodyMatch`] and the request to each address completes within the optional maximum
+/// time. The time from sending the request to the first byte of the response is recorded in the
+/// attempt's [`Timings`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::connectors::Connector;
+/// use port_tester::connectors::http::{BodyMatch, Http};
+///
+/// let probe = Http::new()
+///     .set_path("/healthz")
+///     .set_header("Accept", "application/json")
+///     .set_status(200..=299)
+///     .set_body(BodyMatch::Contains("\"status\":\"up\"".to_string()));
+///
+/// let mut host = Host::new("127.0.0.1", 8080).unwrap();
+/// probe.connect(1, &mut host, 5);
+/// ```
+#[derive(Debug)]
+pub struct Http {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    status: RangeInclusive<u16>,
+    body: Option<BodyMatch>,
+    max_time: Option<chrono::TimeDelta>,
+    #[cfg(feature = "tls")]
+    tls: Option<Tls>,
+}
+
+/// Defaults to `GET /` over plain text, accepting [`DEFAULT_STATUS`].
+impl Default for Http {
+    fn default() -> Self {
+        Http {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            headers: Vec::new(),
+            status: DEFAULT_STATUS,
+            body: None,
+            max_time: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl Connector for Http {
+    fn name(&self) -> &str {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            return HTTPS_NAME;
+        }
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut timings = Timings::default();
+        #[cfg(feature = "tls")]
+        let mut cert = None;
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            let addr_start = Local::now();
+            let deadline = self.deadline(timeout);
+            let res = (|| {
+                #[cfg(feature = "tls")]
+                if let Some(tls) = &self.tls {
+                    let mut hs = Handshake::default();
+                    let stream = tls.open(host, addr, timeout, &mut hs);
+                    timings = hs.timings();
+                    cert = hs.take_certificate();
+                    let stream = stream?;
+                    let sock = stream.sock.try_clone();
+                    let stream = deadline.wrap(stream, sock)?;
+                    let resp = self.exchange(stream, host, &mut timings)?;
+                    return self.check(&resp, Local::now() - addr_start);
+                }
+
+                timings = Timings::default();
+                let resp = self.request(host, addr, timeout, deadline, &mut timings)?;
+                self.check(&resp, Local::now() - addr_start)
+            })();
+            reached.note(&res, timings.connect().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            host.set_certificate(cert);
+        }
+        host.record_result(
+            reached.apply(
+                MetricsResult::new(seq, start, dur, status)
+                    .with_timings(timings)
+                    .with_addrs(addrs),
+            ),
+        );
+    }
+}
+
+impl Http {
+    /// Create a new plain text [`Http`] probe. See [`Http::default`].
+    pub fn new() -> Self {
+        Http::default()
+    }
+
+    /// Create a new [`Http`] probe that sends its request over TLS using `tls`.
+    #[cfg(feature = "tls")]
+    pub fn https(tls: Tls) -> Self {
+        Http {
+            tls: Some(tls),
+            ..Http::default()
+        }
+    }
+
+    /// Set the request method, e.g. `GET` or `HEAD`.
+    pub fn set_method(mut self, method: &str) -> Self {
+        self.method = method.to_uppercase();
+        self
+    }
+
+    /// Set the request path, including any query string.
+    pub fn set_path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        self
+    }
+
+    /// Add a request header. `Host`, `User-Agent`, `Accept` and `Connection` replace the defaults.
+    pub fn set_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Set the range of accepted response status codes.
+    pub fn set_status(mut self, status: RangeInclusive<u16>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Require the response body to satisfy `body`.
+    pub fn set_body(mut self, body: BodyMatch) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Fail the request to an address if it takes longer than `max_time` in total. The time left
+    /// caps the timeout of each read and write after connecting, so a stalled or slow server is
+    /// failed once `max_time` has passed.
+    pub fn set_max_time(mut self, max_time: chrono::TimeDelta) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    /// Build the request head for `host`.
+    fn request_head(&self, host: &Host) -> String {
+        let mut authority = match host.name().parse::<std::net::IpAddr>() {
+            Ok(std::net::IpAddr::V6(ip)) => format!("[{}]", ip),
+            _ => host.name().to_owned(),
+        };
+        if host.port() != self.default_port() {
+            authority = format!("{}:{}", authority, host.port());
+        }
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.path);
+        let defaults = [
+            ("Host", authority.as_str()),
+            (
+                "User-Agent",
+                concat!("port-tester/", env!("CARGO_PKG_VERSION")),
+            ),
+            ("Accept", "*/*"),
+            ("Connection", "close"),
+        ];
+        for (name, value) in defaults {
+            if !self
+                .headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+            {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        head
+    }
+
+    fn default_port(&self) -> u16 {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            return 443;
+        }
+        80
+    }
+
+    /// Returns the [`Deadline`] of an attempt to an address starting now, from `max_time`.
+    fn deadline(&self, timeout: u64) -> Deadline {
+        Deadline {
+            start: Instant::now(),
+            max: self.max_time.and_then(|m| m.to_std().ok()),
+            timeout: Duration::from_secs(timeout),
+        }
+    }
+
+    /// Connect to `addr` over plain text, send the request and read the response.
+    fn request(
+        &self,
+        host: &Host,
+        addr: &SocketAddr,
+        timeout: u64,
+        deadline: Deadline,
+        timings: &mut Timings,
+    ) -> Result<Response> {
+        let start = Local::now();
+        let sock =
+            port_open::open_addr(addr, timeout).map_err(|e| Error::new(SourceError::Io(e)))?;
+        *timings = timings.set_connect(Local::now() - start);
+
+        let to = Some(std::time::Duration::from_secs(timeout));
+        sock.set_read_timeout(to)
+            .and_then(|_| sock.set_write_timeout(to))
+            .map_err(|e| Error::new(SourceError::Io(e)))?;
+
+        let clone = sock.try_clone();
+        self.exchange(deadline.wrap(sock, clone)?, host, timings)
+    }
+
+    /// Send the request over `stream` and read the status line, headers and, if a body
+    /// expectation is set, the body.
+    fn exchange<S: Read + Write>(
+        &self,
+        mut stream: S,
+        host: &Host,
+        timings: &mut Timings,
+    ) -> Result<Response> {
+        let io_err = |e| Error::new(SourceError::Io(e));
+
+        stream
+            .write_all(self.request_head(host).as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(io_err)?;
+        let sent = Local::now();
+
+        let mut reader = BufReader::new(stream);
+        if reader.fill_buf().map_err(io_err)?.is_empty() {
+            return Err(Error::new(SourceError::Msg(
+                "connection closed before response".to_string(),
+            )));
+        }
+        *timings = timings.set_first_byte(Local::now() - sent);
+
+        let status_line = read_line(&mut reader)?;
+        let status = parse_status(&status_line)?;
+
+        let mut content_length = None;
+        let mut chunked = false;
+        loop {
+            let line = read_line(&mut reader)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse::<u64>().ok();
+                } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                    chunked = value.to_ascii_lowercase().contains("chunked");
+                }
+            }
+        }
+
+        let body = match &self.body {
+            Some(_) if self.method != "HEAD" => {
+                let bytes = if chunked {
+                    read_chunked(&mut reader)?
+                } else {
+                    read_to_limit(&mut reader, content_length.unwrap_or(BODY_LIMIT))?
+                };
+                Some(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            _ => None,
+        };
+
+        Ok(Response { status, body })
+    }
+
+    /// Check `resp` and the total attempt duration `dur` against the expectations.
+    fn check(&self, resp: &Response, dur: chrono::TimeDelta) -> Result<()> {
+        if !self.status.contains(&resp.status) {
+            return Err(Error::new(SourceError::Msg(format!(
+                "unexpected status {} (expected {}-{})",
+                resp.status,
+                self.status.start(),
+                self.status.end()
+            ))));
+        }
+
+        if let Some(body) = &self.body {
+            if !resp.body.as_deref().is_some_and(|b| body.is_match(b)) {
+                return Err(Error::new(SourceError::Msg(format!(
+                    "response body did not match {}",
+                    body
+                ))));
+            }
+        }
+
+        if let Some(max) = self.max_time {
+            if dur > max {
+                return Err(Error::new(SourceError::Msg(format!(
+                    "response took {}ms (max {}ms)",
+                    dur.num_milliseconds(),
+                    max.num_milliseconds()
+                ))));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Time limit of an attempt to a single address set by [`Http::set_max_time`].
+#[derive(Copy, Clone, Debug)]
+struct Deadline {
+    start: Instant,
+    /// Maximum time of the attempt, if set.
+    max: Option<Duration>,
+    /// Timeout of each read and write on the socket.
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// Wrap `stream`, whose socket is `sock`, so its reads and writes keep to the deadline.
+    fn wrap<S>(self, stream: S, sock: std::io::Result<TcpStream>) -> Result<Limited<S>> {
+        let sock = match self.max {
+            Some(_) => Some(sock.map_err(|e| Error::new(SourceError::Io(e)))?),
+            None => None,
+        };
+        Ok(Limited {
+            inner: stream,
+            sock,
+            deadline: self,
+        })
+    }
+
+    /// Returns the error of an attempt that ran past its deadline.
+    fn expired(&self, max: Duration) -> std::io::Error {
+        std::io::Error::new(
+            ErrorKind::TimedOut,
+            format!(
+                "response took {}ms (max {}ms)",
+                self.start.elapsed().as_millis(),
+                max.as_millis()
+            ),
+        )
+    }
+}
+
+/// Stream whose reads and writes fail once its [`Deadline`] has passed. Each read and write waits
+/// no longer than the smaller of the socket timeout and the time left, so a stalled or slow
+/// server is failed at the deadline rather than after a full timeout per read.
+struct Limited<S> {
+    inner: S,
+    /// Handle to the socket under `inner` whose timeouts are capped, if a deadline is set.
+    sock: Option<TcpStream>,
+    deadline: Deadline,
+}
+
+impl<S> Limited<S> {
+    /// Run `op` with the socket timeouts capped at the time left, failing if none is left.
+    fn limit<T>(&mut self, op: impl FnOnce(&mut S) -> std::io::Result<T>) -> std::io::Result<T> {
+        let (Some(sock), Some(max)) = (&self.sock, self.deadline.max) else {
+            return op(&mut self.inner);
+        };
+        let left = max.saturating_sub(self.deadline.start.elapsed());
+        if left.is_zero() {
+            return Err(self.deadline.expired(max));
+        }
+        let to = Some(left.min(self.deadline.timeout));
+        sock.set_read_timeout(to)?;
+        sock.set_write_timeout(to)?;
+        match op(&mut self.inner) {
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                match self.deadline.start.elapsed() >= max {
+                    true => Err(self.deadline.expired(max)),
+                    false => Err(e),
+                }
+            }
+            res => res,
+        }
+    }
+}
+
+impl<S: Read> Read for Limited<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.limit(|s| s.read(buf))
+    }
+}
+
+impl<S: Write> Write for Limited<S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.limit(|s| s.write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.limit(|s| s.flush())
+    }
+}
+
+/// Read a single CRLF or LF terminated line without the line ending.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut buf = Vec::new();
+    reader
+        .take(LINE_LIMIT)
+        .read_until(b'\n', &mut buf)
+        .map_err(|e| Error::new(SourceError::Io(e)))?;
+    if !buf.ends_with(b"\n") {
+        return Err(Error::new(SourceError::Msg(
+            "truncated response header".to_string(),
+        )));
+    }
+    Ok(String::from_utf8_lossy(&buf).trim_end().to_string())
+}
+
+/// Parse the status code from a status line like `HTTP/1.1 200 OK`.
+fn parse_status(line: &str) -> Result<u16> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next().map(str::parse::<u16>)) {
+        (Some(v), Some(Ok(code))) if v.starts_with("HTTP/") => Ok(code),
+        _ => Err(Error::new(SourceError::Msg(format!(
+            "invalid HTTP status line: {:?}",
+            line
+        )))),
+    }
+}
+
+/// Read up to `limit` bytes, stopping early at the end of the stream.
+fn read_to_limit<R: Read>(reader: &mut R, limit: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match reader.take(limit.min(BODY_LIMIT)).read_to_end(&mut buf) {
+        // TLS peers commonly close without a close_notify once the body is sent.
+        Err(e) if e.kind() != std::io::ErrorKind::UnexpectedEof => {
+            Err(Error::new(SourceError::Io(e)))
+        }
+        _ => Ok(buf),
+    }
+}
+
+/// Read and decode a chunked transfer encoded body.
+fn read_chunked<R: BufRead>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16)
+            .map_err(|_| Error::new(SourceError::Msg(format!("invalid chunk size: {:?}", line))))?;
+        if size == 0 || body.len() as u64 >= BODY_LIMIT {
+            break;
+        }
+        body.extend(read_to_limit(reader, size)?);
+        // Discard the CRLF after the chunk data.
+        read_line(reader)?;
+    }
+    Ok(body)
+}
+
+/// Parse a status range like `200`, `200-299` or `2xx`.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if `s` is not a valid range.
+pub fn parse_status_range(s: &str) -> Result<RangeInclusive<u16>> {
+    let err = || {
+        Error::new(SourceError::Msg(format!("Invalid status range: {}", s)))
+            .set_code(CODE_OPTIONS_ERROR)
+    };
+    let code = |c: &str| c.trim().parse::<u16>().map_err(|_| err());
+
+    let range = if let Some(class) = s.strip_suffix("xx") {
+        // Check the class before multiplying so a large one can't overflow.
+        let c = code(class)?;
+        if !(1..=9).contains(&c) {
+            return Err(err());
+        }
+        c * 100..=c * 100 + 99
+    } else if let Some((lo, hi)) = s.split_once('-') {
+        code(lo)?..=code(hi)?
+    } else {
+        let c = code(s)?;
+        c..=c
+    };
+
+    if range.is_empty() || !(100..=999).contains(range.start()) || *range.end() > 999 {
+        return Err(err());
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Start a server that accepts a single connection, reads the request head and writes
+    /// `response` after `delay`. Returns the port and a receiver for the request head.
+    fn serve(response: &'static str, delay: u64) -> (u16, std::sync::mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(sock);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let _ = tx.send(head);
+            std::thread::sleep(std::time::Duration::from_millis(delay));
+            let _ = reader.get_mut().write_all(response.as_bytes());
+        });
+        (port, rx)
+    }
+
+    fn probe(http: Http, port: u16) -> Host {
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+        http.connect(1, &mut host, 2);
+        host
+    }
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 15\r\n\r\n{\"status\":\"up\"}";
+
+    #[test]
+    fn test_connect_success() {
+        let (port, rx) = serve(OK, 0);
+        let http = Http::new()
+            .set_method("get")
+            .set_path("/healthz?full=1")
+            .set_header("X-Trace", "abc");
+        assert_eq!(http.name(), NAME);
+        let host = probe(http, port);
+
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().first_byte().is_some());
+        assert!(mr.timings().handshake().is_none());
+
+        let head = rx.recv().unwrap();
+        assert!(
+            head.starts_with("GET /healthz?full=1 HTTP/1.1\r\n"),
+            "{}",
+            head
+        );
+        assert!(head.contains(&format!("Host: 127.0.0.1:{}\r\n", port)));
+        assert!(head.contains("Connection: close\r\n"));
+        assert!(head.contains("X-Trace: abc\r\n"));
+    }
+
+    #[test]
+    fn test_connect_status() {
+        let (port, _) = serve("HTTP/1.1 503 Service Unavailable\r\n\r\n", 0);
+        let host = probe(Http::new(), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("unexpected status 503"), "{}", mr);
+
+        let (port, _) = serve("HTTP/1.1 503 Service Unavailable\r\n\r\n", 0);
+        let host = probe(Http::new().set_status(500..=599), port);
+        assert!(!host.metrics().result(1).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_connect_body() {
+        let (port, _) = serve(OK, 0);
+        let host = probe(Http::new().set_body(BodyMatch::Contains("up".into())), port);
+        assert!(!host.metrics().result(1).unwrap().is_err());
+
+        let (port, _) = serve(OK, 0);
+        let re = Regex::new(r#""status":\s*"down""#).unwrap();
+        let host = probe(Http::new().set_body(BodyMatch::Regex(re)), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("did not match"), "{}", mr);
+    }
+
+    #[test]
+    fn test_connect_chunked() {
+        let (port, _) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nstat\r\n4\r\nus=u\r\n1\r\np\r\n0\r\n\r\n",
+            0,
+        );
+        let host = probe(
+            Http::new().set_body(BodyMatch::Contains("status=up".into())),
+            port,
+        );
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+    }
+
+    #[test]
+    fn test_connect_max_time() {
+        // The stalled server is failed at the deadline, not after the 2s read timeout.
+        let (port, _) = serve(OK, 1000);
+        let max = chrono::TimeDelta::try_milliseconds(100).unwrap();
+        let host = probe(Http::new().set_max_time(max), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("max 100ms"), "{}", mr);
+        assert!(mr.duration() < chrono::TimeDelta::try_milliseconds(500).unwrap());
+        assert!(mr.timings().first_byte().is_none());
+
+        // A body sent a chunk at a time, each within the read timeout, is failed at the deadline.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+            let _ = sock.write_all(head.as_bytes());
+            for _ in 0..10 {
+                if sock.write_all(b"2\r\nup\r\n").is_err() {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            let _ = sock.write_all(b"0\r\n\r\n");
+        });
+        let max = chrono::TimeDelta::try_milliseconds(250).unwrap();
+        let http = Http::new()
+            .set_max_time(max)
+            .set_body(BodyMatch::Contains("up".into()));
+        let host = probe(http, port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("max 250ms"), "{}", mr);
+        assert!(mr.duration() < chrono::TimeDelta::try_milliseconds(600).unwrap());
+    }
+
+    #[test]
+    fn test_connect_not_http() {
+        let (port, _) = serve("SSH-2.0-OpenSSH_9.6\r\n", 0);
+        let host = probe(Http::new(), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(
+            mr.to_string().contains("invalid HTTP status line"),
+            "{}",
+            mr
+        );
+
+        let (port, _) = serve("", 0);
+        let host = probe(Http::new(), port);
+        assert!(host.metrics().result(1).unwrap().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "tls")]
+    fn test_connect_https() {
+        let (port, cert) = crate::connectors::tls::test::serve("127.0.0.1", OK.as_bytes());
+        let http = Http::https(crate::connectors::tls::test::trusting(cert))
+            .set_body(BodyMatch::Contains("up".into()));
+        assert_eq!(http.name(), HTTPS_NAME);
+        let host = probe(http, port);
+
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().handshake().is_some());
+        assert!(mr.timings().first_byte().is_some());
+        assert!(host.certificate().is_some());
+    }
+
+    #[test]
+    fn test_parse_status_range() {
+        assert_eq!(parse_status_range("200").unwrap(), 200..=200);
+        assert_eq!(parse_status_range("200-299").unwrap(), 200..=299);
+        assert_eq!(parse_status_range("3xx").unwrap(), 300..=399);
+        assert_eq!(parse_status_range("9xx").unwrap(), 900..=999);
+        assert!(parse_status_range("0xx").is_err());
+        assert!(parse_status_range("660xx").is_err());
+        assert!(parse_status_range("700xx").is_err());
+        assert!(parse_status_range("299-200").is_err());
+        assert!(parse_status_range("abc").is_err());
+        assert!(parse_status_range("1000").is_err());
+        assert_eq!(
+            parse_status_range("").err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
+        );
+    }
+
+    #[test]
+    fn test_request_head() {
+        let host = Host::new("::1", 80).unwrap();
+        let head = Http::new()
+            .set_method("head")
+            .set_header("host", "example.com")
+            .request_head(&host);
+        assert!(head.starts_with("HEAD / HTTP/1.1\r\n"));
+        assert!(head.contains("host: example.com\r\n"));
+        assert!(!head.contains("Host: [::1]"));
+        assert!(head.ends_with("\r\n\r\n"));
+
+        let head = Http::new().request_head(&host);
+        assert!(head.contains("Host: [::1]\r\n"));
+    }
+}
@@ -1 +1,479 @@
+//! Connection probes.
+//!
+//! Every probe implements the [`Connector`] trait and records its result into the target
+//! [`Host`] with [`Host::record`], so all probes report through the same [`Metrics`].
+//! The binaries look probes up by name in a [`Registry`].
+//!
+//! [`Metrics`]: crate::core::metrics::Metrics
+
+use crate::Host;
+use crate::core::addr::AddrResult;
+use crate::core::error::*;
+use crate::core::host::AddrStrategy;
+use crate::core::metrics::{FailureKind, MetricsResult};
+use chrono::Local;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+pub mod banner;
+pub mod dns;
+pub mod http;
 pub mod port_open;
+pub mod script;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod udp;
+
+/// Delay before racing the next address with [`race_addrs`], the Connection Attempt Delay
+/// recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// A probe that performs a single connection attempt against a [`Host`].
+///
+/// Implementations must record exactly one result per call into the host's metrics using
+/// [`Host::record`]. Probes are shared with the Ctrl-C handler thread, so they must be
+/// [`Send`] and [`Sync`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use chrono::Local;
+/// use port_tester::Host;
+/// use port_tester::connectors::{Connector, Registry};
+/// use port_tester::core::metrics::Status;
+///
+/// struct AlwaysUp;
+///
+/// impl Connector for AlwaysUp {
+///     fn name(&self) -> &str {
+///         "always_up"
+///     }
+///
+///     fn connect(&self, seq: u32, host: &mut Host, _timeout: u64) {
+///         host.record(seq, Local::now(), chrono::TimeDelta::zero(), Status::Success);
+///     }
+/// }
+///
+/// let mut registry = Registry::default();
+/// registry.register(Box::new(AlwaysUp));
+/// assert!(registry.get("always_up").is_some());
+/// ```
+pub trait Connector: Send + Sync {
+    /// Returns the name used to select this probe, e.g. with `--probe`.
+    fn name(&self) -> &str;
+
+    /// Perform attempt `seq` against `host` and record the result. `timeout` is in seconds.
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64);
+}
+
+/// Run `probe` against the addresses of `host` picked by its [`AddrStrategy`] for attempt
+/// `seq`, timing each address.
+///
+/// Addresses are probed in order until one passes, except with [`AddrStrategy::All`] where every
+/// address is probed and the attempt fails if any address fails. Returns the result of the last
+/// failing address, or of the last address probed if none failed, along with the
+/// [`AddrResult`] of each address probed. Probes record the results with
+/// [`MetricsResult::with_addrs`].
+///
+/// # Errors
+///
+/// Returns an error if every address fails, any address fails under [`AddrStrategy::All`] or
+/// `host` has no addresses to probe.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::Host;
+/// use port_tester::connectors::probe_addrs;
+///
+/// let host = Host::new("127.0.0.1", 80).unwrap();
+/// let (res, addrs) = probe_addrs(&host, 1, |addr| Ok(addr.port()));
+/// assert_eq!(res.unwrap(), 80);
+/// assert_eq!(addrs[0].addr(), *host.addr());
+/// ```
+///
+/// [`MetricsResult::with_addrs`]: crate::core::metrics::MetricsResult::with_addrs
+pub fn probe_addrs<T, F>(host: &Host, seq: u32, mut probe: F) -> (Result<T>, Vec<AddrResult>)
+where
+    F: FnMut(&SocketAddr) -> Result<T>,
+{
+    let all = host.addr_strategy() == AddrStrategy::All;
+    let mut results = Vec::new();
+    let mut passed = None;
+    let mut failed = None;
+    for addr in host.attempt_addrs(seq) {
+        let start = Local::now();
+        let res = probe(&addr);
+        results.push(AddrResult::new(
+            addr,
+            Local::now() - start,
+            res.as_ref().err(),
+        ));
+
+        match res {
+            Ok(v) => {
+                passed = Some(v);
+                if !all {
+                    break;
+                }
+            }
+            Err(e) => failed = Some(e),
+        }
+    }
+
+    let res = match (passed, failed) {
+        (Some(v), None) => Ok(v),
+        (Some(v), Some(_)) if !all => Ok(v),
+        (_, Some(e)) => Err(e),
+        (None, None) => Err(no_addrs()),
+    };
+    (res, results)
+}
+
+/// Race `probe` against the addresses of `host` picked by its [`AddrStrategy`] for attempt
+/// `seq`, as described by Happy Eyeballs (RFC 8305).
+///
+/// The first address is probed straight away and each following address is started `delay`
+/// after the one before it, or as soon as the one before it fails. The first address to pass
+/// wins and the probes still running are left to finish on their own, so `probe` must give up
+/// within its own timeout. Returns the result of the winner, or of the last address to fail,
+/// along with the [`AddrResult`] of each address that finished, in the order they finished.
+/// Probes record the results with [`MetricsResult::with_addrs`] and
+/// [`MetricsResult::with_race`].
+///
+/// # Errors
+///
+/// Returns an error if every address fails or `host` has no addresses to probe.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::Host;
+/// use port_tester::connectors::{CONNECTION_ATTEMPT_DELAY, race_addrs};
+///
+/// let host = Host::new("127.0.0.1", 80).unwrap();
+/// let (res, addrs) = race_addrs(&host, 1, CONNECTION_ATTEMPT_DELAY, |addr| Ok(addr.port()));
+/// assert_eq!(res.unwrap(), 80);
+/// assert_eq!(addrs[0].addr(), *host.addr());
+/// ```
+///
+/// [`MetricsResult::with_addrs`]: crate::core::metrics::MetricsResult::with_addrs
+/// [`MetricsResult::with_race`]: crate::core::metrics::MetricsResult::with_race
+pub fn race_addrs<T, F>(
+    host: &Host,
+    seq: u32,
+    delay: Duration,
+    probe: F,
+) -> (Result<T>, Vec<AddrResult>)
+where
+    T: Send + 'static,
+    F: Fn(&SocketAddr) -> Result<T> + Send + Sync + 'static,
+{
+    let probe = Arc::new(probe);
+    let (tx, rx) = mpsc::channel();
+    let mut pending: VecDeque<SocketAddr> = host.attempt_addrs(seq).into();
+    let mut running = 0;
+    let mut results = Vec::new();
+    let mut failed = None;
+    loop {
+        // Start the next address whenever the one before it failed or has run for `delay`.
+        if let Some(addr) = pending.pop_front() {
+            let tx = tx.clone();
+            let probe = Arc::clone(&probe);
+            std::thread::spawn(move || {
+                let start = Local::now();
+                let res = probe(&addr);
+                // Sending fails once the race is over, which drops the late result.
+                let _ = tx.send((addr, Local::now() - start, res));
+            });
+            running += 1;
+        }
+        if running == 0 {
+            break;
+        }
+
+        let finished = match pending.is_empty() {
+            true => rx.recv().ok(),
+            false => rx.recv_timeout(delay).ok(),
+        };
+        if let Some((addr, dur, res)) = finished {
+            running -= 1;
+            results.push(AddrResult::new(addr, dur, res.as_ref().err()));
+            match res {
+                Ok(v) => return (Ok(v), results),
+                Err(e) => failed = Some(e),
+            }
+        }
+    }
+
+    (Err(failed.unwrap_or_else(no_addrs)), results)
+}
+
+/// Returns the error for a host without addresses to probe.
+fn no_addrs() -> Error {
+    Error::new(SourceError::Io(std::io::Error::new(
+        std::io::ErrorKind::AddrNotAvailable,
+        "no addresses to connect to",
+    )))
+}
+
+/// Whether the last address to fail during an attempt had already reached the service, so a
+/// failed protocol step is recorded as [`FailureKind::Protocol`] rather than the kind of its
+/// error. [`probe_addrs`] fails an attempt with the error of the last failing address, so that
+/// address decides.
+#[derive(Debug, Default)]
+pub(crate) struct Reached(bool);
+
+impl Reached {
+    /// Note the result of probing an address, which reached the service if `reached`.
+    pub(crate) fn note<T>(&mut self, res: &Result<T>, reached: bool) {
+        if res.is_err() {
+            self.0 = reached;
+        }
+    }
+
+    /// Returns `result` with [`FailureKind::Protocol`] if the address it failed on reached the
+    /// service.
+    pub(crate) fn apply(&self, result: MetricsResult) -> MetricsResult {
+        match self.0 {
+            true => result.with_failure_kind(FailureKind::Protocol),
+            false => result,
+        }
+    }
+}
+
+/// A named collection of [`Connector`]s to select probes from.
+///
+/// [`Registry::default`] contains all of the built-in probes. Use [`Registry::new`] for an empty
+/// registry.
+pub struct Registry {
+    connectors: Vec<Box<dyn Connector>>,
+}
+
+/// Registers all built-in probes.
+impl Default for Registry {
+    fn default() -> Self {
+        let mut r = Registry::new();
+        r.register(Box::new(port_open::PortOpen));
+        #[cfg(feature = "tls")]
+        r.register(Box::new(tls::Tls::new()));
+        r.register(Box::new(http::Http::new()));
+        #[cfg(feature = "tls")]
+        r.register(Box::new(http::Http::https(tls::Tls::new())));
+        r.register(Box::new(udp::Udp::new()));
+        r.register(Box::new(dns::Dns::new()));
+        r.register(Box::new(banner::Banner::new()));
+        r.register(Box::new(script::Script::new()));
+        r
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry")
+            .field("connectors", &self.names())
+            .finish()
+    }
+}
+
+impl Registry {
+    /// Create an empty [`Registry`].
+    pub fn new() -> Self {
+        Registry {
+            connectors: Vec::new(),
+        }
+    }
+
+    /// Add a [`Connector`] to the registry. A connector with the same name is replaced.
+    pub fn register(&mut self, connector: Box<dyn Connector>) {
+        self.connectors.retain(|c| c.name() != connector.name());
+        self.connectors.push(connector);
+    }
+
+    /// Returns the [`Connector`] registered under `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&dyn Connector> {
+        self.connectors
+            .iter()
+            .find(|c| c.name() == name)
+            .map(|c| c.as_ref())
+    }
+
+    /// Returns the [`Connector`] registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`CODE_OPTIONS_ERROR`] if no connector is registered under `name`.
+    pub fn select(&self, name: &str) -> Result<&dyn Connector> {
+        self.get(name).ok_or_else(|| {
+            Error::new(SourceError::Msg(format!(
+                "Unknown probe: {} (available: {})",
+                name,
+                self.names().join(", ")
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        })
+    }
+
+    /// Returns the names of all registered connectors in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.connectors.iter().map(|c| c.name()).collect()
+    }
+
+    /// Removes the [`Connector`] registered under `name` and returns it, if any.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn Connector>> {
+        let i = self.connectors.iter().position(|c| c.name() == name)?;
+        Some(self.connectors.remove(i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::metrics::Status;
+    use chrono::Local;
+
+    struct Fake(&'static str);
+
+    impl Connector for Fake {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn connect(&self, seq: u32, host: &mut Host, _timeout: u64) {
+            host.record(
+                seq,
+                Local::now(),
+                chrono::TimeDelta::zero(),
+                Status::Success,
+            );
+        }
+    }
+
+    #[test]
+    fn test_default() {
+        let r = Registry::default();
+        assert_eq!(r.names()[0], port_open::NAME);
+        assert!(r.get(port_open::NAME).is_some());
+        #[cfg(feature = "tls")]
+        assert!(r.get(tls::NAME).is_some());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut r = Registry::new();
+        assert!(r.names().is_empty());
+
+        r.register(Box::new(Fake("fake")));
+        r.register(Box::new(Fake("other")));
+        r.register(Box::new(Fake("fake")));
+        assert_eq!(r.names(), vec!["other", "fake"]);
+
+        assert!(r.remove("other").is_some());
+        assert!(r.remove("other").is_none());
+        assert_eq!(r.names(), vec!["fake"]);
+    }
+
+    #[test]
+    fn test_select() {
+        let mut r = Registry::default();
+        r.register(Box::new(Fake("fake")));
+
+        let mut host = Host::default();
+        r.select("fake").unwrap().connect(1, &mut host, 1);
+        assert_eq!(host.metrics().success(), 1);
+
+        let e = r.select("missing").err().unwrap();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+        assert!(e.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_probe_addrs() {
+        // One address that answers and one that refuses.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let up: SocketAddr = ([127, 0, 0, 1], port).into();
+        let down: SocketAddr = ([127, 0, 0, 2], port).into();
+        let mut host = Host::from_addrs("example.com", vec![down, up]).unwrap();
+        let open = |addr: &SocketAddr| {
+            port_open::open_addr(addr, 1).map_err(|e| Error::new(SourceError::Io(e)))
+        };
+        let tried = |addrs: &[AddrResult]| {
+            addrs
+                .iter()
+                .map(|a| (a.addr(), a.is_err()))
+                .collect::<Vec<_>>()
+        };
+
+        // Sequential falls through to the address that answers.
+        let (res, addrs) = probe_addrs(&host, 1, open);
+        assert!(res.is_ok());
+        assert_eq!(tried(&addrs), vec![(down, true), (up, false)]);
+
+        // Round robin uses one address per attempt.
+        host.set_addr_strategy(AddrStrategy::RoundRobin);
+        let (res, addrs) = probe_addrs(&host, 1, open);
+        assert!(res.is_err());
+        assert_eq!(tried(&addrs), vec![(down, true)]);
+        let (res, addrs) = probe_addrs(&host, 2, open);
+        assert!(res.is_ok());
+        assert_eq!(tried(&addrs), vec![(up, false)]);
+
+        // All probes every address and fails if any fails.
+        host.set_addr_strategy(AddrStrategy::All);
+        let (res, addrs) = probe_addrs(&host, 1, open);
+        assert!(res.is_err());
+        assert_eq!(tried(&addrs), vec![(down, true), (up, false)]);
+
+        // The probe records the outcome of each address.
+        port_open::PortOpen.connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert_eq!(mr.addr(), Some(up));
+        assert_eq!(host.metrics().addr_summaries().len(), 2);
+        assert_eq!(host.metrics().addr_summaries()[0].failure(), 1);
+        assert_eq!(host.metrics().addr_summaries()[1].success(), 1);
+    }
+
+    #[test]
+    fn test_race_addrs() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let up: SocketAddr = ([127, 0, 0, 1], port).into();
+        let slow: SocketAddr = "[::1]:1".parse().unwrap();
+        let down: SocketAddr = ([127, 0, 0, 2], port).into();
+        let mut host = Host::from_addrs("example.com", vec![up, slow]).unwrap();
+        host.set_addr_strategy(AddrStrategy::HappyEyeballs);
+
+        // An address that hangs loses to the next one started after the delay.
+        let probe = move |addr: &SocketAddr| {
+            if *addr == slow {
+                std::thread::sleep(Duration::from_secs(2));
+            }
+            port_open::open_addr(addr, 1).map_err(|e| Error::new(SourceError::Io(e)))
+        };
+        let start = std::time::Instant::now();
+        let (res, addrs) = race_addrs(&host, 1, Duration::from_millis(50), probe);
+        assert!(res.is_ok());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(addrs.len(), 1);
+        assert_eq!(addrs[0].addr(), up);
+
+        // A failure starts the next address without waiting for the delay.
+        let host = Host::from_addrs("example.com", vec![down, up]).unwrap();
+        let open = |addr: &SocketAddr| {
+            port_open::open_addr(addr, 1).map_err(|e| Error::new(SourceError::Io(e)))
+        };
+        let (res, addrs) = race_addrs(&host, 1, Duration::from_secs(5), open);
+        assert!(res.is_ok());
+        assert_eq!(addrs.len(), 2);
+        assert!(addrs[0].is_err());
+
+        let host = Host::from_addrs("example.com", vec![down]).unwrap();
+        let (res, addrs) = race_addrs(&host, 1, CONNECTION_ATTEMPT_DELAY, open);
+        assert!(res.is_err());
+        assert_eq!(addrs.len(), 1);
+    }
+}
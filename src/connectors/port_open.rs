@@ -1,40 +1,67 @@
 use chrono::Local;
 
 use crate::Host;
+use crate::connectors::{CONNECTION_ATTEMPT_DELAY, Connector, probe_addrs, race_addrs};
 use crate::core::error::*;
-use crate::core::metrics::Status;
+use crate::core::host::AddrStrategy;
+use crate::core::metrics::{MetricsResult, Status, Timings};
 
-use std::net::TcpStream;
+use std::net::{SocketAddr, TcpStream};
+
+/// Name used to select the [`PortOpen`] probe.
+pub const NAME: &str = "port_open";
+
+/// [`Connector`] that only tests the ability to establish a TCP connection. See [`connect`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PortOpen;
+
+impl Connector for PortOpen {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        connect(seq, host, timeout);
+    }
+}
 
 // Fully open and close the port and report any errors. Does not test any protocol information other
-// than the ability to establish a TCP connection to the specified port.
+// than the ability to establish a TCP connection to the specified port. The addresses are raced
+// with race_addrs under AddrStrategy::HappyEyeballs and counted as a race on dual-stack hosts.
 pub fn connect(seq: u32, host: &mut Host, timeout: u64) {
     let start = Local::now();
-    let mut last_err = None;
-    let mut success = false;
-
-    // Attempt to connect to each resolved address until one succeeds.
-    for addr in host.addrs() {
-        match TcpStream::connect_timeout(addr, std::time::Duration::from_secs(timeout)) {
-            Ok(_) => {
-                success = true;
-                break;
-            }
-            Err(e) => last_err = Some(e),
-        }
-    }
-
+    let open = move |addr: &SocketAddr| {
+        open_addr(addr, timeout).map_err(|e| Error::new(SourceError::Io(e)))
+    };
+    let race = host.addr_strategy() == AddrStrategy::HappyEyeballs;
+    let (res, addrs) = match race {
+        true => race_addrs(host, seq, CONNECTION_ATTEMPT_DELAY, open),
+        false => probe_addrs(host, seq, open),
+    };
     let dur = Local::now() - start;
-    if success {
-        host.record(seq, start, dur, Status::Success);
-    } else {
-        host.record(
-            seq,
-            start,
-            dur,
-            Status::new(false, last_err.map(|e| Error::new(SourceError::Io(e)))),
-        );
+    let status = match res {
+        Ok(_) => Status::Success,
+        Err(e) => Status::Failure(Some(e)),
+    };
+    // The connect time is the time taken by the address that accepted the connection.
+    let mut timings = Timings::default();
+    if let Some(a) = addrs.iter().find(|a| !a.is_err()) {
+        timings = timings.set_connect(a.duration());
     }
+    let mut result = MetricsResult::new(seq, start, dur, status)
+        .with_timings(timings)
+        .with_addrs(addrs);
+    // Only a race between the address families tells anything about them.
+    if race && host.is_dual_stack() {
+        result = result.with_race();
+    }
+    host.record_result(result);
+}
+
+/// Open a TCP stream to `addr` with a connect timeout of `timeout` seconds. Probes that speak a
+/// protocol over TCP use this with [`probe_addrs`] to establish their stream.
+pub fn open_addr(addr: &SocketAddr, timeout: u64) -> std::io::Result<TcpStream> {
+    TcpStream::connect_timeout(addr, std::time::Duration::from_secs(timeout))
 }
 
 #[cfg(test)]
@@ -67,4 +94,70 @@ mod test {
         assert!(mr.is_some());
         assert!(mr.unwrap().is_err());
     }
+
+    #[test]
+    fn test_connector() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        let c = PortOpen;
+        assert_eq!(c.name(), NAME);
+        c.connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err());
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().dns().is_none());
+    }
+
+    #[test]
+    fn test_connect_all() {
+        use crate::core::metrics::FailureKind;
+        use crate::core::scan::PortState;
+
+        // One backend accepts the connection and the other refuses it.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let up: SocketAddr = ([127, 0, 0, 1], port).into();
+        let down: SocketAddr = ([127, 0, 0, 2], port).into();
+        let mut host = Host::from_addrs("127.0.0.1", vec![up, down]).unwrap();
+        host.set_addr_strategy(AddrStrategy::All);
+
+        connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert_eq!(mr.failure_kind(), Some(FailureKind::Refused));
+        assert_eq!(PortState::from(mr), PortState::Closed);
+    }
+
+    #[test]
+    fn test_connect_race() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let v4: SocketAddr = ([127, 0, 0, 1], port).into();
+        let mut host = Host::from_addrs("127.0.0.1", vec![v4]).unwrap();
+        host.set_addr_strategy(AddrStrategy::HappyEyeballs);
+
+        // Without both address families there is nothing to race.
+        connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err());
+        assert!(!mr.is_race());
+
+        // Nothing listens on the IPv6 loopback so IPv4 wins.
+        let v6: SocketAddr = ([0, 0, 0, 0, 0, 0, 0, 1], port).into();
+        let mut host = Host::from_addrs("localhost", vec![v6, v4]).unwrap();
+        host.set_addr_strategy(AddrStrategy::HappyEyeballs);
+        connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err());
+        assert!(mr.is_race());
+        assert_eq!(mr.addr(), Some(v4));
+        assert_eq!(host.metrics().races().ipv4_won(), 1);
+        assert!(
+            host.metrics()
+                .full_report()
+                .contains("happy eyeballs: 1 races")
+        );
+    }
 }
@@ -0,0 +1,482 @@
+use chrono::{Local, TimeDelta};
+use regex::bytes::Regex;
+
+use crate::Host;
+use crate::connectors::udp::parse_hex;
+use crate::connectors::{Connector, Reached, port_open, probe_addrs};
+use crate::core::error::*;
+use crate::core::metrics::{MetricsResult, Status, Timings};
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Name used to select the [`Script`] probe.
+pub const NAME: &str = "script";
+
+/// Maximum number of bytes buffered while waiting for an expect step to match.
+const BUFFER_LIMIT: usize = 64 * 1024;
+
+/// A single step of a [`Script`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Step {
+    /// Send these bytes.
+    Send(Vec<u8>),
+    /// Wait for the data received since the last match to match the pattern. Without a timeout
+    /// the attempt timeout is used.
+    Expect {
+        pattern: Regex,
+        timeout: Option<Duration>,
+    },
+    /// Close the connection.
+    Close,
+}
+
+impl Step {
+    /// Returns the script keyword of this step.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Step::Send(_) => "send",
+            Step::Expect { .. } => "expect",
+            Step::Close => "close",
+        }
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Send(b) => write!(f, "send {:?}", String::from_utf8_lossy(b)),
+            Step::Expect { pattern, .. } => write!(f, "expect /{}/", pattern.as_str()),
+            Step::Close => write!(f, "close"),
+        }
+    }
+}
+
+/// [`Connector`] that connects over TCP and runs a send/expect conversation.
+///
+/// A script has one step per line. Blank lines and lines starting with `#` are ignored.
+///
+/// - `send <text>` sends the text after the first space. `\r`, `\n`, `\t`, `\0`, `\\` and `\xNN`
+///   escapes are supported.
+/// - `send-hex <hex>` sends the bytes given as hex. See [`parse_hex`].
+/// - `expect <regex>` waits for the data received since the last match to match the regular
+///   expression.
+/// - `timeout <ms>` sets the time following `expect` steps may wait. Defaults to the attempt
+///   timeout.
+/// - `close` closes the connection. It must be the last step.
+///
+/// The attempt fails on the first step that fails. The error context names the failed step,
+/// how long it ran and the time taken by each completed step. The time from connecting to the
+/// first byte received is recorded as [`Timings::first_byte`]. An empty script only connects.
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::connectors::Connector;
+/// use port_tester::connectors::script::Script;
+///
+/// let probe: Script = "expect ^\\+OK\nsend QUIT\\r\\n\nexpect ^\\+OK\nclose"
+///     .parse()
+///     .unwrap();
+/// let mut host = Host::new("127.0.0.1", 110).unwrap();
+/// probe.connect(1, &mut host, 5);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Connector for Script {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut timings = Timings::default();
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            timings = Timings::default();
+            let res = self.run(addr, timeout, &mut timings);
+            reached.note(&res, timings.connect().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+
+        host.record_result(
+            reached.apply(
+                MetricsResult::new(seq, start, dur, status)
+                    .with_timings(timings)
+                    .with_addrs(addrs),
+            ),
+        );
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Script::parse(s)
+    }
+}
+
+impl Script {
+    /// Create a new, empty [`Script`] probe.
+    pub fn new() -> Self {
+        Script::default()
+    }
+
+    /// Parse a script. See [`Script`] for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`CODE_OPTIONS_ERROR`] naming the line of the first invalid step.
+    pub fn parse(script: &str) -> Result<Self> {
+        let mut steps = Vec::new();
+        let mut timeout = None;
+
+        for (i, line) in script.lines().enumerate() {
+            let err = |msg: String| {
+                Error::new(SourceError::Msg(format!(
+                    "Invalid script line {}: {}",
+                    i + 1,
+                    msg
+                )))
+                .set_code(CODE_OPTIONS_ERROR)
+            };
+
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            if matches!(steps.last(), Some(Step::Close)) {
+                return Err(err("close must be the last step".to_string()));
+            }
+
+            let (keyword, arg) = line
+                .trim_start()
+                .split_once(' ')
+                .unwrap_or((line.trim(), ""));
+            match keyword {
+                "send" => steps.push(Step::Send(unescape(arg).map_err(err)?)),
+                "send-hex" => {
+                    steps.push(Step::Send(parse_hex(arg).map_err(|e| err(e.to_string()))?))
+                }
+                "expect" if !arg.is_empty() => steps.push(Step::Expect {
+                    pattern: Regex::new(arg).map_err(|e| err(e.to_string()))?,
+                    timeout,
+                }),
+                "timeout" => {
+                    let ms = arg
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|_| err(format!("invalid timeout: {}", arg)))?;
+                    timeout = Some(Duration::from_millis(ms));
+                }
+                "close" if arg.trim().is_empty() => steps.push(Step::Close),
+                "expect" | "close" => return Err(err(format!("invalid {} step", keyword))),
+                _ => return Err(err(format!("unknown step: {}", keyword))),
+            }
+        }
+
+        Ok(Script { steps })
+    }
+
+    /// Returns the steps of the script.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Connect to `addr` and run each step, adding the step timings to the error context on
+    /// failure.
+    fn run(&self, addr: &SocketAddr, timeout: u64, timings: &mut Timings) -> Result<()> {
+        let start = Local::now();
+        let mut sock =
+            port_open::open_addr(addr, timeout).map_err(|e| Error::new(SourceError::Io(e)))?;
+        let connected = Local::now();
+        *timings = timings.set_connect(connected - start);
+
+        let mut conv = Conversation {
+            buf: Vec::new(),
+            connected,
+            timeout: Duration::from_secs(timeout),
+            timings,
+        };
+        let mut done: Vec<(&Step, TimeDelta)> = Vec::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let step_start = Local::now();
+            let res = conv.step(&mut sock, step);
+            let took = Local::now() - step_start;
+
+            if let Err(e) = res {
+                let completed = done
+                    .iter()
+                    .map(|(s, d)| format!("{} {}ms", s.kind(), d.num_milliseconds()))
+                    .collect::<Vec<_>>();
+                return Err(e.set_context(&format!(
+                    "script step {}/{} ({}) failed after {}ms; completed: {}",
+                    i + 1,
+                    self.steps.len(),
+                    step,
+                    took.num_milliseconds(),
+                    if completed.is_empty() {
+                        "none".to_string()
+                    } else {
+                        completed.join(", ")
+                    }
+                )));
+            }
+            done.push((step, took));
+        }
+
+        Ok(())
+    }
+}
+
+/// State shared by the steps of a running script.
+struct Conversation<'a> {
+    /// Data received but not yet consumed by an expect step.
+    buf: Vec<u8>,
+    connected: chrono::DateTime<Local>,
+    timeout: Duration,
+    timings: &'a mut Timings,
+}
+
+impl Conversation<'_> {
+    fn step(&mut self, sock: &mut TcpStream, step: &Step) -> Result<()> {
+        let io_err = |e| Error::new(SourceError::Io(e));
+        match step {
+            Step::Send(data) => sock.write_all(data).map_err(io_err),
+            Step::Close => sock.shutdown(Shutdown::Both).map_err(io_err),
+            Step::Expect { pattern, timeout } => {
+                self.expect(sock, pattern, timeout.unwrap_or(self.timeout))
+            }
+        }
+    }
+
+    /// Read until the buffered data matches `pattern`, then consume the data up to the end of
+    /// the match.
+    fn expect(&mut self, sock: &mut TcpStream, pattern: &Regex, timeout: Duration) -> Result<()> {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut chunk = [0; 4096];
+
+        loop {
+            if let Some(m) = pattern.find(&self.buf) {
+                self.buf.drain(..m.end());
+                return Ok(());
+            }
+
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() || self.buf.len() >= BUFFER_LIMIT {
+                return Err(self.no_match(pattern, timeout));
+            }
+            sock.set_read_timeout(Some(remaining))
+                .map_err(|e| Error::new(SourceError::Io(e)))?;
+
+            match sock.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(Error::new(SourceError::Msg(format!(
+                        "connection closed (received {:?})",
+                        String::from_utf8_lossy(&self.buf)
+                    ))));
+                }
+                Ok(n) => {
+                    if self.timings.first_byte().is_none() {
+                        *self.timings = self.timings.set_first_byte(Local::now() - self.connected);
+                    }
+                    self.buf.extend_from_slice(&chunk[..n]);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Err(self.no_match(pattern, timeout));
+                }
+                Err(e) => return Err(Error::new(SourceError::Io(e))),
+            }
+        }
+    }
+
+    fn no_match(&self, pattern: &Regex, timeout: Duration) -> Error {
+        Error::new(SourceError::Msg(format!(
+            "no match for /{}/ within {}ms (received {:?})",
+            pattern.as_str(),
+            timeout.as_millis(),
+            String::from_utf8_lossy(&self.buf)
+        )))
+    }
+}
+
+/// Replace the escapes allowed in a `send` step with the bytes they represent.
+fn unescape(s: &str) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'r') => out.push(b'\r'),
+            Some(b'n') => out.push(b'\n'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'0') => out.push(0),
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'x') => {
+                let hex = [bytes.next(), bytes.next()];
+                let byte = match hex {
+                    [Some(h), Some(l)] => std::str::from_utf8(&[h, l])
+                        .ok()
+                        .and_then(|h| u8::from_str_radix(h, 16).ok()),
+                    _ => None,
+                };
+                out.push(byte.ok_or_else(|| "invalid \\x escape".to_string())?);
+            }
+            Some(c) => return Err(format!("unknown escape: \\{}", c as char)),
+            None => return Err("trailing \\".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Start a minimal POP3-like server: it greets, answers `USER` with `+OK` and anything else
+    /// with `-ERR`, and closes after `QUIT`. Returns the port.
+    fn serve() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            s.write_all(b"+OK ready\r\n").unwrap();
+            let mut r = BufReader::new(s.try_clone().unwrap());
+            let mut line = String::new();
+            while r.read_line(&mut line).unwrap_or(0) > 0 {
+                match line.trim_end() {
+                    l if l.starts_with("USER") => s.write_all(b"+OK user\r\n").unwrap(),
+                    "QUIT" => {
+                        let _ = s.write_all(b"+OK bye\r\n");
+                        return;
+                    }
+                    _ => s.write_all(b"-ERR unknown\r\n").unwrap(),
+                }
+                line.clear();
+            }
+        });
+        port
+    }
+
+    fn probe(script: &str) -> Host {
+        let mut host = Host::new("127.0.0.1", serve()).unwrap();
+        script.parse::<Script>().unwrap().connect(1, &mut host, 1);
+        host
+    }
+
+    #[test]
+    fn test_connect_success() {
+        let host = probe(
+            "# POP3 login\n\
+             expect ^\\+OK\n\
+             send USER test\\r\\n\n\
+             expect \\+OK user\\r\\n\n\
+             send-hex 51 55 49 54 0d 0a\n\
+             expect \\+OK bye\n\
+             close\n",
+        );
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().first_byte().is_some());
+        assert_eq!(Script::new().name(), NAME);
+    }
+
+    #[test]
+    fn test_connect_fail() {
+        let host = probe("expect ^\\+OK\nsend PASS x\\r\\n\nexpect ^\\+OK\nclose");
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        let Status::Failure(Some(e)) = mr.status() else {
+            panic!("expected an error");
+        };
+        assert!(
+            e.context()
+                .starts_with("script step 3/4 (expect /^\\+OK/) failed after "),
+            "{}",
+            e.context()
+        );
+        let completed = Regex::new(r"completed: expect \d+ms, send \d+ms$").unwrap();
+        assert!(
+            completed.is_match(e.context().as_bytes()),
+            "{}",
+            e.context()
+        );
+        assert!(
+            e.to_string().contains(
+                "no match for /^\\+OK/ within 1000ms (received \" ready\\r\\n-ERR unknown\\r\\n\")"
+            ),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn test_connect_step_timeout() {
+        let host = probe("timeout 100\nexpect ^never");
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("within 100ms"), "{}", mr);
+        assert!(mr.duration() < chrono::TimeDelta::try_milliseconds(900).unwrap());
+    }
+
+    #[test]
+    fn test_connect_closed() {
+        let host = probe("send QUIT\\r\\n\nexpect \\+OK bye\\r\\n\nexpect more");
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.to_string().contains("script step 3/3"), "{}", mr);
+        assert!(mr.to_string().contains("connection closed"), "{}", mr);
+    }
+
+    #[test]
+    fn test_parse() {
+        let s = Script::parse(
+            "\n# comment\nsend a\\tb\\x00\\\\\nsend-hex 0d0a\ntimeout 250\nexpect ^ok\nclose\n",
+        )
+        .unwrap();
+        assert_eq!(s.steps().len(), 4);
+        assert!(matches!(&s.steps()[0], Step::Send(b) if b == b"a\tb\x00\\"));
+        assert!(matches!(&s.steps()[1], Step::Send(b) if b == b"\r\n"));
+        assert!(matches!(
+            &s.steps()[2],
+            Step::Expect { timeout: Some(t), .. } if *t == Duration::from_millis(250)
+        ));
+        assert_eq!(s.steps()[2].to_string(), "expect /^ok/");
+        assert!(matches!(s.steps()[3], Step::Close));
+
+        assert!(Script::parse("").unwrap().steps().is_empty());
+        let e = Script::parse("send ok\nbogus").err().unwrap();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+        assert!(
+            e.to_string().contains("line 2: unknown step: bogus"),
+            "{}",
+            e
+        );
+        assert!(Script::parse("send \\q").is_err());
+        assert!(Script::parse("send \\x4").is_err());
+        assert!(Script::parse("send-hex abc").is_err());
+        assert!(Script::parse("expect (").is_err());
+        assert!(Script::parse("expect").is_err());
+        assert!(Script::parse("timeout soon").is_err());
+        assert!(Script::parse("close\nsend ok").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use chrono::Local;
+use log::warn;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    StreamOwned,
+};
+
+use crate::Host;
+use crate::connectors::{Connector, Reached, port_open, probe_addrs};
+use crate::core::cert::Certificate;
+use crate::core::error::*;
+use crate::core::metrics::{MetricsResult, Status, Timings};
+
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Name used to select the [`Tls`] probe.
+pub const NAME: &str = "tls";
+
+/// A TLS session over the TCP stream opened by [`Tls::open`].
+pub type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Progress of a TLS connection made by [`Tls::open`].
+///
+/// Fields are filled in as each phase completes, so they are available even when a later phase
+/// fails.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Handshake {
+    timings: Timings,
+    certificate: Option<Certificate>,
+}
+
+impl Handshake {
+    /// Returns the connect and handshake [`Timings`].
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    /// Returns the leaf [`Certificate`] presented by the server, even if it failed to validate.
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificate.as_ref()
+    }
+
+    /// Takes the leaf [`Certificate`] out of the handshake.
+    pub fn take_certificate(&mut self) -> Option<Certificate> {
+        self.certificate.take()
+    }
+}
+
+/// [`Connector`] that completes a TLS handshake after the TCP connection.
+///
+/// SNI is taken from [`Host::name`]. The attempt fails if the handshake fails or the server's
+/// certificate chain does not validate against the trusted roots. The TCP connect time and the
+/// handshake time are recorded separately in the attempt's [`Timings`], and the server's leaf
+/// certificate is stored with [`Host::set_certificate`].
+///
+/// [`Tls::new`] trusts the platform's native root certificates, which are loaded on first use.
+/// Use [`Tls::with_roots`] to supply custom roots.
+#[derive(Debug, Default)]
+pub struct Tls {
+    roots: OnceLock<Arc<RootCertStore>>,
+}
+
+impl Connector for Tls {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut hs = Handshake::default();
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            hs = Handshake::default();
+            let res = self.open(host, addr, timeout, &mut hs).map(|mut stream| {
+                stream.conn.send_close_notify();
+                let _ = stream.conn.complete_io(&mut stream.sock);
+            });
+            reached.note(&res, hs.timings().connect().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+
+        host.set_certificate(hs.take_certificate());
+        host.record_result(
+            reached.apply(
+                MetricsResult::new(seq, start, dur, status)
+                    .with_timings(hs.timings())
+                    .with_addrs(addrs),
+            ),
+        );
+    }
+}
+
+impl Tls {
+    /// Create a new [`Tls`] probe that trusts the platform's native root certificates.
+    pub fn new() -> Self {
+        Tls::default()
+    }
+
+    /// Create a new [`Tls`] probe that trusts `roots`.
+    pub fn with_roots(roots: RootCertStore) -> Self {
+        Tls {
+            roots: OnceLock::from(Arc::new(roots)),
+        }
+    }
+
+    /// Returns the trusted roots, loading the native roots if needed.
+    pub fn roots(&self) -> Arc<RootCertStore> {
+        Arc::clone(self.roots.get_or_init(native_roots))
+    }
+
+    /// Open a TCP stream to `addr` and complete a TLS handshake over it, using [`Host::name`] as
+    /// the server name.
+    ///
+    /// The connect and handshake times and the server's leaf certificate are written to
+    /// `handshake` as they become available. `timeout` is in seconds and is applied to the
+    /// connect and to each read and write of the handshake.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TCP connection fails, [`Host::name`] is not a valid server name,
+    /// or the handshake fails, including when the certificate chain does not validate.
+    pub fn open(
+        &self,
+        host: &Host,
+        addr: &SocketAddr,
+        timeout: u64,
+        handshake: &mut Handshake,
+    ) -> Result<TlsStream> {
+        let server_name = ServerName::try_from(host.name().to_owned()).map_err(|e| {
+            Error::new(SourceError::Msg(format!(
+                "Invalid TLS server name: {}: {}",
+                host.name(),
+                e
+            )))
+        })?;
+        let verifier = Arc::new(CaptureVerifier::new(self.roots())?);
+        let config = client_config(verifier.clone());
+
+        let start = Local::now();
+        let mut sock =
+            port_open::open_addr(addr, timeout).map_err(|e| Error::new(SourceError::Io(e)))?;
+        let handshake_start = Local::now();
+        handshake.timings = handshake.timings.set_connect(handshake_start - start);
+
+        let to = Some(std::time::Duration::from_secs(timeout));
+        sock.set_read_timeout(to)
+            .and_then(|_| sock.set_write_timeout(to))
+            .map_err(|e| Error::new(SourceError::Io(e)))?;
+
+        let mut conn = ClientConnection::new(config, server_name)
+            .map_err(|e| Error::new(SourceError::Io(std::io::Error::other(e))))?;
+        let mut res = Ok(());
+        while conn.is_handshaking() {
+            if let Err(e) = conn.complete_io(&mut sock) {
+                res = Err(Error::new(SourceError::Io(e)));
+                break;
+            }
+        }
+        handshake.timings = handshake
+            .timings
+            .set_handshake(Local::now() - handshake_start);
+        handshake.certificate = verifier.leaf();
+
+        res.map(|_| StreamOwned::new(conn, sock))
+    }
+}
+
+/// Load the platform's native root certificates.
+fn native_roots() -> Arc<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    for e in &native.errors {
+        warn!("error loading native root certificates: {}", e);
+    }
+    let (added, ignored) = roots.add_parsable_certificates(native.certs);
+    if ignored > 0 {
+        warn!("ignored {} unparsable native root certificates", ignored);
+    }
+    if added == 0 {
+        warn!("no native root certificates found, all certificate chains will fail to validate");
+    }
+
+    Arc::new(roots)
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Build a [`ClientConfig`] using the ring crypto provider and `verifier`.
+fn client_config(verifier: Arc<CaptureVerifier>) -> Arc<ClientConfig> {
+    Arc::new(
+        ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .expect("ring provider supports the default protocol versions")
+            .dangerous()
+            .with_custom_certificate_verifier(verifier)
+            .with_no_client_auth(),
+    )
+}
+
+/// Verifies server certificates against a root store, keeping a copy of the leaf certificate so
+/// it can be inspected even when validation fails.
+#[derive(Debug)]
+struct CaptureVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    leaf: Mutex<Option<CertificateDer<'static>>>,
+}
+
+impl CaptureVerifier {
+    fn new(roots: Arc<RootCertStore>) -> Result<Self> {
+        let inner = WebPkiServerVerifier::builder_with_provider(roots, provider())
+            .build()
+            .map_err(|e| {
+                Error::new(SourceError::Msg(format!(
+                    "Invalid root certificates: {}",
+                    e
+                )))
+            })?;
+        Ok(CaptureVerifier {
+            inner,
+            leaf: Mutex::new(None),
+        })
+    }
+
+    /// Returns the parsed leaf certificate, if one was presented and could be parsed.
+    fn leaf(&self) -> Option<Certificate> {
+        let leaf = self.leaf.lock().unwrap();
+        leaf.as_ref()
+            .and_then(|der| Certificate::from_der(der).ok())
+    }
+}
+
+impl ServerCertVerifier for CaptureVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        *self.leaf.lock().unwrap() = Some(end_entity.clone().into_owned());
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use rustls::ServerConfig;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// Start a TLS server for `name` that accepts a single connection and writes `response` after
+    /// the handshake. Returns the port and the certificate clients should trust.
+    pub(crate) fn serve(name: &str, response: &'static [u8]) -> (u16, CertificateDer<'static>) {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_owned()]).unwrap();
+        let der = cert.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der()));
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![der.clone()], key)
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let conn = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(conn, sock);
+            let _ = stream.write_all(response);
+            let _ = stream.flush();
+            stream.conn.send_close_notify();
+            let _ = stream.conn.complete_io(&mut stream.sock);
+        });
+
+        (port, der)
+    }
+
+    /// Returns a [`Tls`] probe that only trusts `cert`.
+    pub(crate) fn trusting(cert: CertificateDer<'static>) -> Tls {
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).unwrap();
+        Tls::with_roots(roots)
+    }
+
+    #[test]
+    fn test_connect_success() {
+        let (port, cert) = serve("127.0.0.1", b"");
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        let tls = trusting(cert);
+        assert_eq!(tls.name(), NAME);
+        tls.connect(1, &mut host, 2);
+
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().handshake().is_some());
+        assert_eq!(host.certificate().unwrap().sans(), &["127.0.0.1"]);
+    }
+
+    #[test]
+    fn test_connect_untrusted() {
+        let (port, _) = serve("127.0.0.1", b"");
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        // Trust an unrelated certificate so the server's chain does not validate.
+        let other = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_owned()]).unwrap();
+        trusting(other.cert.der().clone()).connect(1, &mut host, 2);
+
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("certificate"), "{}", mr);
+        assert!(mr.timings().connect().is_some());
+        assert!(mr.timings().handshake().is_some());
+        // The leaf is still captured when the chain does not validate.
+        assert!(host.certificate().is_some());
+    }
+
+    #[test]
+    fn test_connect_name_mismatch() {
+        let (port, cert) = serve("example.com", b"");
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        trusting(cert).connect(1, &mut host, 2);
+        assert!(host.metrics().result(1).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_connect_not_tls() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let _ = sock.write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+        });
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        Tls::new().connect(1, &mut host, 2);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.timings().connect().is_some());
+    }
+
+    #[test]
+    fn test_connect_no_roots() {
+        let (port, _) = serve("127.0.0.1", b"");
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+
+        Tls::with_roots(RootCertStore::empty()).connect(1, &mut host, 2);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("root"), "{}", mr);
+    }
+
+    #[test]
+    fn test_connect_closed() {
+        let mut host = Host::new("127.0.0.1", 1).unwrap();
+
+        Tls::new().connect(1, &mut host, 1);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.timings().is_empty());
+        assert!(host.certificate().is_none());
+    }
+}
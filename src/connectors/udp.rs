@@ -0,0 +1,278 @@
+use chrono::Local;
+use regex::bytes::Regex;
+
+use crate::Host;
+use crate::connectors::{Connector, Reached, probe_addrs};
+use crate::core::error::*;
+use crate::core::metrics::{MetricsResult, Status, Timings};
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+
+/// Name used to select the [`Udp`] probe.
+pub const NAME: &str = "udp";
+
+/// Largest possible UDP payload.
+const MAX_DATAGRAM: usize = 65535;
+
+/// [`Connector`] that sends a datagram and waits for a reply.
+///
+/// The attempt passes when any reply is received within the timeout or, when an expectation is
+/// set with [`Udp::set_expect`], when a reply matches it. Each resolved address is tried in
+/// sequence until one replies. An ICMP port unreachable, reported by the OS as
+/// [`ErrorKind::ConnectionRefused`], fails the address immediately with a `port unreachable`
+/// error instead of waiting for the timeout. The time from sending the datagram to receiving the
+/// reply is recorded as [`Timings::first_byte`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::connectors::Connector;
+/// use port_tester::connectors::udp::{Udp, parse_hex};
+///
+/// let probe = Udp::new().set_payload(parse_hex("0d0a").unwrap());
+/// let mut host = Host::new("127.0.0.1", 514).unwrap();
+/// probe.connect(1, &mut host, 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Udp {
+    payload: Vec<u8>,
+    expect: Option<Regex>,
+}
+
+impl Connector for Udp {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn connect(&self, seq: u32, host: &mut Host, timeout: u64) {
+        let start = Local::now();
+        let mut timings = Timings::default();
+        let mut reached = Reached::default();
+        let (res, addrs) = probe_addrs(host, seq, |addr| {
+            timings = Timings::default();
+            let res = self.exchange(addr, timeout, &mut timings);
+            // A reply that doesn't match came from the service.
+            reached.note(&res, timings.first_byte().is_some());
+            res
+        });
+        let dur = Local::now() - start;
+
+        let status = match res {
+            Ok(_) => Status::Success,
+            Err(e) => Status::Failure(Some(e)),
+        };
+        host.record_result(
+            reached.apply(
+                MetricsResult::new(seq, start, dur, status)
+                    .with_timings(timings)
+                    .with_addrs(addrs),
+            ),
+        );
+    }
+}
+
+impl Udp {
+    /// Create a new [`Udp`] probe that sends an empty datagram and accepts any reply.
+    pub fn new() -> Self {
+        Udp::default()
+    }
+
+    /// Set the datagram payload to send.
+    pub fn set_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Require the reply to match `expect`.
+    pub fn set_expect(mut self, expect: Regex) -> Self {
+        self.expect = Some(expect);
+        self
+    }
+
+    /// Send the payload to `addr` and wait up to `timeout` seconds for a reply.
+    fn exchange(&self, addr: &SocketAddr, timeout: u64, timings: &mut Timings) -> Result<()> {
+        let io_err = |e| Error::new(SourceError::Io(e));
+        let bind: SocketAddr = if addr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+
+        let sock = UdpSocket::bind(bind).map_err(io_err)?;
+        sock.connect(addr).map_err(io_err)?;
+        sock.set_read_timeout(Some(std::time::Duration::from_secs(timeout)))
+            .map_err(io_err)?;
+
+        let sent = Local::now();
+        sock.send(&self.payload)
+            .map_err(|e| io_err(unreachable(e)))?;
+
+        let mut buf = vec![0; MAX_DATAGRAM];
+        let n = match sock.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(io_err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("no reply from {}", addr),
+                )));
+            }
+            Err(e) => return Err(io_err(unreachable(e))),
+        };
+        *timings = timings.set_first_byte(Local::now() - sent);
+
+        match &self.expect {
+            Some(re) if !re.is_match(&buf[..n]) => Err(Error::new(SourceError::Msg(format!(
+                "reply from {} did not match /{}/",
+                addr,
+                re.as_str()
+            )))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Replace a connection refused error, which is how an ICMP port unreachable is reported on a
+/// connected UDP socket, with a clearer `port unreachable` error of the same kind.
+fn unreachable(e: std::io::Error) -> std::io::Error {
+    match e.kind() {
+        ErrorKind::ConnectionRefused => {
+            std::io::Error::new(ErrorKind::ConnectionRefused, "port unreachable (ICMP)")
+        }
+        _ => e,
+    }
+}
+
+/// Parse a hex string into bytes. Whitespace, `:` separators and a leading `0x` are ignored.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if `s` contains non-hex characters or an odd
+/// number of digits.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::connectors::udp::parse_hex;
+///
+/// assert_eq!(parse_hex("0x de:ad BEEF").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+/// assert!(parse_hex("abc").is_err());
+/// ```
+pub fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    let err = || {
+        Error::new(SourceError::Msg(format!("Invalid hex payload: {}", s)))
+            .set_code(CODE_OPTIONS_ERROR)
+    };
+    let s = s.trim();
+    let digits: Vec<u8> = s
+        .strip_prefix("0x")
+        .unwrap_or(s)
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
+    if digits.len() % 2 != 0 {
+        return Err(err());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|p| u8::from_str_radix(p, 16).ok())
+                .ok_or_else(err)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Start a server that answers a single datagram with `reply`, or never answers if `reply`
+    /// is `None`. Returns the port.
+    fn serve(reply: Option<&'static [u8]>) -> u16 {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = sock.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buf = [0; 1024];
+            let (_, peer) = sock.recv_from(&mut buf).unwrap();
+            if let Some(r) = reply {
+                sock.send_to(r, peer).unwrap();
+            }
+        });
+        port
+    }
+
+    fn probe(udp: Udp, port: u16) -> Host {
+        let mut host = Host::new("127.0.0.1", port).unwrap();
+        udp.connect(1, &mut host, 1);
+        host
+    }
+
+    #[test]
+    fn test_connect_success() {
+        let port = serve(Some(b"pong"));
+        let udp = Udp::new().set_payload(b"ping".to_vec());
+        assert_eq!(udp.name(), NAME);
+        let host = probe(udp, port);
+
+        let mr = host.metrics().result(1).unwrap();
+        assert!(!mr.is_err(), "{}", mr);
+        assert!(mr.timings().first_byte().is_some());
+    }
+
+    #[test]
+    fn test_connect_expect() {
+        let port = serve(Some(b"\x00\x01pong"));
+        let re = Regex::new(r"(?-u)^\x00\x01po").unwrap();
+        let host = probe(Udp::new().set_expect(re), port);
+        assert!(!host.metrics().result(1).unwrap().is_err());
+
+        let port = serve(Some(b"nope"));
+        let re = Regex::new("pong").unwrap();
+        let host = probe(Udp::new().set_expect(re), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("did not match"), "{}", mr);
+    }
+
+    #[test]
+    fn test_connect_no_reply() {
+        let port = serve(None);
+        let host = probe(Udp::new(), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("no reply"), "{}", mr);
+        assert!(mr.timings().first_byte().is_none());
+    }
+
+    #[test]
+    fn test_connect_unreachable() {
+        // Bind and drop a socket to find a port with nothing listening.
+        let port = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let host = probe(Udp::new().set_payload(b"ping".to_vec()), port);
+        let mr = host.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert!(mr.to_string().contains("port unreachable"), "{}", mr);
+        assert!(mr.duration() < chrono::TimeDelta::try_milliseconds(900).unwrap());
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("").unwrap(), Vec::<u8>::new());
+        assert_eq!(parse_hex("00ff").unwrap(), vec![0x00, 0xff]);
+        assert_eq!(parse_hex("0x0A 0b:0C").unwrap(), vec![0x0a, 0x0b, 0x0c]);
+        assert!(parse_hex("0").is_err());
+        assert!(parse_hex("zz").is_err());
+        assert_eq!(
+            parse_hex("é1").err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
+        );
+    }
+}
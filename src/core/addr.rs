@@ -0,0 +1,587 @@
+//! Per-address results of connection attempts.
+//!
+//! A [`Host`] can resolve to several addresses and each attempt probes one or more of them,
+//! depending on its [`AddrStrategy`]. This module provides [`AddrResult`] for the outcome of each
+//! address probed during an attempt and [`AddrSummary`] for the totals of each address across
+//! attempts, so a single bad backend behind a name stands out. [`FamilyRaces`] counts which
+//! address family won the attempts raced with Happy Eyeballs. [`AddrChange`] records a change
+//! of the addresses when a host is resolved again during a run.
+//!
+//! [`Host`]: crate::Host
+//! [`AddrStrategy`]: crate::core::host::AddrStrategy
+
+use crate::Error;
+use crate::core::metrics::{FailureKind, Latencies, Latency};
+use chrono::Local;
+use std::net::SocketAddr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Outcome of probing a single address during an attempt.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::TimeDelta;
+/// use port_tester::core::addr::AddrResult;
+/// use port_tester::core::metrics::FailureKind;
+/// use port_tester::{Error, SourceError};
+///
+/// let addr = "192.0.2.1:443".parse().unwrap();
+/// let dur = TimeDelta::try_milliseconds(20).unwrap();
+/// let refused = Error::new(SourceError::Io(std::io::ErrorKind::ConnectionRefused.into()));
+/// let r = AddrResult::new(addr, dur, Some(&refused));
+/// assert!(r.is_err());
+/// assert_eq!(r.failure_kind(), Some(FailureKind::Refused));
+/// assert!(!AddrResult::new(addr, dur, None).is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct AddrResult {
+    addr: SocketAddr,
+    duration: chrono::TimeDelta,
+    failure_kind: Option<FailureKind>,
+    error: Option<String>,
+}
+
+impl AddrResult {
+    /// Create a new [`AddrResult`] for `addr` that took `duration` and failed with `error`, or
+    /// passed if `error` is `None`.
+    pub fn new(addr: SocketAddr, duration: chrono::TimeDelta, error: Option<&Error>) -> Self {
+        AddrResult {
+            addr,
+            duration,
+            failure_kind: error.map(FailureKind::from),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    /// Returns the address probed.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the time taken to probe the address.
+    pub fn duration(&self) -> chrono::TimeDelta {
+        self.duration
+    }
+
+    /// Returns `true` if the address failed.
+    pub fn is_err(&self) -> bool {
+        self.failure_kind.is_some()
+    }
+
+    /// Returns the [`FailureKind`] of the failure, or `None` if the address passed.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        self.failure_kind
+    }
+
+    /// Returns the error message of the failure, or `None` if the address passed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Serializable representation of an [`AddrResult`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AddrResultJSON {
+    /// Address probed.
+    addr: SocketAddr,
+    /// Time taken in milliseconds.
+    duration_ms: i64,
+    /// Class of the failure, if the address failed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    failure_kind: Option<FailureKind>,
+    /// Error message of the failure, if the address failed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    error: Option<String>,
+}
+
+impl AddrResultJSON {
+    /// Returns the address probed.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the time taken in milliseconds.
+    pub fn duration_ms(&self) -> i64 {
+        self.duration_ms
+    }
+
+    /// Returns the class of the failure, if the address failed.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        self.failure_kind
+    }
+
+    /// Returns the error message of the failure, if the address failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl From<&AddrResult> for AddrResultJSON {
+    fn from(r: &AddrResult) -> Self {
+        AddrResultJSON {
+            addr: r.addr,
+            duration_ms: r.duration.num_milliseconds(),
+            failure_kind: r.failure_kind,
+            error: r.error.clone(),
+        }
+    }
+}
+
+/// Totals of a single address across attempts.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::TimeDelta;
+/// use port_tester::core::addr::{AddrResult, AddrSummary};
+///
+/// let addr = "192.0.2.1:443".parse().unwrap();
+/// let mut summary = AddrSummary::new(addr);
+/// for ms in [10, 30] {
+///     let dur = TimeDelta::try_milliseconds(ms).unwrap();
+///     summary.record(&AddrResult::new(addr, dur, None));
+/// }
+/// assert_eq!(summary.success(), 2);
+/// assert_eq!(
+///     summary.report(),
+///     "attempts: 2, success: 2, fail: 0, failure rate: 0.00%, rtt min/avg/max = 10.000/20.000/30.000 ms"
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub struct AddrSummary {
+    addr: SocketAddr,
+    attempts: u32,
+    success: u32,
+    failure: u32,
+    /// Durations of the passing probes.
+    latencies: Latencies,
+}
+
+impl AddrSummary {
+    /// Create a new [`AddrSummary`] for `addr` with no attempts.
+    pub fn new(addr: SocketAddr) -> Self {
+        AddrSummary {
+            addr,
+            attempts: 0,
+            success: 0,
+            failure: 0,
+            latencies: Latencies::default(),
+        }
+    }
+
+    /// Returns the address.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the number of times the address was probed.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the number of times the address passed.
+    pub fn success(&self) -> u32 {
+        self.success
+    }
+
+    /// Returns the number of times the address failed.
+    pub fn failure(&self) -> u32 {
+        self.failure
+    }
+
+    /// Returns the failure rate as a percentage (0.0 – 100.0).
+    ///
+    /// Returns `0.0` when the address has not been probed.
+    pub fn failure_rate(&self) -> f64 {
+        if self.attempts > 0 {
+            (self.failure as f64 / self.attempts as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the [`Latency`] statistics of the passing probes, or `None` if there are none.
+    pub fn latency(&self) -> Option<Latency> {
+        self.latencies.latency()
+    }
+
+    /// Record the outcome of probing the address.
+    pub fn record(&mut self, result: &AddrResult) {
+        self.attempts += 1;
+        match result.is_err() {
+            true => self.failure += 1,
+            false => {
+                self.success += 1;
+                self.latencies.record(result.duration);
+            }
+        }
+    }
+
+    /// Returns the counts of the address on one line, followed by the minimum, average and
+    /// maximum round trip time if any probe passed.
+    ///
+    /// Output format: `"attempts: N, success: N, fail: N, failure rate: N.NN%, rtt min/avg/max =
+    /// a/b/c ms"`
+    pub fn report(&self) -> String {
+        let counts = format!(
+            "attempts: {}, success: {}, fail: {}, failure rate: {:.2}%",
+            self.attempts,
+            self.success,
+            self.failure,
+            self.failure_rate()
+        );
+        match self.latencies.min_avg_max_ms() {
+            Some((min, avg, max)) => format!(
+                "{}, rtt min/avg/max = {:.3}/{:.3}/{:.3} ms",
+                counts, min, avg, max
+            ),
+            None => counts,
+        }
+    }
+
+    /// Returns an owned [`AddrSummaryJSON`] snapshot of the address totals.
+    pub fn to_json(&self) -> AddrSummaryJSON {
+        AddrSummaryJSON {
+            addr: self.addr,
+            attempts: self.attempts,
+            success: self.success,
+            failure: self.failure,
+            failure_rate: self.failure_rate(),
+            latency: self.latency(),
+        }
+    }
+}
+
+/// Serializable representation of an [`AddrSummary`]. Produced by [`AddrSummary::to_json`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AddrSummaryJSON {
+    /// Address probed.
+    addr: SocketAddr,
+    /// Number of times the address was probed.
+    attempts: u32,
+    /// Number of times the address passed.
+    success: u32,
+    /// Number of times the address failed.
+    failure: u32,
+    /// Calculated failure rate (0.0 - 100.0).
+    failure_rate: f64,
+    /// Latency statistics of the passing probes, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    latency: Option<Latency>,
+}
+
+impl AddrSummaryJSON {
+    /// Returns the address probed.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the number of times the address was probed.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the number of times the address passed.
+    pub fn success(&self) -> u32 {
+        self.success
+    }
+
+    /// Returns the number of times the address failed.
+    pub fn failure(&self) -> u32 {
+        self.failure
+    }
+
+    /// Returns the failure rate as a percentage (0.0 – 100.0).
+    pub fn failure_rate(&self) -> f64 {
+        self.failure_rate
+    }
+
+    /// Returns the latency statistics of the passing probes, if any.
+    pub fn latency(&self) -> Option<&Latency> {
+        self.latency.as_ref()
+    }
+}
+
+/// Counts of the address family that won each attempt raced with
+/// [`AddrStrategy::HappyEyeballs`] against a dual-stack host.
+///
+/// A dual-stack host with a broken IPv6 path still passes its attempts when IPv4 wins the race,
+/// so the rate IPv6 lost at is the only sign of the breakage.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::addr::FamilyRaces;
+///
+/// let mut races = FamilyRaces::default();
+/// races.record(&"[2001:db8::1]:443".parse().unwrap());
+/// races.record(&"192.0.2.1:443".parse().unwrap());
+/// assert_eq!(races.ipv6_lost_rate(), 50.0);
+/// assert_eq!(
+///     races.report(),
+///     "happy eyeballs: 2 races, ipv6 won 1, ipv4 won 1, ipv6 lost 50.00%"
+/// );
+/// ```
+///
+/// [`AddrStrategy::HappyEyeballs`]: crate::core::host::AddrStrategy::HappyEyeballs
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub struct FamilyRaces {
+    /// Number of raced attempts that connected.
+    races: u32,
+    /// Number of races won by an IPv6 address.
+    ipv6_won: u32,
+    /// Number of races won by an IPv4 address.
+    ipv4_won: u32,
+}
+
+impl FamilyRaces {
+    /// Returns the number of raced attempts that connected.
+    pub fn races(&self) -> u32 {
+        self.races
+    }
+
+    /// Returns the number of races won by an IPv6 address.
+    pub fn ipv6_won(&self) -> u32 {
+        self.ipv6_won
+    }
+
+    /// Returns the number of races won by an IPv4 address.
+    pub fn ipv4_won(&self) -> u32 {
+        self.ipv4_won
+    }
+
+    /// Returns the percentage (0.0 – 100.0) of the races IPv6 lost to IPv4.
+    ///
+    /// Returns `0.0` when there were no races.
+    pub fn ipv6_lost_rate(&self) -> f64 {
+        if self.races > 0 {
+            (self.ipv4_won as f64 / self.races as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Record a race won by `winner`.
+    pub fn record(&mut self, winner: &SocketAddr) {
+        self.races += 1;
+        match winner.is_ipv6() {
+            true => self.ipv6_won += 1,
+            false => self.ipv4_won += 1,
+        }
+    }
+
+    /// Returns the race counts on one line.
+    ///
+    /// Output format: `"happy eyeballs: N races, ipv6 won N, ipv4 won N, ipv6 lost N.NN%"`
+    pub fn report(&self) -> String {
+        format!(
+            "happy eyeballs: {} races, ipv6 won {}, ipv4 won {}, ipv6 lost {:.2}%",
+            self.races,
+            self.ipv6_won,
+            self.ipv4_won,
+            self.ipv6_lost_rate()
+        )
+    }
+}
+
+/// A change of the addresses a host resolves to, seen when it was resolved again.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Local;
+/// use port_tester::core::addr::AddrChange;
+///
+/// let (a, b) = ("192.0.2.1:443".parse().unwrap(), "192.0.2.2:443".parse().unwrap());
+/// let change = AddrChange::new(Local::now(), vec![a], vec![a, b]);
+/// assert_eq!(change.added(), vec![b]);
+/// assert!(change.removed().is_empty());
+/// assert_eq!(change.report(), "addresses changed from 192.0.2.1:443 to 192.0.2.1:443, 192.0.2.2:443");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct AddrChange {
+    timestamp: chrono::DateTime<Local>,
+    from: Vec<SocketAddr>,
+    to: Vec<SocketAddr>,
+}
+
+/// Formats as the timestamp followed by the [`AddrChange::report`].
+impl std::fmt::Display for AddrChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.timestamp, self.report())
+    }
+}
+
+impl AddrChange {
+    /// Create a new [`AddrChange`] from the addresses `from` to the addresses `to`, seen at
+    /// `timestamp`.
+    pub fn new(
+        timestamp: chrono::DateTime<Local>,
+        from: Vec<SocketAddr>,
+        to: Vec<SocketAddr>,
+    ) -> Self {
+        AddrChange {
+            timestamp,
+            from,
+            to,
+        }
+    }
+
+    /// Returns the time the change was seen.
+    pub fn timestamp(&self) -> chrono::DateTime<Local> {
+        self.timestamp
+    }
+
+    /// Returns the addresses before the change.
+    pub fn from(&self) -> &[SocketAddr] {
+        &self.from
+    }
+
+    /// Returns the addresses after the change.
+    pub fn to(&self) -> &[SocketAddr] {
+        &self.to
+    }
+
+    /// Returns the addresses that were added by the change.
+    pub fn added(&self) -> Vec<SocketAddr> {
+        self.to
+            .iter()
+            .filter(|a| !self.from.contains(a))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the addresses that were removed by the change.
+    pub fn removed(&self) -> Vec<SocketAddr> {
+        self.from
+            .iter()
+            .filter(|a| !self.to.contains(a))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the change on one line.
+    ///
+    /// Output format: `"addresses changed from a, b to c"`
+    pub fn report(&self) -> String {
+        format!(
+            "addresses changed from {} to {}",
+            join(&self.from),
+            join(&self.to)
+        )
+    }
+
+    /// Returns an owned [`AddrChangeJSON`] snapshot of the change.
+    pub fn to_json(&self) -> AddrChangeJSON {
+        AddrChangeJSON {
+            timestamp: self.timestamp.to_rfc3339(),
+            from: self.from.clone(),
+            to: self.to.clone(),
+        }
+    }
+}
+
+/// Serializable representation of an [`AddrChange`]. Produced by [`AddrChange::to_json`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AddrChangeJSON {
+    /// RFC 3339 formatted time the change was seen.
+    timestamp: String,
+    /// Addresses before the change.
+    from: Vec<SocketAddr>,
+    /// Addresses after the change.
+    to: Vec<SocketAddr>,
+}
+
+impl AddrChangeJSON {
+    /// Returns the RFC 3339 timestamp string for when the change was seen.
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    /// Returns the addresses before the change.
+    pub fn from(&self) -> &[SocketAddr] {
+        &self.from
+    }
+
+    /// Returns the addresses after the change.
+    pub fn to(&self) -> &[SocketAddr] {
+        &self.to
+    }
+}
+
+/// Returns `addrs` joined with commas.
+fn join(addrs: &[SocketAddr]) -> String {
+    addrs
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SourceError;
+
+    #[test]
+    fn test_addr_summary() {
+        let addr: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let ms = |v| chrono::TimeDelta::try_milliseconds(v).unwrap();
+        let timeout = Error::new(SourceError::Io(std::io::ErrorKind::TimedOut.into()));
+
+        let mut summary = AddrSummary::new(addr);
+        assert_eq!(summary.failure_rate(), 0.0);
+        assert!(summary.latency().is_none());
+
+        summary.record(&AddrResult::new(addr, ms(5000), Some(&timeout)));
+        assert_eq!(
+            summary.report(),
+            "attempts: 1, success: 0, fail: 1, failure rate: 100.00%"
+        );
+
+        summary.record(&AddrResult::new(addr, ms(20), None));
+        assert_eq!(summary.attempts(), 2);
+        assert_eq!(summary.failure(), 1);
+        assert_eq!(summary.latency().unwrap().max_ms(), 20.0);
+
+        let json = summary.to_json();
+        assert_eq!(json.addr(), addr);
+        assert_eq!(json.failure_rate(), 50.0);
+        assert_eq!(json.latency().unwrap().min_ms(), 20.0);
+
+        let r = AddrResult::new(addr, ms(5000), Some(&timeout));
+        let json = AddrResultJSON::from(&r);
+        assert_eq!(json.duration_ms(), 5000);
+        assert_eq!(json.failure_kind(), Some(FailureKind::Timeout));
+        assert_eq!(json.error(), Some("timed out"));
+    }
+
+    #[test]
+    fn test_family_races() {
+        let mut races = FamilyRaces::default();
+        assert_eq!(races.ipv6_lost_rate(), 0.0);
+
+        races.record(&"[2001:db8::1]:443".parse().unwrap());
+        races.record(&"192.0.2.1:443".parse().unwrap());
+        races.record(&"192.0.2.1:443".parse().unwrap());
+        races.record(&"192.0.2.2:443".parse().unwrap());
+        assert_eq!(races.races(), 4);
+        assert_eq!(races.ipv6_won(), 1);
+        assert_eq!(races.ipv4_won(), 3);
+        assert_eq!(races.ipv6_lost_rate(), 75.0);
+    }
+}
@@ -0,0 +1,325 @@
+//! TLS certificate inspection.
+//!
+//! This module provides the [`Certificate`] struct, a parsed summary of a server's leaf
+//! certificate, and [`Expiry`] for classifying how close that certificate is to expiring.
+
+use crate::core::error::*;
+use chrono::{DateTime, TimeZone, Utc};
+use std::net::IpAddr;
+use x509_parser::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Owned, serializable snapshot of a [`Certificate`].
+///
+/// Produced by [`Certificate::to_json`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct CertificateJSON {
+    subject: String,
+    issuer: String,
+    sans: Vec<String>,
+    /// RFC 3339 formatted start of the validity period.
+    not_before: String,
+    /// RFC 3339 formatted end of the validity period.
+    not_after: String,
+    /// Whole days until `not_after` when the snapshot was taken. Negative once expired.
+    days_remaining: i64,
+}
+
+impl CertificateJSON {
+    /// Returns the subject distinguished name.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Returns the issuer distinguished name.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Returns the subject alternative names.
+    pub fn sans(&self) -> &[String] {
+        &self.sans
+    }
+
+    /// Returns the RFC 3339 timestamp the certificate becomes valid.
+    pub fn not_before(&self) -> &str {
+        &self.not_before
+    }
+
+    /// Returns the RFC 3339 timestamp the certificate expires.
+    pub fn not_after(&self) -> &str {
+        &self.not_after
+    }
+
+    /// Returns the whole days until expiry when the snapshot was taken.
+    pub fn days_remaining(&self) -> i64 {
+        self.days_remaining
+    }
+}
+
+/// Summary of an X.509 certificate presented by a server.
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::core::cert::Certificate;
+///
+/// # let der: Vec<u8> = Vec::new();
+/// let cert = Certificate::from_der(&der).expect("Failed to parse certificate");
+/// println!("{} expires {}", cert.subject(), cert.not_after());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Certificate {
+    subject: String,
+    issuer: String,
+    sans: Vec<String>,
+    not_before: DateTime<Utc>,
+    not_after: DateTime<Utc>,
+}
+
+impl Certificate {
+    /// Parse a DER encoded X.509 certificate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `der` is not a valid X.509 certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|e| Error::new(SourceError::Msg(format!("Invalid certificate: {}", e))))?;
+
+        let mut sans = Vec::new();
+        if let Ok(Some(ext)) = cert.subject_alternative_name() {
+            for name in &ext.value.general_names {
+                match name {
+                    GeneralName::DNSName(n) => sans.push(n.to_string()),
+                    GeneralName::IPAddress(b) => {
+                        if let Ok(ip) = <[u8; 4]>::try_from(*b) {
+                            sans.push(IpAddr::from(ip).to_string());
+                        } else if let Ok(ip) = <[u8; 16]>::try_from(*b) {
+                            sans.push(IpAddr::from(ip).to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Certificate {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+            not_before: to_utc(cert.validity().not_before),
+            not_after: to_utc(cert.validity().not_after),
+        })
+    }
+
+    /// Returns the subject distinguished name, e.g. `CN=example.com`.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Returns the issuer distinguished name.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Returns the DNS and IP subject alternative names.
+    pub fn sans(&self) -> &[String] {
+        &self.sans
+    }
+
+    /// Returns the start of the validity period.
+    pub fn not_before(&self) -> DateTime<Utc> {
+        self.not_before
+    }
+
+    /// Returns the end of the validity period.
+    pub fn not_after(&self) -> DateTime<Utc> {
+        self.not_after
+    }
+
+    /// Returns the whole days from `now` until the certificate expires. Negative once expired.
+    pub fn days_remaining(&self, now: DateTime<Utc>) -> i64 {
+        (self.not_after - now).num_days()
+    }
+
+    /// Classify the certificate's expiry at `now` against the `warn_days` and `crit_days`
+    /// thresholds.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use chrono::Utc;
+    /// use port_tester::core::cert::{Certificate, Expiry};
+    ///
+    /// # let der: Vec<u8> = Vec::new();
+    /// let cert = Certificate::from_der(&der).unwrap();
+    /// if cert.expiry(Utc::now(), 30, 7) != Expiry::Ok {
+    ///     println!("renew {}", cert.subject());
+    /// }
+    /// ```
+    pub fn expiry(&self, now: DateTime<Utc>, warn_days: u32, crit_days: u32) -> Expiry {
+        if now >= self.not_after {
+            return Expiry::Expired;
+        }
+
+        let days = self.days_remaining(now);
+        if days < crit_days as i64 {
+            Expiry::Critical
+        } else if days < warn_days as i64 {
+            Expiry::Warning
+        } else {
+            Expiry::Ok
+        }
+    }
+
+    /// Returns a multi-line report of the certificate details with the days remaining at `now`.
+    pub fn report(&self, now: DateTime<Utc>) -> String {
+        format!(
+            "subject: {}\nissuer: {}\nsans: {}\nnot_before: {}\nnot_after: {} ({} days)",
+            self.subject,
+            self.issuer,
+            self.sans.join(", "),
+            self.not_before.to_rfc3339(),
+            self.not_after.to_rfc3339(),
+            self.days_remaining(now)
+        )
+    }
+
+    /// Returns an owned [`CertificateJSON`] snapshot with the days remaining at `now`.
+    pub fn to_json(&self, now: DateTime<Utc>) -> CertificateJSON {
+        CertificateJSON {
+            subject: self.subject.clone(),
+            issuer: self.issuer.clone(),
+            sans: self.sans.clone(),
+            not_before: self.not_before.to_rfc3339(),
+            not_after: self.not_after.to_rfc3339(),
+            days_remaining: self.days_remaining(now),
+        }
+    }
+}
+
+fn to_utc(t: ASN1Time) -> DateTime<Utc> {
+    Utc.timestamp_opt(t.timestamp(), 0)
+        .single()
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Expiry classification of a [`Certificate`]. See [`Certificate::expiry`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum Expiry {
+    #[default]
+    Ok,
+    /// Expires within the warning threshold.
+    Warning,
+    /// Expires within the critical threshold.
+    Critical,
+    /// Already expired.
+    Expired,
+}
+
+impl std::fmt::Display for Expiry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Expiry {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Expiry::Ok => "ok",
+            Expiry::Warning => "warning",
+            Expiry::Critical => "critical",
+            Expiry::Expired => "expired",
+        }
+    }
+
+    /// Returns the exit code for this classification.
+    pub fn code(&self) -> i32 {
+        match self {
+            Expiry::Ok => CODE_SUCCESS,
+            Expiry::Warning => CODE_CERT_WARNING,
+            Expiry::Critical => CODE_CERT_CRITICAL,
+            Expiry::Expired => CODE_CERT_EXPIRED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn generate() -> Vec<u8> {
+        let mut params =
+            rcgen::CertificateParams::new(vec!["example.com".to_string(), "127.0.0.1".to_string()])
+                .unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "example.com");
+        params.not_before = rcgen::date_time_ymd(2020, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2030, 6, 15);
+        let key = rcgen::KeyPair::generate().unwrap();
+        params.self_signed(&key).unwrap().der().to_vec()
+    }
+
+    #[test]
+    fn test_from_der() {
+        let not_after = Utc.with_ymd_and_hms(2030, 6, 15, 0, 0, 0).unwrap();
+        let c = Certificate::from_der(&generate()).unwrap();
+        assert_eq!(c.subject(), "CN=example.com");
+        assert_eq!(c.issuer(), "CN=example.com");
+        assert_eq!(c.sans(), &["example.com", "127.0.0.1"]);
+        assert_eq!(
+            c.not_before(),
+            Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(c.not_after(), not_after);
+
+        assert!(Certificate::from_der(b"not a certificate").is_err());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let not_after = Utc.with_ymd_and_hms(2030, 6, 15, 0, 0, 0).unwrap();
+        let c = Certificate::from_der(&generate()).unwrap();
+        let days = |d| not_after - chrono::TimeDelta::days(d);
+
+        assert_eq!(c.days_remaining(days(40)), 40);
+        assert_eq!(c.expiry(days(40), 30, 7), Expiry::Ok);
+        assert_eq!(c.expiry(days(30), 30, 7), Expiry::Ok);
+        assert_eq!(c.expiry(days(29), 30, 7), Expiry::Warning);
+        assert_eq!(c.expiry(days(6), 30, 7), Expiry::Critical);
+        assert_eq!(c.expiry(days(0), 30, 7), Expiry::Expired);
+        assert_eq!(c.expiry(days(-3), 30, 7), Expiry::Expired);
+        assert_eq!(c.days_remaining(days(-3)), -3);
+
+        assert_eq!(Expiry::Ok.code(), CODE_SUCCESS);
+        assert_eq!(Expiry::Warning.code(), CODE_CERT_WARNING);
+        assert_eq!(Expiry::Critical.code(), CODE_CERT_CRITICAL);
+        assert_eq!(Expiry::Expired.code(), CODE_CERT_EXPIRED);
+        assert_eq!(Expiry::Critical.to_string(), "critical");
+    }
+
+    #[test]
+    fn test_report() {
+        let not_after = Utc.with_ymd_and_hms(2030, 6, 15, 0, 0, 0).unwrap();
+        let c = Certificate::from_der(&generate()).unwrap();
+        let now = not_after - chrono::TimeDelta::days(10);
+        assert_eq!(
+            c.report(now),
+            "subject: CN=example.com\nissuer: CN=example.com\nsans: example.com, 127.0.0.1\nnot_before: 2020-01-01T00:00:00+00:00\nnot_after: 2030-06-15T00:00:00+00:00 (10 days)"
+        );
+
+        let j = c.to_json(now);
+        assert_eq!(j.subject(), "CN=example.com");
+        assert_eq!(j.sans().len(), 2);
+        assert_eq!(j.not_after(), "2030-06-15T00:00:00+00:00");
+        assert_eq!(j.days_remaining(), 10);
+    }
+}
@@ -11,6 +11,15 @@ pub const CODE_RUNTIME_ERROR: i32 = 1;
 /// Exit code for when the command-line options are invalid.
 pub const CODE_OPTIONS_ERROR: i32 = 3;
 
+/// Exit code for when a certificate expires within the warning threshold.
+pub const CODE_CERT_WARNING: i32 = 4;
+
+/// Exit code for when a certificate expires within the critical threshold.
+pub const CODE_CERT_CRITICAL: i32 = 5;
+
+/// Exit code for when a certificate has expired.
+pub const CODE_CERT_EXPIRED: i32 = 6;
+
 /// Exit code for missing file permissions
 pub const CODE_PERMISSION_DENIED: i32 = 13;
 
@@ -4,14 +4,21 @@
 //! It handles DNS resolution of hostnames to [`SocketAddr`] and maintains the
 //! [`Metrics`] associated with connection attempts to that host.
 
+use crate::core::addr::{AddrChange, AddrChangeJSON};
+#[cfg(feature = "tls")]
+use crate::core::cert::{Certificate, CertificateJSON};
 use crate::core::error::*;
 use crate::core::metrics::Metrics;
 use crate::core::metrics::MetricsJSON;
+use crate::core::metrics::MetricsResult;
+use crate::core::metrics::MetricsResultJSON;
 use crate::core::metrics::Status;
+use crate::core::resolver::{Resolver, Srv};
 use chrono::Local;
 use dns_lookup::lookup_host;
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::str::FromStr;
 
 #[cfg(feature = "serde")]
 use serde::Serialize;
@@ -26,6 +33,13 @@ pub struct HostJSON {
     name: String,
     addrs: Vec<SocketAddr>,
     metrics: MetricsJSON,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    addr_changes: Vec<AddrChangeJSON>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    srv: Option<Srv>,
+    #[cfg(feature = "tls")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    certificate: Option<CertificateJSON>,
 }
 
 impl HostJSON {
@@ -36,6 +50,234 @@ impl HostJSON {
     }
 }
 
+/// Owned, serializable event of a [`Host`] for streaming output, one JSON object per line.
+///
+/// Serialized with a `type` of `attempt`, `summary` or `final` alongside the host's `name` and
+/// `port`. An attempt has the fields of [`MetricsResultJSON`] and a summary or final summary
+/// those of [`MetricsJSON`] without the per-attempt results. Produced by [`Host::attempt_json`],
+/// [`Host::summary_json`] and [`Host::final_json`].
+///
+/// [`MetricsResultJSON`]: crate::core::metrics::MetricsResultJSON
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum HostEventJSON {
+    /// Result of a single attempt.
+    Attempt {
+        name: String,
+        port: u16,
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        result: MetricsResultJSON,
+    },
+    /// Summary of the attempts so far.
+    Summary {
+        name: String,
+        port: u16,
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        metrics: MetricsJSON,
+    },
+    /// Summary of all attempts once they are complete.
+    Final {
+        name: String,
+        port: u16,
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        metrics: MetricsJSON,
+    },
+}
+
+impl HostEventJSON {
+    /// Serializes this event to a single line of JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string(&self).map_err(|e| Error::new(crate::SourceError::SerdeJson(e)))
+    }
+}
+
+/// How the resolved addresses of a [`Host`] are used across attempts.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::host::{AddrStrategy, Host};
+/// use std::net::SocketAddr;
+///
+/// let addrs: Vec<SocketAddr> = vec!["192.0.2.1:80".parse().unwrap(), "192.0.2.2:80".parse().unwrap()];
+/// let mut host = Host::from_addrs("example.com", addrs.clone()).unwrap();
+/// host.set_addr_strategy("round-robin".parse().unwrap());
+/// assert_eq!(host.attempt_addrs(1), vec![addrs[0]]);
+/// assert_eq!(host.attempt_addrs(2), vec![addrs[1]]);
+/// assert_eq!(host.attempt_addrs(3), vec![addrs[0]]);
+///
+/// host.set_addr_strategy(AddrStrategy::All);
+/// assert_eq!(host.attempt_addrs(1), addrs);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum AddrStrategy {
+    /// Try each address in order until one passes.
+    #[default]
+    Sequential,
+    /// Use the next address for each attempt, wrapping around to the first.
+    RoundRobin,
+    /// Probe every address on each attempt. The attempt fails if any address fails.
+    All,
+    /// Use a randomly chosen address for each attempt.
+    Random,
+    /// Race the addresses as described by Happy Eyeballs (RFC 8305). The addresses are ordered
+    /// IPv6 first, alternating between the families, and each is started a short delay after the
+    /// one before it until one connects. Probes that can't race try them in that order instead.
+    HappyEyeballs,
+}
+
+impl std::fmt::Display for AddrStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AddrStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "sequential" => Ok(AddrStrategy::Sequential),
+            "round-robin" => Ok(AddrStrategy::RoundRobin),
+            "all" => Ok(AddrStrategy::All),
+            "random" => Ok(AddrStrategy::Random),
+            "happy-eyeballs" => Ok(AddrStrategy::HappyEyeballs),
+            _ => Err(Error::new(SourceError::Msg(format!(
+                "Unsupported address strategy: {} (supported: sequential, round-robin, all, random, \
+                 happy-eyeballs)",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)),
+        }
+    }
+}
+
+impl AddrStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddrStrategy::Sequential => "sequential",
+            AddrStrategy::RoundRobin => "round-robin",
+            AddrStrategy::All => "all",
+            AddrStrategy::Random => "random",
+            AddrStrategy::HappyEyeballs => "happy-eyeballs",
+        }
+    }
+}
+
+/// Address family a hostname is resolved to. See [`resolve_family`] and [`Resolver`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum AddrFamily {
+    /// Keep both IPv4 and IPv6 addresses.
+    #[default]
+    Any,
+    /// Keep only IPv4 addresses.
+    Ipv4,
+    /// Keep only IPv6 addresses.
+    Ipv6,
+}
+
+impl std::fmt::Display for AddrFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl AddrFamily {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddrFamily::Any => "any",
+            AddrFamily::Ipv4 => "IPv4",
+            AddrFamily::Ipv6 => "IPv6",
+        }
+    }
+
+    /// Returns `true` if `ip` belongs to this family.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match self {
+            AddrFamily::Any => true,
+            AddrFamily::Ipv4 => ip.is_ipv4(),
+            AddrFamily::Ipv6 => ip.is_ipv6(),
+        }
+    }
+}
+
+/// When to resolve the name of a [`Host`] again during a run. See [`Host::resolve`].
+///
+/// Parses from a number of attempts, like `10`, or a duration with a unit of `ms`, `s`, `m` or
+/// `h`, like `30s` or `5m`.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeDelta};
+/// use port_tester::core::host::ResolveEvery;
+///
+/// let every: ResolveEvery = "10".parse().unwrap();
+/// assert_eq!(every, ResolveEvery::Attempts(10));
+/// assert!(!every.is_due(10, Local::now()));
+/// assert!(every.is_due(11, Local::now()));
+///
+/// let every: ResolveEvery = "5m".parse().unwrap();
+/// assert_eq!(every, ResolveEvery::Interval(TimeDelta::try_minutes(5).unwrap()));
+/// assert!(every.is_due(2, Local::now() - TimeDelta::try_minutes(6).unwrap()));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ResolveEvery {
+    /// Resolve again after this many attempts.
+    Attempts(u32),
+    /// Resolve again once this much time has passed since the last resolution.
+    Interval(chrono::TimeDelta),
+}
+
+impl FromStr for ResolveEvery {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let every = match s.trim().parse::<u32>() {
+            Ok(n) => Some(ResolveEvery::Attempts(n)).filter(|_| n > 0),
+            Err(_) => parse_duration(s.trim()).map(ResolveEvery::Interval),
+        };
+        every.ok_or_else(|| {
+            Error::new(SourceError::Msg(format!(
+                "Invalid resolve interval: {} (expected a number of attempts or a duration like \
+                 500ms, 30s, 5m or 1h)",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        })
+    }
+}
+
+impl ResolveEvery {
+    /// Returns `true` if the name should be resolved again before attempt `seq`, when it was last
+    /// resolved at `resolved`.
+    pub fn is_due(&self, seq: u32, resolved: chrono::DateTime<Local>) -> bool {
+        match self {
+            ResolveEvery::Attempts(n) => seq > 1 && (seq - 1) % n == 0,
+            ResolveEvery::Interval(d) => Local::now() - resolved >= *d,
+        }
+    }
+}
+
+/// Parses a positive duration with a unit of `ms`, `s`, `m` or `h`, like `30s`.
+fn parse_duration(s: &str) -> Option<chrono::TimeDelta> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let n: i64 = s[..split].parse().ok().filter(|n| *n > 0)?;
+    match &s[split..] {
+        "ms" => chrono::TimeDelta::try_milliseconds(n),
+        "s" => chrono::TimeDelta::try_seconds(n),
+        "m" => chrono::TimeDelta::try_minutes(n),
+        "h" => chrono::TimeDelta::try_hours(n),
+        _ => None,
+    }
+}
+
 /// Tracks connection information and metrics for port connection attempts to the user specified
 /// host.
 ///
@@ -57,8 +299,25 @@ pub struct Host {
     name: String,
     /// Resolved remote addresses to attempt.
     addrs: Vec<SocketAddr>,
+    /// How the addresses are used across attempts.
+    strategy: AddrStrategy,
+    /// Resolver used to resolve the name again, which keeps the address family and overrides.
+    resolver: Resolver,
+    /// Time the name was last resolved.
+    resolved: chrono::DateTime<Local>,
+    /// Time taken by the last resolution of the name, if it is not an IP address.
+    resolve_time: Option<chrono::TimeDelta>,
+    /// Whether the last resolution time is still to be added to the next recorded result.
+    resolve_pending: bool,
+    /// Changes of the addresses seen when the name was resolved again, oldest first.
+    changes: Vec<AddrChange>,
+    /// SRV record the host was discovered from, if any.
+    srv: Option<Srv>,
     /// Internal metrics storage for connection attempts.
     metrics: Metrics,
+    /// Leaf certificate presented by the host on the most recent TLS attempt.
+    #[cfg(feature = "tls")]
+    certificate: Option<Certificate>,
 }
 
 /// Defaults to an empty hostname and an unspecified IPv4 address (0.0.0.0) on port 0.
@@ -68,7 +327,16 @@ impl Default for Host {
         Host {
             name: "".to_string(),
             addrs: vec![SocketAddr::new(ip, 0)],
+            strategy: AddrStrategy::default(),
+            resolver: Resolver::default(),
+            resolved: Local::now(),
+            resolve_time: None,
+            resolve_pending: false,
+            changes: Vec::new(),
+            srv: None,
             metrics: Metrics::default(),
+            #[cfg(feature = "tls")]
+            certificate: None,
         }
     }
 }
@@ -83,14 +351,174 @@ impl Host {
     ///
     /// Returns an error if the host string is invalid or if DNS resolution fails.
     pub fn new(host: &str, port: u16) -> Result<Self> {
-        let addrs = resolve_addresses(host, port)?;
+        Host::with_family(host, port, AddrFamily::Any)
+    }
+
+    /// Create a new [`Host`] by resolving the provided hostname and port to addresses of
+    /// `family` only. See [`resolve_family`].
+    ///
+    /// The time taken to resolve a hostname is added to the [`Timings`] of the first recorded
+    /// result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if DNS resolution fails or the host has no addresses of `family`.
+    ///
+    /// [`Timings`]: crate::core::metrics::Timings
+    pub fn with_family(host: &str, port: u16, family: AddrFamily) -> Result<Self> {
+        Host::with_resolver(host, port, Resolver::new().set_family(family))
+    }
+
+    /// Create a new [`Host`] by resolving the provided hostname and port with `resolver`, which
+    /// is kept to resolve the name again. See [`Resolver::resolve`].
+    ///
+    /// The time taken to resolve a hostname is added to the [`Timings`] of the first recorded
+    /// result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `resolver` fails to resolve the host.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::host::Host;
+    /// use port_tester::core::resolver::Resolver;
+    ///
+    /// let resolver = Resolver::new().set_override("db.example.com:5432:127.0.0.1".parse().unwrap());
+    /// let host = Host::with_resolver("db.example.com", 5432, resolver).unwrap();
+    /// assert_eq!(host.addr(), &"127.0.0.1:5432".parse().unwrap());
+    /// ```
+    ///
+    /// [`Timings`]: crate::core::metrics::Timings
+    pub fn with_resolver(host: &str, port: u16, resolver: Resolver) -> Result<Self> {
+        let start = Local::now();
+        let addrs = resolver.resolve(host, port)?;
+        let took = Local::now() - start;
+        let mut host = Host::from_addrs(host, addrs)?;
+        host.resolver = resolver;
+        host.set_resolve_time(took);
+        Ok(host)
+    }
+
+    /// Create a [`Host`] for each target the SRV records of `name`, like
+    /// `_ldap._tcp.example.com`, point at, on the port of its record. The hosts are in the order
+    /// of [`Resolver::lookup_srv`] and each keeps its [`Srv`] record. The targets are resolved
+    /// with `resolver`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SRV lookup fails or a target can't be resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use port_tester::core::host::Host;
+    /// use port_tester::core::resolver::Resolver;
+    ///
+    /// for host in Host::from_srv("_ldap._tcp.example.com", &Resolver::new()).unwrap() {
+    ///     println!("{}:{} {}", host.name(), host.port(), host.srv().unwrap().report());
+    /// }
+    /// ```
+    pub fn from_srv(name: &str, resolver: &Resolver) -> Result<Vec<Self>> {
+        resolver
+            .lookup_srv(name)?
+            .into_iter()
+            .map(|srv| {
+                let mut host = Host::with_resolver(srv.target(), srv.port(), resolver.clone())
+                    .map_err(|mut e| {
+                        e.mut_context(&format!("SRV target of {}", name));
+                        e
+                    })?;
+                host.srv = Some(srv);
+                Ok(host)
+            })
+            .collect()
+    }
+
+    /// Create a new [`Host`] named `name` with already resolved `addrs`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `addrs` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::host::Host;
+    ///
+    /// let host = Host::from_addrs("example.com", vec!["192.0.2.1:443".parse().unwrap()]).unwrap();
+    /// assert_eq!(host.name(), "example.com");
+    /// assert_eq!(host.port(), 443);
+    /// assert!(Host::from_addrs("example.com", Vec::new()).is_err());
+    /// ```
+    pub fn from_addrs(name: &str, addrs: Vec<SocketAddr>) -> Result<Self> {
+        if addrs.is_empty() {
+            return Err(Error::new(SourceError::Msg(format!(
+                "No IP addresses found for hostname: {}",
+                name
+            )))
+            .set_code(CODE_RUNTIME_ERROR));
+        }
+
         Ok(Host {
-            name: host.to_owned(),
+            name: name.to_owned(),
             addrs,
+            strategy: AddrStrategy::default(),
+            resolver: Resolver::default(),
+            resolved: Local::now(),
+            resolve_time: None,
+            resolve_pending: false,
+            changes: Vec::new(),
+            srv: None,
             metrics: Metrics::default(),
+            #[cfg(feature = "tls")]
+            certificate: None,
         })
     }
 
+    /// Returns a new [`Host`] with the same name, resolved addresses, [`AddrStrategy`] and
+    /// [`Resolver`] on `port`, without resolving the hostname again. If the resolver has an
+    /// override for the name on `port`, its addresses are used instead. The new host starts with
+    /// empty metrics, no address changes and no SRV record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::host::Host;
+    ///
+    /// let host = Host::new("127.0.0.1", 22).unwrap();
+    /// let https = host.for_port(443);
+    /// assert_eq!(https.name(), "127.0.0.1");
+    /// assert_eq!(https.port(), 443);
+    /// ```
+    pub fn for_port(&self, port: u16) -> Host {
+        let addrs = self
+            .resolver
+            .lookup_override(&self.name, port)
+            .and_then(|addrs| filter_family(&self.name, addrs, self.resolver.family()).ok())
+            .unwrap_or_else(|| {
+                self.addrs
+                    .iter()
+                    .map(|a| SocketAddr::new(a.ip(), port))
+                    .collect()
+            });
+        Host {
+            name: self.name.clone(),
+            addrs,
+            strategy: self.strategy,
+            resolver: self.resolver.clone(),
+            resolved: self.resolved,
+            resolve_time: self.resolve_time,
+            resolve_pending: false,
+            changes: Vec::new(),
+            srv: None,
+            metrics: Metrics::default(),
+            #[cfg(feature = "tls")]
+            certificate: None,
+        }
+    }
+
     /// Returns the original hostname or IP string provided during creation.
     pub fn name(&self) -> &str {
         self.name.as_str()
@@ -116,6 +544,104 @@ impl Host {
         &self.addrs
     }
 
+    /// Returns `true` if the host resolved to both IPv6 and IPv4 addresses.
+    pub fn is_dual_stack(&self) -> bool {
+        self.addrs.iter().any(|a| a.is_ipv6()) && self.addrs.iter().any(|a| a.is_ipv4())
+    }
+
+    /// Resolve the name again with the host's [`Resolver`] and replace the addresses if they
+    /// changed. Returns the [`AddrChange`] if they did.
+    ///
+    /// The time taken is added to the [`Timings`] of the next recorded result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name can't be resolved. The addresses are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::host::Host;
+    ///
+    /// let mut host = Host::new("127.0.0.1", 22).unwrap();
+    /// assert!(host.resolve().unwrap().is_none());
+    /// assert!(host.addr_changes().is_empty());
+    /// ```
+    ///
+    /// [`Timings`]: crate::core::metrics::Timings
+    pub fn resolve(&mut self) -> Result<Option<&AddrChange>> {
+        let start = Local::now();
+        let addrs = self.resolver.resolve(&self.name, self.port());
+        self.resolved = Local::now();
+        self.set_resolve_time(self.resolved - start);
+        let addrs = addrs?;
+        let (mut old, mut new) = (self.addrs.clone(), addrs.clone());
+        old.sort();
+        new.sort();
+        if old == new {
+            return Ok(None);
+        }
+
+        let from = std::mem::replace(&mut self.addrs, addrs);
+        self.changes
+            .push(AddrChange::new(self.resolved, from, self.addrs.clone()));
+        Ok(self.changes.last())
+    }
+
+    /// Returns the SRV record the host was discovered from, if any. See [`Host::from_srv`].
+    pub fn srv(&self) -> Option<&Srv> {
+        self.srv.as_ref()
+    }
+
+    /// Returns the time the name was last resolved.
+    pub fn resolved_at(&self) -> chrono::DateTime<Local> {
+        self.resolved
+    }
+
+    /// Returns the time taken by the last resolution of the name, or `None` if the name is an IP
+    /// address or was never resolved.
+    pub fn resolve_time(&self) -> Option<chrono::TimeDelta> {
+        self.resolve_time
+    }
+
+    /// Keep the time taken to resolve the name to add to the next recorded result. IP addresses
+    /// are not resolved, so their time is not kept.
+    fn set_resolve_time(&mut self, took: chrono::TimeDelta) {
+        if self.name.parse::<IpAddr>().is_err() {
+            self.resolve_time = Some(took);
+            self.resolve_pending = true;
+        }
+    }
+
+    /// Returns the changes of the addresses seen by [`Host::resolve`], oldest first.
+    pub fn addr_changes(&self) -> &[AddrChange] {
+        &self.changes
+    }
+
+    /// Returns the [`AddrStrategy`] used to pick addresses for each attempt.
+    pub fn addr_strategy(&self) -> AddrStrategy {
+        self.strategy
+    }
+
+    /// Set the [`AddrStrategy`] used to pick addresses for each attempt.
+    pub fn set_addr_strategy(&mut self, strategy: AddrStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Returns the addresses to probe, in order, for attempt `seq` under the host's
+    /// [`AddrStrategy`].
+    pub fn attempt_addrs(&self, seq: u32) -> Vec<SocketAddr> {
+        match self.strategy {
+            AddrStrategy::Sequential | AddrStrategy::All => self.addrs.clone(),
+            AddrStrategy::RoundRobin => {
+                let i = seq.saturating_sub(1) as usize % self.addrs.len();
+                vec![self.addrs[i]]
+            }
+            AddrStrategy::Random => vec![self.addrs[random_index(self.addrs.len())]],
+            AddrStrategy::HappyEyeballs => interleave_families(&self.addrs),
+        }
+    }
+
     /// Returns a reference to the internal [`Metrics`].
     pub fn metrics(&self) -> &Metrics {
         &self.metrics
@@ -126,6 +652,18 @@ impl Host {
         &mut self.metrics
     }
 
+    /// Returns the leaf [`Certificate`] presented on the most recent TLS attempt, if any.
+    #[cfg(feature = "tls")]
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificate.as_ref()
+    }
+
+    /// Set the leaf [`Certificate`] presented by the host.
+    #[cfg(feature = "tls")]
+    pub fn set_certificate(&mut self, certificate: Option<Certificate>) {
+        self.certificate = certificate;
+    }
+
     /// Record a connection attempt result into the host's metrics.
     ///
     /// # Examples
@@ -147,7 +685,25 @@ impl Host {
         duration: chrono::TimeDelta,
         status: Status,
     ) {
-        self.metrics.record(seq, timestamp, duration, status);
+        self.record_result(MetricsResult::new(seq, timestamp, duration, status));
+    }
+
+    /// Record a prebuilt [`MetricsResult`] into the host's metrics. See
+    /// [`Metrics::record_result`].
+    ///
+    /// The time taken to resolve the name since the last recorded result, if any, is added to the
+    /// result's [`Timings`] as the dns phase.
+    ///
+    /// [`Timings`]: crate::core::metrics::Timings
+    pub fn record_result(&mut self, mut result: MetricsResult) {
+        if self.resolve_pending {
+            if let Some(took) = self.resolve_time {
+                let timings = result.timings().set_dns(took);
+                result = result.with_timings(timings);
+            }
+            self.resolve_pending = false;
+        }
+        self.metrics.record_result(result);
     }
 
     /// Returns an owned [`HostJSON`] snapshot of this host and its current metrics.
@@ -157,6 +713,13 @@ impl Host {
             name: self.name.clone(),
             addrs: self.addrs.clone(),
             metrics: self.metrics.to_json(),
+            addr_changes: self.changes.iter().map(AddrChange::to_json).collect(),
+            srv: self.srv.clone(),
+            #[cfg(feature = "tls")]
+            certificate: self
+                .certificate
+                .as_ref()
+                .map(|c| c.to_json(chrono::Utc::now())),
         })
     }
 
@@ -165,6 +728,99 @@ impl Host {
     pub fn to_json_string(&self) -> Result<String> {
         self.to_json()?.to_json_string()
     }
+
+    /// Returns a [`HostEventJSON::Attempt`] of the result of attempt `seq`, or `None` if it
+    /// wasn't recorded.
+    pub fn attempt_json(&self, seq: u32) -> Option<HostEventJSON> {
+        self.metrics.result(seq).map(|mr| HostEventJSON::Attempt {
+            name: self.name.clone(),
+            port: self.port(),
+            result: MetricsResultJSON::from(mr),
+        })
+    }
+
+    /// Returns a [`HostEventJSON::Summary`] of the attempts so far.
+    pub fn summary_json(&self) -> HostEventJSON {
+        HostEventJSON::Summary {
+            name: self.name.clone(),
+            port: self.port(),
+            metrics: self.metrics.to_summary_json(),
+        }
+    }
+
+    /// Returns a [`HostEventJSON::Final`] summary of all attempts.
+    pub fn final_json(&self) -> HostEventJSON {
+        HostEventJSON::Final {
+            name: self.name.clone(),
+            port: self.port(),
+            metrics: self.metrics.to_summary_json(),
+        }
+    }
+}
+
+/// Returns a random index below `len`.
+fn random_index(len: usize) -> usize {
+    use std::hash::{BuildHasher, Hasher};
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_u128(Local::now().timestamp_nanos_opt().unwrap_or_default() as u128);
+    h.finish() as usize % len
+}
+
+/// Returns `addrs` ordered IPv6 first, alternating between the address families as described by
+/// RFC 8305 section 4. The order within each family is kept.
+fn interleave_families(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.iter().partition(|a| a.is_ipv6());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    for i in 0..v6.len().max(v4.len()) {
+        ordered.extend(v6.get(i));
+        ordered.extend(v4.get(i));
+    }
+    ordered
+}
+
+/// Resolves a hostname or IP string and a port into one or more [`SocketAddr`]s of `family`.
+///
+/// # Errors
+///
+/// Returns an error if [`resolve_addresses`] fails, or with [`CODE_OPTIONS_ERROR`] if none of
+/// the addresses are of `family`.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::host::{AddrFamily, resolve_family};
+///
+/// let addrs = resolve_family("::1", 80, AddrFamily::Ipv6).unwrap();
+/// assert_eq!(addrs, vec!["[::1]:80".parse().unwrap()]);
+/// assert!(resolve_family("::1", 80, AddrFamily::Ipv4).is_err());
+/// ```
+pub fn resolve_family(host: &str, port: u16, family: AddrFamily) -> Result<Vec<SocketAddr>> {
+    filter_family(host, resolve_addresses(host, port)?, family)
+}
+
+/// Keeps the addresses of `host` that are of `family`, or errors with [`CODE_OPTIONS_ERROR`] if
+/// there are none.
+pub(crate) fn filter_family(
+    host: &str,
+    addrs: Vec<SocketAddr>,
+    family: AddrFamily,
+) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = addrs
+        .into_iter()
+        .filter(|a| family.contains(&a.ip()))
+        .collect();
+    if addrs.is_empty() {
+        let family = match family {
+            AddrFamily::Any => "IP",
+            f => f.as_str(),
+        };
+        return Err(Error::new(SourceError::Msg(format!(
+            "No {} addresses found for hostname: {}",
+            family, host
+        )))
+        .set_code(CODE_OPTIONS_ERROR));
+    }
+    Ok(addrs)
 }
 
 /// Resolves a hostname or IP string and a port into one or more [`SocketAddr`]s.
@@ -262,6 +918,50 @@ mod test {
         assert_eq!(h.addr().port(), 443);
     }
 
+    #[test]
+    fn test_addr_strategy() {
+        let addrs: Vec<SocketAddr> = (1..=3)
+            .map(|i| SocketAddr::new(IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, i)), 80))
+            .collect();
+        let mut h = Host::from_addrs("example.com", addrs.clone()).unwrap();
+        assert!(!h.is_dual_stack());
+        assert_eq!(h.addr_strategy(), AddrStrategy::Sequential);
+        assert_eq!(h.attempt_addrs(1), addrs);
+
+        h.set_addr_strategy(AddrStrategy::RoundRobin);
+        let used: Vec<SocketAddr> = (1..=4).flat_map(|seq| h.attempt_addrs(seq)).collect();
+        assert_eq!(used, vec![addrs[0], addrs[1], addrs[2], addrs[0]]);
+        assert_eq!(h.for_port(443).attempt_addrs(2)[0].ip(), addrs[1].ip());
+
+        h.set_addr_strategy(AddrStrategy::Random);
+        for seq in 1..=10 {
+            let used = h.attempt_addrs(seq);
+            assert_eq!(used.len(), 1);
+            assert!(addrs.contains(&used[0]));
+        }
+
+        let (v6a, v6b): (SocketAddr, SocketAddr) = (
+            "[2001:db8::1]:80".parse().unwrap(),
+            "[2001:db8::2]:80".parse().unwrap(),
+        );
+        let mut h =
+            Host::from_addrs("example.com", vec![addrs[0], addrs[1], v6a, addrs[2], v6b]).unwrap();
+        assert!(h.is_dual_stack());
+        h.set_addr_strategy(AddrStrategy::HappyEyeballs);
+        assert_eq!(
+            h.attempt_addrs(1),
+            vec![v6a, addrs[0], v6b, addrs[1], addrs[2]]
+        );
+
+        assert_eq!(
+            "ROUND-ROBIN".parse::<AddrStrategy>().unwrap(),
+            AddrStrategy::RoundRobin
+        );
+        assert_eq!(AddrStrategy::All.to_string(), "all");
+        let e = "first".parse::<AddrStrategy>().err().unwrap();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+    }
+
     #[test]
     fn test_record() {
         let mut h = Host::new("127.0.0.1", 80).unwrap();
@@ -306,6 +1006,149 @@ mod test {
         assert!(addrs.is_err());
     }
 
+    #[test]
+    fn test_resolve_family() {
+        let addrs = resolve_family("127.0.0.1", 80, AddrFamily::Any).unwrap();
+        assert_eq!(addrs, resolve_addresses("127.0.0.1", 80).unwrap());
+        assert_eq!(
+            resolve_family("127.0.0.1", 80, AddrFamily::Ipv4).unwrap(),
+            addrs
+        );
+
+        let e = resolve_family("127.0.0.1", 80, AddrFamily::Ipv6).unwrap_err();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+        assert!(
+            e.to_string()
+                .contains("No IPv6 addresses found for hostname: 127.0.0.1")
+        );
+
+        let h = Host::with_family("::1", 22, AddrFamily::Ipv6).unwrap();
+        assert!(h.addrs().iter().all(|a| a.is_ipv6()));
+        assert!(Host::with_family("::1", 22, AddrFamily::Ipv4).is_err());
+    }
+
+    #[test]
+    fn test_with_resolver() {
+        let resolver = Resolver::new()
+            .set_override("db.example.com:5432:127.0.0.1".parse().unwrap())
+            .set_override("db.example.com:6432:127.0.0.2,::1".parse().unwrap());
+        let mut h = Host::with_resolver("db.example.com", 5432, resolver.clone()).unwrap();
+        assert_eq!(h.addrs(), &["127.0.0.1:5432".parse().unwrap()]);
+        assert!(h.resolve().unwrap().is_none());
+
+        // Ports with their own override use its addresses, others keep the resolved ones.
+        assert_eq!(
+            h.for_port(6432).addrs(),
+            &[
+                "127.0.0.2:6432".parse().unwrap(),
+                "[::1]:6432".parse().unwrap()
+            ]
+        );
+        assert_eq!(h.for_port(80).addrs(), &["127.0.0.1:80".parse().unwrap()]);
+
+        let h = Host::with_resolver(
+            "db.example.com",
+            5432,
+            resolver.set_family(AddrFamily::Ipv4),
+        )
+        .unwrap();
+        assert_eq!(
+            h.for_port(6432).addrs(),
+            &["127.0.0.2:6432".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let stale: SocketAddr = "192.0.2.1:22".parse().unwrap();
+        let mut h = Host::from_addrs("127.0.0.1", vec![stale]).unwrap();
+        let before = h.resolved_at();
+
+        let change = h.resolve().unwrap().unwrap();
+        assert_eq!(change.from(), &[stale]);
+        assert_eq!(
+            change.to(),
+            &["127.0.0.1:22".parse::<SocketAddr>().unwrap()]
+        );
+        assert!(h.resolved_at() >= before);
+        assert_eq!(h.addr().ip(), IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+
+        // The same addresses are not a change.
+        assert!(h.resolve().unwrap().is_none());
+        assert_eq!(h.addr_changes().len(), 1);
+        assert!(h.for_port(80).addr_changes().is_empty());
+
+        let mut h = Host::with_family("127.0.0.1", 22, AddrFamily::Ipv4).unwrap();
+        h.addrs = vec![stale];
+        assert!(h.resolve().unwrap().is_some());
+        let e = Host::from_addrs("bad..name", vec![stale])
+            .unwrap()
+            .resolve()
+            .unwrap_err();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+    }
+
+    #[test]
+    fn test_resolve_time() {
+        let ok = || (Local::now(), chrono::TimeDelta::zero(), Status::Success);
+
+        // Only the first result after a resolution gets its time.
+        let mut h = Host::new("localhost", 22).unwrap();
+        assert!(h.resolve_time().is_some());
+        let (start, dur, status) = ok();
+        h.record(1, start, dur, status);
+        let (start, dur, status) = ok();
+        h.record(2, start, dur, status);
+        assert_eq!(
+            h.metrics().result(1).unwrap().timings().dns(),
+            h.resolve_time()
+        );
+        assert!(h.metrics().result(2).unwrap().timings().dns().is_none());
+
+        h.resolve().unwrap();
+        let (start, dur, status) = ok();
+        h.record(3, start, dur, status);
+        assert!(h.metrics().result(3).unwrap().timings().dns().is_some());
+
+        // IP addresses are not resolved.
+        let mut h = Host::new("127.0.0.1", 22).unwrap();
+        assert!(h.resolve_time().is_none());
+        let (start, dur, status) = ok();
+        h.record(1, start, dur, status);
+        assert!(h.metrics().result(1).unwrap().timings().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_every() {
+        assert_eq!(
+            "3".parse::<ResolveEvery>().unwrap(),
+            ResolveEvery::Attempts(3)
+        );
+        let every = ResolveEvery::Attempts(3);
+        let due: Vec<u32> = (1..=7)
+            .filter(|seq| every.is_due(*seq, Local::now()))
+            .collect();
+        assert_eq!(due, vec![4, 7]);
+
+        for (spec, ms) in [
+            ("250ms", 250),
+            ("30s", 30_000),
+            ("2m", 120_000),
+            ("1h", 3_600_000),
+        ] {
+            let every = spec.parse::<ResolveEvery>().unwrap();
+            let d = chrono::TimeDelta::try_milliseconds(ms).unwrap();
+            assert_eq!(every, ResolveEvery::Interval(d));
+            assert!(!every.is_due(2, Local::now()));
+            assert!(every.is_due(2, Local::now() - d));
+        }
+
+        for spec in ["0", "0s", "-5", "5d", "s", "1.5s", ""] {
+            let e = spec.parse::<ResolveEvery>().unwrap_err();
+            assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+        }
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn test_hostjson() {
@@ -326,4 +1169,32 @@ mod test {
         assert!(h_json_string.is_ok());
         assert_ne!(h_json_string.unwrap(), "".to_string());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_host_event_json() {
+        let mut h = Host::new("127.0.0.1", 22).unwrap();
+        assert!(h.attempt_json(1).is_none());
+        let took = chrono::TimeDelta::milliseconds(5);
+        h.record(1, Local::now(), took, Status::Success);
+
+        let attempt: serde_json::Value =
+            serde_json::from_str(&h.attempt_json(1).unwrap().to_json_string().unwrap()).unwrap();
+        assert_eq!(attempt["type"], "attempt");
+        assert_eq!(attempt["name"], "127.0.0.1");
+        assert_eq!(attempt["port"], 22);
+        assert_eq!(attempt["seq"], 1);
+        assert_eq!(attempt["duration_ms"], 5);
+        assert_eq!(attempt["status"], "ok");
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&h.summary_json().to_json_string().unwrap()).unwrap();
+        assert_eq!(summary["type"], "summary");
+        assert_eq!(summary["attempts"], 1);
+        assert!(summary.get("results").is_none());
+        let end: serde_json::Value =
+            serde_json::from_str(&h.final_json().to_json_string().unwrap()).unwrap();
+        assert_eq!(end["type"], "final");
+        assert_eq!(end["success"], 1);
+    }
 }
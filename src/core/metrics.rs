@@ -4,13 +4,26 @@
 //! It includes:
 //! - [`Metrics`]: The primary container for a sequence of attempt results.
 //! - [`Status`]: An enum representing success or specific failure conditions.
+//! - [`FailureKind`]: The class of a failed attempt, like refused or timed out.
+//! - [`Timings`]: Per-phase timings of a single attempt.
 //! - [`MetricsSummary`]: Aggregated statistics (success rate, attempt count).
+//! - [`Latency`]: Latency statistics of the successful attempts.
+//! - [`Histogram`]: Bucketed latency counts of the successful attempts.
+//!
+//! The results and totals of each address a [`Host`] resolves to are in [`crate::core::addr`].
+//!
+//! [`Host`]: crate::Host
 
 use chrono::Local;
+use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
 
-use crate::core::error::Result;
-use crate::{Error, Verbosity};
+use crate::core::addr::{AddrResult, AddrResultJSON, AddrSummary, AddrSummaryJSON, FamilyRaces};
+use crate::core::error::{CODE_OPTIONS_ERROR, Result};
+use crate::core::outage::{Outages, OutagesJSON};
+use crate::{Error, SourceError, Verbosity};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -20,6 +33,22 @@ const STATUS_SUCCESS: &str = "ok";
 /// Constant value to print for Failure.
 const STATUS_FAILURE: &str = "fail";
 
+/// Default [`Histogram`] bucket upper edges in milliseconds.
+pub const DEFAULT_BUCKETS_MS: [f64; 12] = [
+    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
+];
+/// Width in characters of the longest bar in [`Histogram::report`].
+const HISTOGRAM_WIDTH: u32 = 40;
+/// Columns of each line of [`Metrics::to_csv`], in the order of [`MetricsResult::csv_fields`].
+pub const CSV_COLUMNS: [&str; 6] = [
+    "seq",
+    "timestamp",
+    "duration_ms",
+    "status",
+    "failure_kind",
+    "error",
+];
+
 /// Holds the status of a port open attempt.
 ///
 /// [`Status::Failure`] wraps an optional [`Error`] for cases where the failure was caused by a
@@ -116,6 +145,28 @@ impl Status {
         }
     }
 
+    /// Returns the [`FailureKind`] of a failure, or `None` for [`Status::Success`]. A failure
+    /// without an [`Error`] is [`FailureKind::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::metrics::{FailureKind, Status};
+    /// use port_tester::{Error, SourceError};
+    ///
+    /// let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+    /// let status = Status::new(false, Some(Error::new(SourceError::Io(refused))));
+    /// assert_eq!(status.failure_kind(), Some(FailureKind::Refused));
+    /// assert_eq!(Status::Success.failure_kind(), None);
+    /// ```
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        match self {
+            Status::Success => None,
+            Status::Failure(Some(e)) => Some(FailureKind::from(e)),
+            Status::Failure(None) => Some(FailureKind::Other),
+        }
+    }
+
     /// Returns the string representation of this status for the given [`Verbosity`] level.
     ///
     /// [`Status::Success`] always returns `"ok"` regardless of verbosity. For [`Status::Failure`],
@@ -156,6 +207,182 @@ impl Status {
     }
 }
 
+/// Class of a failed attempt.
+///
+/// Failures are classified from the [`std::io::ErrorKind`] of their [`Error`] so reports can tell
+/// a refused connection from a timeout or a reset. Probes that know more about a failure than its
+/// error, like a DNS server answering with an error code or a protocol step failing after the
+/// address accepted the connection, set the kind with [`MetricsResult::with_failure_kind`].
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::metrics::FailureKind;
+/// use port_tester::{Error, SourceError};
+///
+/// let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+/// assert_eq!(FailureKind::from(&Error::new(SourceError::Io(reset))), FailureKind::Reset);
+/// assert_eq!(FailureKind::Reset.to_string(), "reset");
+/// ```
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum FailureKind {
+    /// The host actively refused the connection.
+    Refused,
+    /// The attempt timed out waiting on the host.
+    Timeout,
+    /// No route to the host.
+    HostUnreachable,
+    /// No route to the host's network.
+    NetworkUnreachable,
+    /// The DNS server queried by the dns probe did not answer or answered with an error.
+    Dns,
+    /// The target's name could not be resolved to its addresses.
+    Resolve,
+    /// The connection was reset or aborted by the host.
+    Reset,
+    /// The address accepted the connection or answered, but the probe's exchange with the service
+    /// failed afterwards, like a failed TLS handshake, an unexpected HTTP status or a banner that
+    /// didn't match. Set by the probe for the address that failed.
+    Protocol,
+    /// Any other failure.
+    Other,
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<&Error> for FailureKind {
+    fn from(e: &Error) -> Self {
+        let SourceError::Io(io) = e.source() else {
+            return FailureKind::Other;
+        };
+        match io.kind() {
+            ErrorKind::ConnectionRefused => FailureKind::Refused,
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => FailureKind::Timeout,
+            ErrorKind::HostUnreachable => FailureKind::HostUnreachable,
+            ErrorKind::NetworkUnreachable => FailureKind::NetworkUnreachable,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe => {
+                FailureKind::Reset
+            }
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Refused => "refused",
+            FailureKind::Timeout => "timeout",
+            FailureKind::HostUnreachable => "host unreachable",
+            FailureKind::NetworkUnreachable => "network unreachable",
+            FailureKind::Dns => "dns",
+            FailureKind::Resolve => "resolve",
+            FailureKind::Reset => "reset",
+            FailureKind::Protocol => "protocol",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+/// Per-phase timings of a single connection attempt.
+///
+/// Each phase is only set when the probe performed it, so a plain TCP probe leaves
+/// [`Timings::handshake`] empty.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::metrics::Timings;
+///
+/// let connect = chrono::TimeDelta::try_milliseconds(20).unwrap();
+/// let t = Timings::default().set_connect(connect);
+/// assert_eq!(t.connect(), Some(connect));
+/// assert!(t.handshake().is_none());
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Timings {
+    dns: Option<chrono::TimeDelta>,
+    connect: Option<chrono::TimeDelta>,
+    handshake: Option<chrono::TimeDelta>,
+    first_byte: Option<chrono::TimeDelta>,
+}
+
+impl Timings {
+    /// Returns the time taken to resolve the target's name before the attempt.
+    pub fn dns(&self) -> Option<chrono::TimeDelta> {
+        self.dns
+    }
+
+    /// Returns the time taken to establish the TCP connection.
+    pub fn connect(&self) -> Option<chrono::TimeDelta> {
+        self.connect
+    }
+
+    /// Returns the time taken to complete the TLS handshake after the TCP connection.
+    pub fn handshake(&self) -> Option<chrono::TimeDelta> {
+        self.handshake
+    }
+
+    /// Returns the time from sending a request to receiving the first byte of the response.
+    pub fn first_byte(&self) -> Option<chrono::TimeDelta> {
+        self.first_byte
+    }
+
+    /// Set the name resolution time.
+    pub fn set_dns(mut self, duration: chrono::TimeDelta) -> Self {
+        self.dns = Some(duration);
+        self
+    }
+
+    /// Set the TCP connect time.
+    pub fn set_connect(mut self, duration: chrono::TimeDelta) -> Self {
+        self.connect = Some(duration);
+        self
+    }
+
+    /// Set the TLS handshake time.
+    pub fn set_handshake(mut self, duration: chrono::TimeDelta) -> Self {
+        self.handshake = Some(duration);
+        self
+    }
+
+    /// Set the time to first byte.
+    pub fn set_first_byte(mut self, duration: chrono::TimeDelta) -> Self {
+        self.first_byte = Some(duration);
+        self
+    }
+
+    /// Returns `true` if no phase has been timed.
+    pub fn is_empty(&self) -> bool {
+        self.dns.is_none()
+            && self.connect.is_none()
+            && self.handshake.is_none()
+            && self.first_byte.is_none()
+    }
+
+    /// Returns the timed phases as `key=<ms>ms` pairs separated by spaces.
+    fn to_kv_string(self) -> String {
+        [
+            ("dns", self.dns),
+            ("connect", self.connect),
+            ("handshake", self.handshake),
+            ("first_byte", self.first_byte),
+        ]
+        .iter()
+        .filter_map(|(k, v)| v.map(|d| format!("{}={}ms", k, d.num_milliseconds())))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
 /// Serializable representation of a single connection attempt result.
 ///
 /// Constructed via [`MetricsJSON`] from [`Metrics::to_json`]. All fields use plain types so no
@@ -172,6 +399,39 @@ pub struct MetricsResultJSON {
     duration_ms: i64,
     /// String representation of the result (e.g., "ok" or "fail: connection refused").
     status: String,
+    /// Class of the failure, if the attempt failed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    failure_kind: Option<FailureKind>,
+    /// Time taken to resolve the target's name in milliseconds, if timed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    dns_ms: Option<i64>,
+    /// Time taken to establish the TCP connection in milliseconds, if timed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    connect_ms: Option<i64>,
+    /// Time taken to complete the TLS handshake in milliseconds, if timed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    handshake_ms: Option<i64>,
+    /// Time to first byte of the response in milliseconds, if timed.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    first_byte_ms: Option<i64>,
+    /// Banner sent by the server, if captured.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    banner: Option<String>,
+    /// Address that answered, or the last address probed if none did.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    addr: Option<SocketAddr>,
+    /// Outcome of each address probed, in the order probed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    addrs: Vec<AddrResultJSON>,
+    /// Whether the addresses were raced with Happy Eyeballs.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    race: bool,
 }
 
 impl MetricsResultJSON {
@@ -194,6 +454,51 @@ impl MetricsResultJSON {
     pub fn status(&self) -> &str {
         &self.status
     }
+
+    /// Returns the class of the failure, if the attempt failed.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        self.failure_kind
+    }
+
+    /// Returns the name resolution time in milliseconds, if timed.
+    pub fn dns_ms(&self) -> Option<i64> {
+        self.dns_ms
+    }
+
+    /// Returns the TCP connect time in milliseconds, if timed.
+    pub fn connect_ms(&self) -> Option<i64> {
+        self.connect_ms
+    }
+
+    /// Returns the TLS handshake time in milliseconds, if timed.
+    pub fn handshake_ms(&self) -> Option<i64> {
+        self.handshake_ms
+    }
+
+    /// Returns the time to first byte in milliseconds, if timed.
+    pub fn first_byte_ms(&self) -> Option<i64> {
+        self.first_byte_ms
+    }
+
+    /// Returns the banner sent by the server, if captured.
+    pub fn banner(&self) -> Option<&str> {
+        self.banner.as_deref()
+    }
+
+    /// Returns the address that answered, or the last address probed if none did.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// Returns the outcome of each address probed, in the order probed.
+    pub fn addrs(&self) -> &[AddrResultJSON] {
+        &self.addrs
+    }
+
+    /// Returns `true` if the addresses were raced with Happy Eyeballs.
+    pub fn race(&self) -> bool {
+        self.race
+    }
 }
 
 impl From<&MetricsResult> for MetricsResultJSON {
@@ -203,6 +508,15 @@ impl From<&MetricsResult> for MetricsResultJSON {
             timestamp: r.timestamp.to_rfc3339(),
             duration_ms: r.duration.num_milliseconds(),
             status: r.status.to_string(),
+            failure_kind: r.failure_kind(),
+            dns_ms: r.timings.dns.map(|d| d.num_milliseconds()),
+            connect_ms: r.timings.connect.map(|d| d.num_milliseconds()),
+            handshake_ms: r.timings.handshake.map(|d| d.num_milliseconds()),
+            first_byte_ms: r.timings.first_byte.map(|d| d.num_milliseconds()),
+            banner: r.banner.clone(),
+            addr: r.addr(),
+            addrs: r.addrs.iter().map(AddrResultJSON::from).collect(),
+            race: r.race,
         }
     }
 }
@@ -216,6 +530,10 @@ impl From<&MetricsResult> for MetricsResultJSON {
 #[non_exhaustive]
 pub struct MetricsJSON {
     /// List of all individual attempt results.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
     results: Vec<MetricsResultJSON>,
     /// Total count of attempts recorded.
     attempts: u32,
@@ -225,6 +543,29 @@ pub struct MetricsJSON {
     failure: u32,
     /// Calculated failure rate (0.0 - 100.0).
     failure_rate: f64,
+    /// Count of failed attempts by class.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    failure_kinds: BTreeMap<FailureKind, u32>,
+    /// Latency statistics of the successful attempts, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    latency: Option<Latency>,
+    /// Latency histogram of the successful attempts, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    histogram: Option<Histogram>,
+    /// Outages seen across the attempts.
+    outages: OutagesJSON,
+    /// Totals of each address probed, in the order first probed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    addrs: Vec<AddrSummaryJSON>,
+    /// Address family that won the attempts raced with Happy Eyeballs, if any were.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    races: Option<FamilyRaces>,
 }
 
 impl MetricsJSON {
@@ -253,6 +594,36 @@ impl MetricsJSON {
         self.failure_rate
     }
 
+    /// Returns the number of failed attempts of each [`FailureKind`] seen.
+    pub fn failure_kinds(&self) -> &BTreeMap<FailureKind, u32> {
+        &self.failure_kinds
+    }
+
+    /// Returns the latency statistics of the successful attempts, if any.
+    pub fn latency(&self) -> Option<&Latency> {
+        self.latency.as_ref()
+    }
+
+    /// Returns the latency histogram of the successful attempts, if any.
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    /// Returns the outages seen across the attempts.
+    pub fn outages(&self) -> &OutagesJSON {
+        &self.outages
+    }
+
+    /// Returns the totals of each address probed, in the order first probed.
+    pub fn addrs(&self) -> &[AddrSummaryJSON] {
+        &self.addrs
+    }
+
+    /// Returns the address family that won the attempts raced with Happy Eyeballs, if any were.
+    pub fn races(&self) -> Option<&FamilyRaces> {
+        self.races.as_ref()
+    }
+
     /// Serializes the current metrics to a JSON string.
     ///
     /// # Errors
@@ -291,6 +662,7 @@ impl MetricsJSON {
 pub struct Metrics {
     results: Vec<MetricsResult>,
     summary: MetricsSummary,
+    outages: Outages,
     verbosity: Verbosity,
 }
 
@@ -310,6 +682,7 @@ impl Metrics {
         Metrics {
             results: Vec::new(),
             summary: MetricsSummary::default(),
+            outages: Outages::default(),
             verbosity: verbose.to_owned(),
         }
     }
@@ -334,6 +707,64 @@ impl Metrics {
         self.summary.failure_rate()
     }
 
+    /// Returns the number of failed attempts of each [`FailureKind`] seen. See
+    /// [`MetricsSummary::failure_kinds`].
+    pub fn failure_kinds(&self) -> &BTreeMap<FailureKind, u32> {
+        self.summary.failure_kinds()
+    }
+
+    /// Returns the latency statistics of the successful attempts, or `None` if no attempt has
+    /// succeeded. See [`MetricsSummary::latency`].
+    pub fn latency(&self) -> Option<Latency> {
+        self.summary.latency()
+    }
+
+    /// Returns the sum of the durations of the successful attempts in milliseconds, without
+    /// building the [`Latency`] statistics.
+    pub(crate) fn latency_sum_ms(&self) -> f64 {
+        self.summary.latencies.sum_ms()
+    }
+
+    /// Returns the latency [`Histogram`] of the successful attempts. See
+    /// [`MetricsSummary::histogram`].
+    pub fn histogram(&self) -> Histogram {
+        self.summary.histogram()
+    }
+
+    /// Set the upper edges of the latency [`Histogram`] buckets. See
+    /// [`MetricsSummary::set_buckets`].
+    pub fn set_buckets(&mut self, edges: Vec<chrono::TimeDelta>) {
+        self.summary.set_buckets(edges);
+    }
+
+    /// Returns the totals of each address probed, in the order first probed. See
+    /// [`MetricsSummary::addr_summaries`].
+    pub fn addr_summaries(&self) -> &[AddrSummary] {
+        self.summary.addr_summaries()
+    }
+
+    /// Returns a report of each address probed. See [`MetricsSummary::addr_report`].
+    pub fn addr_report(&self) -> String {
+        self.summary.addr_report()
+    }
+
+    /// Returns the address family that won the attempts raced with Happy Eyeballs. See
+    /// [`MetricsSummary::races`].
+    pub fn races(&self) -> &FamilyRaces {
+        self.summary.races()
+    }
+
+    /// Returns the [`Outages`] seen across the recorded attempts.
+    pub fn outages(&self) -> &Outages {
+        &self.outages
+    }
+
+    /// Set the number of consecutive failed attempts that make an outage. See
+    /// [`Outages::set_threshold`]. Set this before recording any attempts.
+    pub fn set_outage_threshold(&mut self, threshold: u32) {
+        self.outages = Outages::new().set_threshold(threshold);
+    }
+
     /// Returns the number of recorded results.
     pub fn len(&self) -> usize {
         self.results.len()
@@ -393,8 +824,30 @@ impl Metrics {
         duration: chrono::TimeDelta,
         status: Status,
     ) {
-        self.summary.record(&status);
-        let result = MetricsResult::new(seq, timestamp, duration, status);
+        self.record_result(MetricsResult::new(seq, timestamp, duration, status));
+    }
+
+    /// Record a prebuilt [`MetricsResult`], updating the [`MetricsSummary`].
+    ///
+    /// Use this over [`Metrics::record`] when the result carries more than the basic fields, such
+    /// as [`Timings`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use port_tester::core::metrics::{Metrics, MetricsResult, Status, Timings};
+    /// use port_tester::Verbosity;
+    ///
+    /// let mut m = Metrics::new(&Verbosity::Normal);
+    /// let dur = chrono::TimeDelta::try_milliseconds(100).unwrap();
+    /// let timings = Timings::default().set_connect(dur);
+    /// m.record_result(MetricsResult::new(1, Local::now(), dur, Status::Success).with_timings(timings));
+    /// assert_eq!(m.result(1).unwrap().timings().connect(), Some(dur));
+    /// ```
+    pub fn record_result(&mut self, result: MetricsResult) {
+        self.summary.record_result(&result);
+        self.outages.record(&result);
         self.results.push(result);
     }
 
@@ -421,9 +874,8 @@ impl Metrics {
             .and_then(|i| self.results.get(i as usize))
     }
 
-    /// Returns a single-line summary report from the internal [`MetricsSummary`].
-    ///
-    /// Output format: `"attempts: N, success: N, fail: N, failure rate: N.NN%"`
+    /// Returns a summary report from the internal [`MetricsSummary`]. See
+    /// [`MetricsSummary::report`].
     ///
     /// # Examples
     ///
@@ -436,13 +888,18 @@ impl Metrics {
     /// let dur = chrono::TimeDelta::try_milliseconds(100).unwrap();
     /// m.record(1, Local::now(), dur, Status::Success);
     /// println!("{}", m.report());
-    /// // Output: attempts: 1, success: 1, fail: 0, failure rate: 0.00%
+    /// // Output:
+    /// // attempts: 1, success: 1, fail: 0, failure rate: 0.00%
+    /// // rtt min/avg/max/mdev = 100.000/100.000/100.000/0.000 ms, jitter = 0.000 ms
+    /// // rtt p50/p90/p95/p99 = 100.000/100.000/100.000/100.000 ms
     /// ```
     pub fn report(&self) -> String {
         self.summary.report()
     }
 
-    /// Returns a multi-line report containing each recorded result followed by the summary.
+    /// Returns a multi-line report containing each recorded result followed by the summary, the
+    /// totals of each address if more than one was probed, any [`Outages`] and, if any attempt
+    /// succeeded, the latency [`Histogram`].
     ///
     /// Each result is formatted using the stored [`Verbosity`] level. Results are separated
     /// from the summary by a blank line.
@@ -464,6 +921,12 @@ impl Metrics {
     /// // 2 fail
     /// //
     /// // attempts: 2, success: 1, fail: 1, failure rate: 50.00%
+    /// // rtt min/avg/max/mdev = 100.000/100.000/100.000/0.000 ms, jitter = 0.000 ms
+    /// // rtt p50/p90/p95/p99 = 100.000/100.000/100.000/100.000 ms
+    /// //
+    /// // latency histogram:
+    /// //   <= 1 ms  0
+    /// // ...
     /// ```
     pub fn full_report(&self) -> String {
         let mut report = String::new();
@@ -475,17 +938,54 @@ impl Metrics {
         // Write an empty line as a separator.
         let _ = writeln!(report);
         let _ = writeln!(report, "{}", self.report());
+        if self.summary.addr_summaries().len() > 1 {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "{}", self.addr_report());
+        }
+        if self.summary.races.races() > 0 {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "{}", self.summary.races.report());
+        }
+        if !self.outages.is_empty() {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "{}", self.outages.report());
+        }
+        if !self.summary.latencies.durations().is_empty() {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "{}", self.histogram().report());
+        }
         report
     }
 
     /// Returns an owned [`MetricsJSON`] snapshot of the current metrics state.
     pub fn to_json(&self) -> MetricsJSON {
+        let latency = self.summary.latency();
         MetricsJSON {
             results: self.results.iter().map(MetricsResultJSON::from).collect(),
             attempts: self.summary.attempts,
             success: self.summary.success,
             failure: self.summary.failure,
             failure_rate: self.summary.failure_rate(),
+            failure_kinds: self.summary.failure_kinds.clone(),
+            histogram: latency.map(|_| self.summary.histogram()),
+            latency,
+            outages: self.outages.to_json(),
+            addrs: self
+                .summary
+                .addr_summaries()
+                .iter()
+                .map(AddrSummary::to_json)
+                .collect(),
+            races: Some(self.summary.races).filter(|r| r.races() > 0),
+        }
+    }
+
+    /// Returns an owned [`MetricsJSON`] snapshot of the summary statistics, without the
+    /// per-attempt results.
+    pub fn to_summary_json(&self) -> MetricsJSON {
+        MetricsJSON {
+            results: Vec::new(),
+            ..self.to_json()
         }
     }
 
@@ -498,6 +998,45 @@ impl Metrics {
     pub fn to_json_string(&self) -> Result<String> {
         self.to_json().to_json_string()
     }
+
+    /// Returns the results as CSV, a header row of [`CSV_COLUMNS`] followed by a line per
+    /// result with the fields of [`MetricsResult::csv_fields`]. Fields are quoted as described by
+    /// RFC 4180 when needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use port_tester::core::metrics::{Metrics, Status};
+    /// use port_tester::Verbosity;
+    ///
+    /// let mut m = Metrics::new(&Verbosity::Normal);
+    /// let dur = chrono::TimeDelta::try_milliseconds(250).unwrap();
+    /// m.record(1, Local::now(), dur, Status::Success);
+    /// let csv = m.to_csv();
+    /// let mut lines = csv.lines();
+    /// assert_eq!(lines.next(), Some("seq,timestamp,duration_ms,status,failure_kind,error"));
+    /// assert!(lines.next().unwrap().ends_with(",250,ok,,"));
+    /// ```
+    pub fn to_csv(&self) -> String {
+        self.to_delimited(',')
+    }
+
+    /// Returns the results as TSV, like [`Metrics::to_csv`] but separated by tabs. Tabs and new
+    /// lines in a field are replaced by spaces.
+    pub fn to_tsv(&self) -> String {
+        self.to_delimited('\t')
+    }
+
+    fn to_delimited(&self, delimiter: char) -> String {
+        let mut out = delimited_line(&CSV_COLUMNS, delimiter);
+        for result in &self.results {
+            out.push('\n');
+            out.push_str(&delimited_line(&result.csv_fields(), delimiter));
+        }
+        out.push('\n');
+        out
+    }
 }
 
 /// Stores the metrics for a single connection attempt.
@@ -523,6 +1062,11 @@ pub struct MetricsResult {
     timestamp: chrono::DateTime<Local>,
     duration: chrono::TimeDelta,
     status: Status,
+    failure_kind: Option<FailureKind>,
+    timings: Timings,
+    banner: Option<String>,
+    addrs: Vec<AddrResult>,
+    race: bool,
 }
 
 /// Formats using [`Verbosity::Normal`]. Use [`MetricsResult::to_string_with_verbosity`] to
@@ -561,8 +1105,47 @@ impl MetricsResult {
             seq,
             timestamp,
             duration,
+            failure_kind: status.failure_kind(),
             status,
+            timings: Timings::default(),
+            banner: None,
+            addrs: Vec::new(),
+            race: false,
+        }
+    }
+
+    /// Attach per-phase [`Timings`] to this result.
+    pub fn with_timings(mut self, timings: Timings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Attach the banner sent by the server to this result.
+    pub fn with_banner(mut self, banner: String) -> Self {
+        self.banner = Some(banner);
+        self
+    }
+
+    /// Attach the outcome of each address probed during the attempt, in the order probed.
+    pub fn with_addrs(mut self, addrs: Vec<AddrResult>) -> Self {
+        self.addrs = addrs;
+        self
+    }
+
+    /// Mark the addresses of this attempt as raced with Happy Eyeballs. The address that answered
+    /// is counted as the winner of the race by [`MetricsSummary::races`].
+    pub fn with_race(mut self) -> Self {
+        self.race = true;
+        self
+    }
+
+    /// Set the [`FailureKind`] of a failed attempt in place of the one classified from its
+    /// [`Status`]. Ignored for a successful attempt.
+    pub fn with_failure_kind(mut self, kind: FailureKind) -> Self {
+        if self.is_err() {
+            self.failure_kind = Some(kind);
         }
+        self
     }
 
     /// Returns the 1-based sequence number of this attempt.
@@ -585,6 +1168,95 @@ impl MetricsResult {
         &self.status
     }
 
+    /// Returns the outcome of each address probed during the attempt, in the order probed.
+    pub fn addrs(&self) -> &[AddrResult] {
+        &self.addrs
+    }
+
+    /// Returns the address that answered the attempt, or the last address probed if none did.
+    /// Returns `None` if no addresses were recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeDelta};
+    /// use port_tester::core::addr::AddrResult;
+    /// use port_tester::core::metrics::{MetricsResult, Status};
+    /// use port_tester::{Error, SourceError};
+    ///
+    /// let (a, b) = ("192.0.2.1:80".parse().unwrap(), "192.0.2.2:80".parse().unwrap());
+    /// let dur = TimeDelta::try_milliseconds(10).unwrap();
+    /// let refused = Error::new(SourceError::Io(std::io::ErrorKind::ConnectionRefused.into()));
+    /// let mr = MetricsResult::new(1, Local::now(), dur, Status::Success).with_addrs(vec![
+    ///     AddrResult::new(a, dur, Some(&refused)),
+    ///     AddrResult::new(b, dur, None),
+    /// ]);
+    /// assert_eq!(mr.addr(), Some(b));
+    /// ```
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addrs
+            .iter()
+            .rfind(|a| !a.is_err())
+            .or(self.addrs.last())
+            .map(|a| a.addr())
+    }
+
+    /// Returns the [`FailureKind`] of the attempt, or `None` if it succeeded.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        self.failure_kind
+    }
+
+    /// Returns `true` if the addresses of this attempt were raced with Happy Eyeballs.
+    pub fn is_race(&self) -> bool {
+        self.race
+    }
+
+    /// Returns the per-phase [`Timings`] of this attempt.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Returns the banner sent by the server, if captured.
+    pub fn banner(&self) -> Option<&str> {
+        self.banner.as_deref()
+    }
+
+    /// Returns the fields of this result in the order of [`CSV_COLUMNS`]. The status is `ok` or
+    /// `fail`, and the failure kind and error are empty for a success. The lines of an error with
+    /// context are joined with `: `.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use port_tester::core::metrics::{MetricsResult, Status};
+    ///
+    /// let dur = chrono::TimeDelta::try_milliseconds(100).unwrap();
+    /// let fields = MetricsResult::new(1, Local::now(), dur, Status::Failure(None)).csv_fields();
+    /// assert_eq!(fields[2..], ["100", "fail", "other", ""]);
+    /// ```
+    pub fn csv_fields(&self) -> [String; 6] {
+        let (status, error) = match &self.status {
+            Status::Success => (STATUS_SUCCESS, String::new()),
+            Status::Failure(e) => (
+                STATUS_FAILURE,
+                e.as_ref()
+                    .map(|e| e.to_string().lines().collect::<Vec<_>>().join(": "))
+                    .unwrap_or_default(),
+            ),
+        };
+        [
+            self.seq.to_string(),
+            self.timestamp.to_rfc3339(),
+            self.duration.num_milliseconds().to_string(),
+            status.to_string(),
+            self.failure_kind()
+                .map(|k| k.as_str().to_string())
+                .unwrap_or_default(),
+            error,
+        ]
+    }
+
     /// Returns `true` if this result's status represents a failure.
     ///
     /// # Examples
@@ -612,7 +1284,11 @@ impl MetricsResult {
     /// - `Verbose(0)`: same as [`Verbosity::Normal`]
     /// - `Verbose(1)`: `"<seq> <duration>ms <status>"`
     /// - `Verbose(2)`: `"<timestamp> <seq> <duration>ms <status>"`
-    /// - `Verbose(3+)`: `"start=<timestamp> seq=<seq> dur=<duration>ms status=<status>"`
+    /// - `Verbose(3+)`: `"start=<timestamp> seq=<seq> dur=<duration>ms status=<status>"`, with any
+    ///   [`Timings`] added as `<phase>=<duration>ms` before `status`
+    ///
+    /// From `Verbose(2)` a captured banner is appended as `banner="<banner>"` with control
+    /// characters escaped.
     ///
     /// # Examples
     ///
@@ -628,6 +1304,21 @@ impl MetricsResult {
     /// assert_eq!(mr.to_string_with_verbosity(&Verbosity::Verbose(1)), "1 100ms ok");
     /// ```
     pub fn to_string_with_verbosity(&self, verbosity: &Verbosity) -> String {
+        let mut line = self.format_line(verbosity);
+        if let Verbosity::Verbose(n) = verbosity {
+            if *n >= 2 {
+                if let Some(addr) = self.addr() {
+                    let _ = write!(line, " addr={}", addr);
+                }
+                if let Some(banner) = &self.banner {
+                    let _ = write!(line, " banner={:?}", banner);
+                }
+            }
+        }
+        line
+    }
+
+    fn format_line(&self, verbosity: &Verbosity) -> String {
         match *verbosity {
             Verbosity::Verbose(n) => match n {
                 // Same as Verbosity::Normal.
@@ -649,13 +1340,21 @@ impl MetricsResult {
                     self.duration.num_milliseconds(),
                     self.status.to_string_with_verbosity(verbosity)
                 ),
-                _ => format!(
+                _ if self.timings.is_empty() => format!(
                     "start={} seq={} dur={}ms status={}",
                     self.timestamp,
                     self.seq,
                     self.duration.num_milliseconds(),
                     self.status.to_string_with_verbosity(verbosity)
                 ),
+                _ => format!(
+                    "start={} seq={} dur={}ms {} status={}",
+                    self.timestamp,
+                    self.seq,
+                    self.duration.num_milliseconds(),
+                    self.timings.to_kv_string(),
+                    self.status.to_string_with_verbosity(verbosity)
+                ),
             },
             Verbosity::Normal => format!(
                 "{} {}",
@@ -672,10 +1371,11 @@ impl MetricsResult {
     }
 }
 
-/// A metrics store to track attempt successes and failures.
+/// A metrics store to track attempt successes, failures and latency.
 ///
 /// Create a new `MetricsSummary` with [`MetricsSummary::default`].
-/// Record an attempt with [`MetricsSummary::record`].
+/// Record an attempt with [`MetricsSummary::record`] or, to include its latency,
+/// [`MetricsSummary::record_result`].
 /// Generate a report with [`MetricsSummary::report`].
 ///
 /// # Examples
@@ -688,12 +1388,38 @@ impl MetricsResult {
 /// ms.record(&Status::new(false, None));
 /// ms.report();
 /// ```
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[non_exhaustive]
 pub struct MetricsSummary {
     attempts: u32,
     success: u32,
     failure: u32,
+    /// Count of failed attempts by class.
+    failure_kinds: BTreeMap<FailureKind, u32>,
+    /// Durations of the successful attempts recorded with [`MetricsSummary::record_result`].
+    latencies: Latencies,
+    /// Upper edges of the [`Histogram`] buckets in ascending order.
+    buckets: Vec<chrono::TimeDelta>,
+    /// Totals of each address probed, in the order first probed.
+    addrs: Vec<AddrSummary>,
+    /// Address family that won each raced attempt.
+    races: FamilyRaces,
+}
+
+/// Defaults to no attempts and [`DEFAULT_BUCKETS_MS`] histogram buckets.
+impl Default for MetricsSummary {
+    fn default() -> Self {
+        MetricsSummary {
+            attempts: 0,
+            success: 0,
+            failure: 0,
+            failure_kinds: BTreeMap::new(),
+            latencies: Latencies::default(),
+            buckets: DEFAULT_BUCKETS_MS.iter().map(|ms| from_ms(*ms)).collect(),
+            addrs: Vec::new(),
+            races: FamilyRaces::default(),
+        }
+    }
 }
 
 impl MetricsSummary {
@@ -712,6 +1438,24 @@ impl MetricsSummary {
         self.failure
     }
 
+    /// Returns the number of failed attempts of each [`FailureKind`] seen. Kinds without a
+    /// failure are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::metrics::{FailureKind, MetricsSummary, Status};
+    ///
+    /// let mut ms = MetricsSummary::default();
+    /// ms.record(&Status::Success);
+    /// ms.record(&Status::new(false, None));
+    /// assert_eq!(ms.failure_kinds().get(&FailureKind::Other), Some(&1));
+    /// assert_eq!(ms.failure_kinds().get(&FailureKind::Timeout), None);
+    /// ```
+    pub fn failure_kinds(&self) -> &BTreeMap<FailureKind, u32> {
+        &self.failure_kinds
+    }
+
     /// Returns the failure rate as a percentage (0.0 – 100.0).
     ///
     /// Returns `0.0` when no attempts have been recorded.
@@ -723,34 +1467,188 @@ impl MetricsSummary {
         }
     }
 
-    /// Record a connection attempt, incrementing `success` or `failure` accordingly.
-    ///
-    /// [`Status::Success`] increments the success counter. [`Status::Failure`] increments the
-    /// failure counter. Both increment the total attempts counter.
+    /// Returns the [`Latency`] statistics of the successful attempts recorded with
+    /// [`MetricsSummary::record_result`], or `None` if there are none.
     ///
     /// # Examples
     ///
     /// ```
-    /// use port_tester::core::metrics::{MetricsSummary, Status};
+    /// use chrono::{Local, TimeDelta};
+    /// use port_tester::core::metrics::{MetricsResult, MetricsSummary, Status};
     ///
     /// let mut ms = MetricsSummary::default();
-    /// ms.record(&Status::Success);
-    /// ms.record(&Status::new(false, None));
-    /// assert_eq!(ms.success(), 1);
-    /// assert_eq!(ms.failure(), 1);
-    /// assert_eq!(ms.attempts(), 2);
+    /// assert!(ms.latency().is_none());
+    ///
+    /// for ms_ in [10, 20, 30] {
+    ///     let dur = TimeDelta::try_milliseconds(ms_).unwrap();
+    ///     ms.record_result(&MetricsResult::new(1, Local::now(), dur, Status::Success));
+    /// }
+    /// let latency = ms.latency().unwrap();
+    /// assert_eq!(latency.min_ms(), 10.0);
+    /// assert_eq!(latency.avg_ms(), 20.0);
+    /// assert_eq!(latency.p50_ms(), 20.0);
     /// ```
-    pub fn record(&mut self, status: &Status) {
-        self.attempts += 1;
-        match status {
-            Status::Success => self.success += 1,
-            Status::Failure(..) => self.failure += 1,
+    pub fn latency(&self) -> Option<Latency> {
+        self.latencies.latency()
+    }
+
+    /// Returns the latency [`Histogram`] of the successful attempts recorded with
+    /// [`MetricsSummary::record_result`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeDelta};
+    /// use port_tester::core::metrics::{MetricsResult, MetricsSummary, Status};
+    ///
+    /// let ms = |v| TimeDelta::try_milliseconds(v).unwrap();
+    /// let mut summary = MetricsSummary::default();
+    /// summary.set_buckets(vec![ms(10), ms(100)]);
+    /// for v in [5, 50, 60, 500] {
+    ///     summary.record_result(&MetricsResult::new(1, Local::now(), ms(v), Status::Success));
+    /// }
+    /// let h = summary.histogram();
+    /// assert_eq!(h.edges_ms(), &[10.0, 100.0]);
+    /// assert_eq!(h.counts(), &[1, 2, 1]);
+    /// ```
+    pub fn histogram(&self) -> Histogram {
+        let mut counts = vec![0; self.buckets.len() + 1];
+        for d in self.latencies.durations() {
+            counts[self.buckets.partition_point(|edge| edge < d)] += 1;
+        }
+        Histogram {
+            edges_ms: self.buckets.iter().map(|e| to_ms(*e)).collect(),
+            counts,
         }
     }
 
-    /// Returns a single-line summary of the collected metrics.
+    /// Set the upper edges of the latency [`Histogram`] buckets. Edges are sorted and duplicates
+    /// removed. An attempt falls in the first bucket whose edge is greater than or equal to its
+    /// duration, or in a final bucket for durations above the last edge.
+    pub fn set_buckets(&mut self, mut edges: Vec<chrono::TimeDelta>) {
+        edges.sort();
+        edges.dedup();
+        self.buckets = edges;
+    }
+
+    /// Record a connection attempt, incrementing `success` or `failure` accordingly.
     ///
-    /// Output format: `"attempts: N, success: N, fail: N, failure rate: N.NN%"`
+    /// [`Status::Success`] increments the success counter. [`Status::Failure`] increments the
+    /// failure counter and the counter of its [`FailureKind`]. Both increment the total attempts
+    /// counter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use port_tester::core::metrics::{MetricsSummary, Status};
+    ///
+    /// let mut ms = MetricsSummary::default();
+    /// ms.record(&Status::Success);
+    /// ms.record(&Status::new(false, None));
+    /// assert_eq!(ms.success(), 1);
+    /// assert_eq!(ms.failure(), 1);
+    /// assert_eq!(ms.attempts(), 2);
+    /// ```
+    pub fn record(&mut self, status: &Status) {
+        self.count(status.failure_kind());
+    }
+
+    /// Record a connection attempt like [`MetricsSummary::record`], using the result's
+    /// [`FailureKind`], adding the duration of a successful attempt to the [`Latency`]
+    /// statistics and adding each address probed to its [`AddrSummary`].
+    pub fn record_result(&mut self, result: &MetricsResult) {
+        self.count(result.failure_kind());
+        if !result.is_err() {
+            self.latencies.record(result.duration);
+        }
+        for r in &result.addrs {
+            match self.addrs.iter_mut().find(|a| a.addr() == r.addr()) {
+                Some(summary) => summary.record(r),
+                None => {
+                    let mut summary = AddrSummary::new(r.addr());
+                    summary.record(r);
+                    self.addrs.push(summary);
+                }
+            }
+        }
+        if result.race && !result.is_err() {
+            if let Some(winner) = result.addr() {
+                self.races.record(&winner);
+            }
+        }
+    }
+
+    /// Returns the totals of each address probed, in the order first probed.
+    pub fn addr_summaries(&self) -> &[AddrSummary] {
+        &self.addrs
+    }
+
+    /// Returns the address family that won each attempt recorded with
+    /// [`MetricsResult::with_race`] that passed.
+    pub fn races(&self) -> &FamilyRaces {
+        &self.races
+    }
+
+    /// Returns `"addresses:"` followed by a line for each address probed with its
+    /// [`AddrSummary::report`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeDelta};
+    /// use port_tester::core::addr::AddrResult;
+    /// use port_tester::core::metrics::{MetricsResult, MetricsSummary, Status};
+    ///
+    /// let addr = "192.0.2.1:80".parse().unwrap();
+    /// let dur = TimeDelta::try_milliseconds(10).unwrap();
+    /// let mr = MetricsResult::new(1, Local::now(), dur, Status::Success)
+    ///     .with_addrs(vec![AddrResult::new(addr, dur, None)]);
+    /// let mut ms = MetricsSummary::default();
+    /// ms.record_result(&mr);
+    /// assert_eq!(
+    ///     ms.addr_report(),
+    ///     "addresses:\n  192.0.2.1:80  attempts: 1, success: 1, fail: 0, failure rate: 0.00%, \
+    ///      rtt min/avg/max = 10.000/10.000/10.000 ms"
+    /// );
+    /// ```
+    pub fn addr_report(&self) -> String {
+        let width = self
+            .addrs
+            .iter()
+            .map(|a| a.addr().to_string().len())
+            .max()
+            .unwrap_or(0);
+        let mut report = String::from("addresses:");
+        for a in &self.addrs {
+            let _ = write!(
+                report,
+                "\n  {:<width$}  {}",
+                a.addr().to_string(),
+                a.report()
+            );
+        }
+        report
+    }
+
+    /// Count an attempt that failed with `kind`, or succeeded if `kind` is `None`.
+    fn count(&mut self, kind: Option<FailureKind>) {
+        self.attempts += 1;
+        match kind {
+            None => self.success += 1,
+            Some(kind) => {
+                self.failure += 1;
+                *self.failure_kinds.entry(kind).or_default() += 1;
+            }
+        }
+    }
+
+    /// Returns a summary of the collected metrics.
+    ///
+    /// The first line is `"attempts: N, success: N, fail: N, failure rate: N.NN%"`. When any
+    /// attempt failed, a line like `"failures: 3 refused, 1 timeout"` counts each
+    /// [`FailureKind`]. When any successful attempt has been recorded with
+    /// [`MetricsSummary::record_result`], the [`Latency`] statistics follow on two more lines, see
+    /// [`Latency::report`].
     ///
     /// # Examples
     ///
@@ -764,16 +1662,407 @@ impl MetricsSummary {
     /// ```
     /// Output: `attempts: 2, success: 2, fail: 0, failure rate: 0.00%`
     pub fn report(&self) -> String {
-        format!(
+        let mut counts = format!(
             "attempts: {}, success: {}, fail: {}, failure rate: {:.2}%",
             self.attempts,
             self.success,
             self.failure,
             self.failure_rate()
+        );
+        if !self.failure_kinds.is_empty() {
+            let kinds: Vec<String> = self
+                .failure_kinds
+                .iter()
+                .map(|(kind, n)| format!("{} {}", n, kind))
+                .collect();
+            let _ = write!(counts, "\nfailures: {}", kinds.join(", "));
+        }
+        match self.latency() {
+            Some(latency) => format!("{}\n{}", counts, latency.report()),
+            None => counts,
+        }
+    }
+}
+
+/// Running statistics of the durations of the successful attempts, updated as each is recorded.
+///
+/// The durations themselves are kept for the percentiles and the [`Histogram`], which are only
+/// computed when a report is built with [`Latencies::latency`] or [`Latencies::durations`].
+/// Compares by the durations, as the running fields are derived from them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Latencies {
+    durations: Vec<chrono::TimeDelta>,
+    min_ms: f64,
+    max_ms: f64,
+    sum_ms: f64,
+    sum_sq_ms: f64,
+    jitter_ms: f64,
+}
+
+impl Latencies {
+    /// Add the duration of a successful attempt.
+    pub(crate) fn record(&mut self, duration: chrono::TimeDelta) {
+        let ms = to_ms(duration);
+        match self.durations.last() {
+            Some(last) => {
+                // RFC 3550 section 6.4.1: J += (|D| - J) / 16, in recording order.
+                self.jitter_ms += ((ms - to_ms(*last)).abs() - self.jitter_ms) / 16.0;
+                self.min_ms = self.min_ms.min(ms);
+                self.max_ms = self.max_ms.max(ms);
+            }
+            None => {
+                self.min_ms = ms;
+                self.max_ms = ms;
+            }
+        }
+        self.sum_ms += ms;
+        self.sum_sq_ms += ms * ms;
+        self.durations.push(duration);
+    }
+
+    /// Returns the recorded durations in recording order.
+    pub(crate) fn durations(&self) -> &[chrono::TimeDelta] {
+        &self.durations
+    }
+
+    /// Returns the sum of the durations in milliseconds.
+    pub(crate) fn sum_ms(&self) -> f64 {
+        self.sum_ms
+    }
+
+    /// Returns the minimum, mean and maximum duration in milliseconds without sorting, or `None`
+    /// if there are none.
+    pub(crate) fn min_avg_max_ms(&self) -> Option<(f64, f64, f64)> {
+        match self.durations.len() {
+            0 => None,
+            n => Some((self.min_ms, self.sum_ms / n as f64, self.max_ms)),
+        }
+    }
+
+    /// Returns the [`Latency`] statistics, sorting a copy of the durations once for the
+    /// percentiles. Returns `None` if there are none.
+    pub(crate) fn latency(&self) -> Option<Latency> {
+        let (min_ms, avg_ms, max_ms) = self.min_avg_max_ms()?;
+        let mut sorted: Vec<f64> = self.durations.iter().map(|d| to_ms(*d)).collect();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len() as f64;
+        // Rounding can leave the variance of equal durations just below zero.
+        let var = (self.sum_sq_ms / n - avg_ms * avg_ms).max(0.0);
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * n).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Some(Latency {
+            min_ms,
+            avg_ms,
+            max_ms,
+            mdev_ms: var.sqrt(),
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            jitter_ms: self.jitter_ms,
+        })
+    }
+}
+
+impl PartialEq for Latencies {
+    fn eq(&self, other: &Self) -> bool {
+        self.durations == other.durations
+    }
+}
+
+impl Eq for Latencies {}
+
+impl PartialOrd for Latencies {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Latencies {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.durations.cmp(&other.durations)
+    }
+}
+
+impl std::hash::Hash for Latencies {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.durations.hash(state);
+    }
+}
+
+/// Latency statistics of a set of attempt durations, in milliseconds.
+///
+/// The standard deviation, `mdev`, is the population standard deviation as reported by `ping`.
+/// Percentiles use the nearest-rank method, so each is one of the recorded durations. Jitter is
+/// the interarrival jitter of RFC 3550, a running average of the difference between consecutive
+/// durations with a gain of 1/16. Produced by [`MetricsSummary::latency`] from the successful
+/// attempts only, so the durations are consecutive successes: a failed attempt in between is
+/// skipped rather than counted as a swing to its timeout.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeDelta};
+/// use port_tester::core::metrics::{Metrics, Status};
+/// use port_tester::Verbosity;
+///
+/// let mut m = Metrics::new(&Verbosity::Normal);
+/// for (seq, ms) in [(1, 20), (2, 40)] {
+///     let dur = TimeDelta::try_milliseconds(ms).unwrap();
+///     m.record(seq, Local::now(), dur, Status::Success);
+/// }
+/// let latency = m.latency().unwrap();
+/// assert_eq!(latency.max_ms(), 40.0);
+/// assert_eq!(latency.mdev_ms(), 10.0);
+/// assert_eq!(latency.jitter_ms(), 1.25);
+/// assert_eq!(
+///     latency.report(),
+///     "rtt min/avg/max/mdev = 20.000/30.000/40.000/10.000 ms, jitter = 1.250 ms\n\
+///      rtt p50/p90/p95/p99 = 20.000/40.000/40.000/40.000 ms"
+/// );
+/// ```
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Latency {
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+    mdev_ms: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    jitter_ms: f64,
+}
+
+impl Latency {
+    /// Returns the shortest duration.
+    pub fn min_ms(&self) -> f64 {
+        self.min_ms
+    }
+
+    /// Returns the mean duration.
+    pub fn avg_ms(&self) -> f64 {
+        self.avg_ms
+    }
+
+    /// Returns the longest duration.
+    pub fn max_ms(&self) -> f64 {
+        self.max_ms
+    }
+
+    /// Returns the standard deviation of the durations.
+    pub fn mdev_ms(&self) -> f64 {
+        self.mdev_ms
+    }
+
+    /// Returns the 50th percentile (median) duration.
+    pub fn p50_ms(&self) -> f64 {
+        self.p50_ms
+    }
+
+    /// Returns the 90th percentile duration.
+    pub fn p90_ms(&self) -> f64 {
+        self.p90_ms
+    }
+
+    /// Returns the 95th percentile duration.
+    pub fn p95_ms(&self) -> f64 {
+        self.p95_ms
+    }
+
+    /// Returns the 99th percentile duration.
+    pub fn p99_ms(&self) -> f64 {
+        self.p99_ms
+    }
+
+    /// Returns the RFC 3550 interarrival jitter of the durations, in recording order. For a
+    /// [`MetricsSummary`] these are the successful attempts, skipping any failures in between.
+    pub fn jitter_ms(&self) -> f64 {
+        self.jitter_ms
+    }
+
+    /// Returns the statistics in the style of `ping` on two lines:
+    /// `"rtt min/avg/max/mdev = N/N/N/N ms, jitter = N ms"` and
+    /// `"rtt p50/p90/p95/p99 = N/N/N/N ms"`.
+    pub fn report(&self) -> String {
+        format!(
+            "rtt min/avg/max/mdev = {:.3}/{:.3}/{:.3}/{:.3} ms, jitter = {:.3} ms\nrtt p50/p90/p95/p99 = {:.3}/{:.3}/{:.3}/{:.3} ms",
+            self.min_ms,
+            self.avg_ms,
+            self.max_ms,
+            self.mdev_ms,
+            self.jitter_ms,
+            self.p50_ms,
+            self.p90_ms,
+            self.p95_ms,
+            self.p99_ms
         )
     }
 }
 
+/// Latency histogram of a set of attempt durations.
+///
+/// Bucket `i` counts the durations greater than edge `i - 1` and less than or equal to edge `i`.
+/// There is one more count than there are edges, for the durations above the last edge.
+/// Produced by [`MetricsSummary::histogram`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Histogram {
+    edges_ms: Vec<f64>,
+    counts: Vec<u32>,
+}
+
+impl Histogram {
+    /// Returns the upper edge of each bucket in milliseconds, in ascending order.
+    pub fn edges_ms(&self) -> &[f64] {
+        &self.edges_ms
+    }
+
+    /// Returns the number of durations in each bucket. The last count is for durations above the
+    /// last edge.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Returns the histogram as a text bar chart with a line per bucket, scaled so the largest
+    /// bucket is 40 characters wide.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeDelta};
+    /// use port_tester::core::metrics::{Metrics, Status};
+    /// use port_tester::Verbosity;
+    ///
+    /// let ms = |v| TimeDelta::try_milliseconds(v).unwrap();
+    /// let mut m = Metrics::new(&Verbosity::Normal);
+    /// m.set_buckets(vec![ms(10), ms(100)]);
+    /// for (seq, v) in [(1, 5), (2, 50), (3, 60)] {
+    ///     m.record(seq, Local::now(), ms(v), Status::Success);
+    /// }
+    /// println!("{}", m.histogram().report());
+    /// // Output:
+    /// // latency histogram:
+    /// //   <= 10 ms  1 ####################
+    /// //  <= 100 ms  2 ########################################
+    /// //   > 100 ms  0
+    /// ```
+    pub fn report(&self) -> String {
+        let mut labels: Vec<String> = self
+            .edges_ms
+            .iter()
+            .map(|e| format!("<= {} ms", e))
+            .collect();
+        labels.push(match self.edges_ms.last() {
+            Some(e) => format!("> {} ms", e),
+            None => "all".to_string(),
+        });
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        let count_width = max.to_string().len();
+
+        let mut report = String::from("latency histogram:");
+        for (label, count) in labels.iter().zip(&self.counts) {
+            let bar = match max {
+                0 => 0,
+                _ => (*count as u64 * HISTOGRAM_WIDTH as u64).div_ceil(max as u64) as usize,
+            };
+            let _ = write!(
+                report,
+                "\n  {:>label_width$}  {:>count_width$} {}",
+                label,
+                count,
+                "#".repeat(bar)
+            );
+        }
+        report
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Parse a comma separated list of histogram bucket edges in milliseconds, like `1,5,10,50` or
+/// `0.5,1,2`.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if `spec` is empty or contains an edge that is not
+/// a positive number.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::metrics::parse_buckets;
+///
+/// let edges = parse_buckets("0.5,1,10").unwrap();
+/// assert_eq!(edges[0], chrono::TimeDelta::microseconds(500));
+/// assert!(parse_buckets("1,-5").is_err());
+/// ```
+pub fn parse_buckets(spec: &str) -> Result<Vec<chrono::TimeDelta>> {
+    spec.split(',')
+        .map(|edge| match edge.trim().parse::<f64>() {
+            Ok(ms) if ms > 0.0 && ms.is_finite() => Ok(from_ms(ms)),
+            _ => Err(Error::new(SourceError::Msg(format!(
+                "Invalid histogram bucket: {}",
+                edge
+            )))
+            .set_code(CODE_OPTIONS_ERROR)),
+        })
+        .collect()
+}
+
+/// Join `fields` into a single line separated by `delimiter`, without a line ending.
+///
+/// Separated by tabs, tabs and new lines in a field are replaced by spaces. Otherwise, a field
+/// containing the delimiter, a quote or a new line is quoted as described by RFC 4180.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::metrics::delimited_line;
+///
+/// assert_eq!(delimited_line(&["a", "b,c", "d\"e"], ','), r#"a,"b,c","d""e""#);
+/// assert_eq!(delimited_line(&["a", "b\tc"], '\t'), "a\tb c");
+/// ```
+pub fn delimited_line<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+    let sep = delimiter.to_string();
+    fields
+        .iter()
+        .map(|f| {
+            let f = f.as_ref();
+            if delimiter == '\t' {
+                f.replace(['\t', '\r', '\n'], " ")
+            } else if f.contains([delimiter, '"', '\r', '\n']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&sep)
+}
+
+/// Convert fractional milliseconds to a duration, rounded to the microsecond.
+fn from_ms(ms: f64) -> chrono::TimeDelta {
+    chrono::TimeDelta::microseconds((ms * 1000.0).round() as i64)
+}
+
+/// Convert a duration to fractional milliseconds.
+fn to_ms(d: chrono::TimeDelta) -> f64 {
+    match d.num_microseconds() {
+        Some(us) => us as f64 / 1000.0,
+        None => d.num_milliseconds() as f64,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::SourceError;
@@ -887,6 +2176,129 @@ mod tests {
         assert_eq!(mr_json.timestamp(), start.to_rfc3339());
         assert_eq!(mr_json.duration_ms(), 1234);
         assert_eq!(mr_json.status(), "ok");
+        assert_eq!(mr_json.dns_ms(), None);
+        assert_eq!(mr_json.connect_ms(), None);
+        assert_eq!(mr_json.handshake_ms(), None);
+
+        let timings = Timings::default()
+            .set_dns(chrono::TimeDelta::try_milliseconds(7).unwrap())
+            .set_connect(chrono::TimeDelta::try_milliseconds(34).unwrap())
+            .set_handshake(chrono::TimeDelta::try_milliseconds(1200).unwrap());
+        let mr = MetricsResult::new(1, start, dur, Status::Success).with_timings(timings);
+        let mr_json = MetricsResultJSON::from(&mr);
+        assert_eq!(mr_json.dns_ms(), Some(7));
+        assert_eq!(mr_json.connect_ms(), Some(34));
+        assert_eq!(mr_json.handshake_ms(), Some(1200));
+        assert_eq!(mr_json.first_byte_ms(), None);
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut m = Metrics::new(&Verbosity::Normal);
+        let dur = chrono::TimeDelta::try_milliseconds(12).unwrap();
+        m.record(1, Local::now(), dur, Status::Success);
+        let refused = std::io::Error::from(ErrorKind::ConnectionRefused);
+        let e = Error::new(SourceError::Io(refused)).set_context("Connect failed, giving up");
+        m.record(2, Local::now(), dur, Status::Failure(Some(e)));
+
+        let csv = m.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert!(lines[1].starts_with("1,"));
+        assert!(lines[1].ends_with(",12,ok,,"));
+        assert!(lines[2].starts_with("2,"));
+        assert!(
+            lines[2]
+                .ends_with(",12,fail,refused,\"Connect failed, giving up: connection refused\"")
+        );
+
+        let tsv = m.to_tsv();
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines[0], CSV_COLUMNS.join("\t"));
+        assert!(
+            lines[2].ends_with("\tfail\trefused\tConnect failed, giving up: connection refused")
+        );
+
+        assert_eq!(
+            Metrics::default().to_csv(),
+            format!("{}\n", CSV_COLUMNS.join(","))
+        );
+    }
+
+    #[test]
+    fn test_timings() {
+        let t = Timings::default();
+        assert!(t.is_empty());
+        assert_eq!(t.to_kv_string(), "");
+
+        let t = t.set_connect(chrono::TimeDelta::try_milliseconds(34).unwrap());
+        assert!(!t.is_empty());
+        assert_eq!(t.to_kv_string(), "connect=34ms");
+
+        let t = t.set_handshake(chrono::TimeDelta::try_milliseconds(1200).unwrap());
+        assert_eq!(t.to_kv_string(), "connect=34ms handshake=1200ms");
+
+        let t = t.set_first_byte(chrono::TimeDelta::try_milliseconds(56).unwrap());
+        assert_eq!(t.first_byte(), chrono::TimeDelta::try_milliseconds(56));
+        assert_eq!(
+            t.to_kv_string(),
+            "connect=34ms handshake=1200ms first_byte=56ms"
+        );
+
+        let dns = chrono::TimeDelta::try_milliseconds(3).unwrap();
+        assert_eq!(Timings::default().set_dns(dns).dns(), Some(dns));
+        assert!(!Timings::default().set_dns(dns).is_empty());
+        assert_eq!(
+            t.set_dns(dns).to_kv_string(),
+            "dns=3ms connect=34ms handshake=1200ms first_byte=56ms"
+        );
+
+        let dur = chrono::TimeDelta::try_milliseconds(1234).unwrap();
+        let start = Local::now() - dur;
+        let mr = MetricsResult::new(1, start, dur, Status::Success).with_timings(t);
+        assert_eq!(mr.timings(), &t);
+        assert_eq!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(1)),
+            "1 1234ms ok"
+        );
+        assert_eq!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(3)),
+            format!(
+                "start={} seq=1 dur=1234ms connect=34ms handshake=1200ms first_byte=56ms status=ok",
+                start
+            )
+        );
+    }
+
+    #[test]
+    fn test_banner() {
+        let dur = chrono::TimeDelta::try_milliseconds(12).unwrap();
+        let start = Local::now() - dur;
+        let mr = MetricsResult::new(1, start, dur, Status::Success)
+            .with_banner("SSH-2.0-OpenSSH_9.6\r\n".to_string());
+        assert_eq!(mr.banner(), Some("SSH-2.0-OpenSSH_9.6\r\n"));
+        assert_eq!(mr.to_string_with_verbosity(&Verbosity::Normal), "1 ok");
+        assert_eq!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(1)),
+            "1 12ms ok"
+        );
+        assert_eq!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(2)),
+            format!("{} 1 12ms ok banner=\"SSH-2.0-OpenSSH_9.6\\r\\n\"", start)
+        );
+        assert_eq!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(3)),
+            format!(
+                "start={} seq=1 dur=12ms status=ok banner=\"SSH-2.0-OpenSSH_9.6\\r\\n\"",
+                start
+            )
+        );
+
+        let mr_json = MetricsResultJSON::from(&mr);
+        assert_eq!(mr_json.banner(), Some("SSH-2.0-OpenSSH_9.6\r\n"));
+        let mr_json = MetricsResultJSON::from(&MetricsResult::new(1, start, dur, Status::Success));
+        assert_eq!(mr_json.banner(), None);
     }
 
     #[test]
@@ -910,7 +2322,225 @@ mod tests {
         // Test report()
         assert_eq!(
             ms.report(),
-            "attempts: 2, success: 1, fail: 1, failure rate: 50.00%".to_string()
+            "attempts: 2, success: 1, fail: 1, failure rate: 50.00%\nfailures: 1 other".to_string()
+        );
+    }
+
+    #[test]
+    fn test_failure_kind() {
+        let io = |kind| {
+            Status::Failure(Some(Error::new(SourceError::Io(std::io::Error::from(
+                kind,
+            )))))
+        };
+        let cases = [
+            (ErrorKind::ConnectionRefused, FailureKind::Refused),
+            (ErrorKind::TimedOut, FailureKind::Timeout),
+            (ErrorKind::WouldBlock, FailureKind::Timeout),
+            (ErrorKind::HostUnreachable, FailureKind::HostUnreachable),
+            (
+                ErrorKind::NetworkUnreachable,
+                FailureKind::NetworkUnreachable,
+            ),
+            (ErrorKind::ConnectionReset, FailureKind::Reset),
+            (ErrorKind::ConnectionAborted, FailureKind::Reset),
+            (ErrorKind::BrokenPipe, FailureKind::Reset),
+            (ErrorKind::AddrNotAvailable, FailureKind::Other),
+        ];
+        for (kind, want) in cases {
+            assert_eq!(io(kind).failure_kind(), Some(want), "{:?}", kind);
+        }
+        let msg = Status::Failure(Some(Error::new(SourceError::from("test error"))));
+        assert_eq!(msg.failure_kind(), Some(FailureKind::Other));
+        assert_eq!(Status::Success.failure_kind(), None);
+        assert_eq!(FailureKind::Resolve.to_string(), "resolve");
+
+        // A probe can override the kind of a failure but not give a success one.
+        let dur = chrono::TimeDelta::try_milliseconds(10).unwrap();
+        let mr = |status| MetricsResult::new(1, Local::now(), dur, status);
+        let mut ms = MetricsSummary::default();
+        ms.record_result(&mr(io(ErrorKind::ConnectionReset)));
+        ms.record_result(&mr(io(ErrorKind::TimedOut)));
+        ms.record_result(&mr(io(ErrorKind::TimedOut)));
+        ms.record_result(&mr(msg).with_failure_kind(FailureKind::Dns));
+        let ok = mr(Status::Success).with_failure_kind(FailureKind::Dns);
+        assert_eq!(ok.failure_kind(), None);
+        ms.record_result(&ok);
+
+        assert_eq!(ms.success(), 1);
+        assert_eq!(
+            ms.failure_kinds().iter().collect::<Vec<_>>(),
+            vec![
+                (&FailureKind::Timeout, &2),
+                (&FailureKind::Dns, &1),
+                (&FailureKind::Reset, &1)
+            ]
+        );
+        assert!(
+            ms.report()
+                .contains("\nfailures: 2 timeout, 1 dns, 1 reset\n")
+        );
+    }
+
+    /// Returns the [`Latency`] statistics of `durations` recorded in order.
+    fn latency_of(durations: &[chrono::TimeDelta]) -> Option<Latency> {
+        let mut latencies = Latencies::default();
+        for d in durations {
+            latencies.record(*d);
+        }
+        latencies.latency()
+    }
+
+    #[test]
+    fn test_latency() {
+        assert!(latency_of(&[]).is_none());
+
+        let ms = |v: i64| chrono::TimeDelta::try_milliseconds(v).unwrap();
+        let l = latency_of(&[ms(5)]).unwrap();
+        assert_eq!(
+            (l.min_ms(), l.avg_ms(), l.max_ms(), l.mdev_ms()),
+            (5.0, 5.0, 5.0, 0.0)
+        );
+        assert_eq!((l.p50_ms(), l.p99_ms()), (5.0, 5.0));
+
+        // 1ms to 100ms in reverse order, so each percentile is its own rank.
+        let durations: Vec<_> = (1..=100).rev().map(ms).collect();
+        let l = latency_of(&durations).unwrap();
+        assert_eq!(l.min_ms(), 1.0);
+        assert_eq!(l.max_ms(), 100.0);
+        assert_eq!(l.avg_ms(), 50.5);
+        assert!((l.mdev_ms() - 28.866).abs() < 0.001);
+        assert_eq!(
+            (l.p50_ms(), l.p90_ms(), l.p95_ms(), l.p99_ms()),
+            (50.0, 90.0, 95.0, 99.0)
+        );
+
+        let l = latency_of(&[chrono::TimeDelta::microseconds(1500)]).unwrap();
+        assert_eq!(l.min_ms(), 1.5);
+
+        // Failed attempts are left out of the latency statistics.
+        let mut summary = MetricsSummary::default();
+        let fail = MetricsResult::new(1, Local::now(), ms(5000), Status::Failure(None));
+        summary.record_result(&fail);
+        assert!(summary.latency().is_none());
+        assert_eq!(
+            summary.report(),
+            "attempts: 1, success: 0, fail: 1, failure rate: 100.00%\nfailures: 1 other"
+        );
+        summary.record_result(&MetricsResult::new(
+            2,
+            Local::now(),
+            ms(10),
+            Status::Success,
+        ));
+        summary.record_result(&MetricsResult::new(
+            3,
+            Local::now(),
+            ms(30),
+            Status::Success,
+        ));
+        assert_eq!(summary.latency().unwrap().avg_ms(), 20.0);
+        assert_eq!(
+            summary.report(),
+            "attempts: 3, success: 2, fail: 1, failure rate: 33.33%\n\
+             failures: 1 other\n\
+             rtt min/avg/max/mdev = 10.000/20.000/30.000/10.000 ms, jitter = 1.250 ms\n\
+             rtt p50/p90/p95/p99 = 10.000/30.000/30.000/30.000 ms"
+        );
+    }
+
+    #[test]
+    fn test_jitter() {
+        let ms = |v: i64| chrono::TimeDelta::try_milliseconds(v).unwrap();
+        assert_eq!(latency_of(&[ms(10)]).unwrap().jitter_ms(), 0.0);
+        assert_eq!(
+            latency_of(&[ms(10), ms(10), ms(10)]).unwrap().jitter_ms(),
+            0.0
+        );
+
+        // Each step adds 1/16 of the difference between |D| and the current jitter.
+        let l = latency_of(&[ms(10), ms(26), ms(10)]).unwrap();
+        assert_eq!(l.jitter_ms(), 1.0 + (16.0 - 1.0) / 16.0);
+
+        // Jitter follows recording order, so the same durations sorted have less of it.
+        let swings = latency_of(&[ms(10), ms(50), ms(10), ms(50)]).unwrap();
+        let steady = latency_of(&[ms(10), ms(10), ms(50), ms(50)]).unwrap();
+        assert_eq!(swings.avg_ms(), steady.avg_ms());
+        assert!(swings.jitter_ms() > steady.jitter_ms());
+
+        // A failed attempt in between is skipped, so its duration doesn't add to the jitter.
+        let mut summary = MetricsSummary::default();
+        let fail = Status::Failure(None);
+        for (v, status) in [
+            (10, Status::Success),
+            (5000, fail),
+            (26, Status::Success),
+            (10, Status::Success),
+        ] {
+            summary.record_result(&MetricsResult::new(1, Local::now(), ms(v), status));
+        }
+        assert_eq!(summary.latency().unwrap().jitter_ms(), l.jitter_ms());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let ms = |v: i64| chrono::TimeDelta::try_milliseconds(v).unwrap();
+        let mut summary = MetricsSummary::default();
+        let h = summary.histogram();
+        assert_eq!(h.edges_ms(), &DEFAULT_BUCKETS_MS);
+        assert_eq!(h.counts(), &[0; 13]);
+
+        summary.set_buckets(vec![ms(100), ms(10), ms(10)]);
+        for v in [1, 10, 11, 100, 101, 5000] {
+            summary.record_result(&MetricsResult::new(1, Local::now(), ms(v), Status::Success));
+        }
+        summary.record_result(&MetricsResult::new(
+            1,
+            Local::now(),
+            ms(1),
+            Status::Failure(None),
+        ));
+        let h = summary.histogram();
+        assert_eq!(h.edges_ms(), &[10.0, 100.0]);
+        assert_eq!(h.counts(), &[2, 2, 2]);
+        assert_eq!(
+            h.report(),
+            format!(
+                "latency histogram:\n   <= 10 ms  2 {0}\n  <= 100 ms  2 {0}\n   > 100 ms  2 {0}",
+                "#".repeat(40)
+            )
+        );
+
+        summary.set_buckets(vec![chrono::TimeDelta::microseconds(500)]);
+        summary.record_result(&MetricsResult::new(1, Local::now(), ms(0), Status::Success));
+        let h = summary.histogram();
+        assert_eq!(h.counts(), &[1, 6]);
+        assert_eq!(
+            h.report(),
+            format!(
+                "latency histogram:\n  <= 0.5 ms  1 {}\n   > 0.5 ms  6 {}",
+                "#".repeat(7),
+                "#".repeat(40)
+            )
+        );
+
+        summary.set_buckets(Vec::new());
+        assert_eq!(summary.histogram().counts(), &[7]);
+        assert_eq!(
+            summary.histogram().report(),
+            format!("latency histogram:\n  all  7 {}", "#".repeat(40))
+        );
+
+        assert_eq!(
+            parse_buckets("1, 2.5").unwrap(),
+            vec![ms(1), chrono::TimeDelta::microseconds(2500)]
+        );
+        assert!(parse_buckets("").is_err());
+        assert!(parse_buckets("0").is_err());
+        assert!(parse_buckets("inf").is_err());
+        assert_eq!(
+            parse_buckets("fast").err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
         );
     }
 
@@ -937,16 +2567,21 @@ mod tests {
         assert!(m.result(2).unwrap().is_err());
 
         // Test report()
-        assert_eq!(
-            m.report(),
-            "attempts: 2, success: 1, fail: 1, failure rate: 50.00%".to_string()
-        );
-
-        // Test report()
+        let report = "attempts: 2, success: 1, fail: 1, failure rate: 50.00%\n\
+            failures: 1 other\n\
+            rtt min/avg/max/mdev = 1234.000/1234.000/1234.000/0.000 ms, jitter = 0.000 ms\n\
+            rtt p50/p90/p95/p99 = 1234.000/1234.000/1234.000/1234.000 ms";
+        assert_eq!(m.report(), report.to_string());
+
+        // Test full_report()
+        m.set_buckets(vec![chrono::TimeDelta::try_milliseconds(1000).unwrap()]);
         assert_eq!(
             m.full_report(),
-            "1 ok\n2 fail: test error\n\nattempts: 2, success: 1, fail: 1, failure rate: 50.00%\n"
-                .to_string()
+            format!(
+                "1 ok\n2 fail: test error\n\n{}\n\nlatency histogram:\n  <= 1000 ms  0\n   > 1000 ms  1 {}\n",
+                report,
+                "#".repeat(40)
+            )
         );
     }
 
@@ -968,10 +2603,157 @@ mod tests {
         assert_eq!(m_json.success(), 1);
         assert_eq!(m_json.failure(), 1);
         assert_eq!(m_json.failure_rate(), 50.00);
+        assert_eq!(m_json.latency().unwrap().p99_ms(), 1234.0);
+        assert_eq!(m_json.histogram().unwrap().counts().iter().sum::<u32>(), 1);
+        assert!(m_json.outages().windows().is_empty());
+        assert_eq!(m_json.failure_kinds().get(&FailureKind::Other), Some(&1));
+        assert_eq!(m_json.results()[1].failure_kind(), Some(FailureKind::Other));
 
         // Test pulling back a result.
         let m_string = m_json.to_json_string();
+        assert!(
+            m_string
+                .as_ref()
+                .unwrap()
+                .contains("\"latency\":{\"min_ms\":1234.0,")
+        );
+        assert!(
+            m_string
+                .as_ref()
+                .unwrap()
+                .contains("\"outages\":{\"threshold\":3,")
+        );
+        assert!(
+            m_string
+                .as_ref()
+                .unwrap()
+                .contains("\"failure_kinds\":{\"other\":1}")
+        );
         assert!(m_string.is_ok());
         assert_ne!(m_string.unwrap(), "".to_string());
     }
+
+    #[test]
+    fn test_outages() {
+        let mut m = Metrics::new(&Verbosity::Normal);
+        m.set_outage_threshold(2);
+        let dur = chrono::TimeDelta::try_milliseconds(100).unwrap();
+        let start = Local::now();
+        for (i, ok) in [true, false, false, false, true].iter().enumerate() {
+            let at = start + chrono::TimeDelta::try_seconds(i as i64).unwrap();
+            m.record(i as u32 + 1, at, dur, Status::new(*ok, None));
+        }
+
+        assert_eq!(m.outages().threshold(), 2);
+        assert_eq!(m.outages().len(), 1);
+        assert_eq!(m.outages().iter().next().unwrap().failures(), 3);
+        assert!(
+            m.full_report()
+                .contains("\n\noutages: 1, longest: 3s, total downtime: 3s\n  ")
+        );
+        assert_eq!(m.to_json().outages().downtime_ms(), 3000);
+    }
+
+    #[test]
+    fn test_addrs() {
+        let (a, b): (SocketAddr, SocketAddr) = (
+            "192.0.2.1:80".parse().unwrap(),
+            "192.0.2.22:80".parse().unwrap(),
+        );
+        let dur = chrono::TimeDelta::try_milliseconds(10).unwrap();
+        let refused = Error::new(SourceError::Io(ErrorKind::ConnectionRefused.into()));
+
+        let mut m = Metrics::new(&Verbosity::Verbose(2));
+        m.record_result(
+            MetricsResult::new(1, Local::now(), dur, Status::Success).with_addrs(vec![
+                AddrResult::new(a, dur, Some(&refused)),
+                AddrResult::new(b, dur, None),
+            ]),
+        );
+        m.record_result(
+            MetricsResult::new(2, Local::now(), dur, Status::Success)
+                .with_addrs(vec![AddrResult::new(b, dur, None)]),
+        );
+
+        let mr = m.result(1).unwrap();
+        assert_eq!(mr.addr(), Some(b));
+        assert!(
+            mr.to_string_with_verbosity(&Verbosity::Verbose(2))
+                .ends_with(" ok addr=192.0.2.22:80")
+        );
+        assert!(!mr.to_string().contains("addr="));
+        assert!(MetricsResult::default().addr().is_none());
+
+        let summaries = m.addr_summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!((summaries[0].addr(), summaries[0].failure()), (a, 1));
+        assert_eq!((summaries[1].addr(), summaries[1].success()), (b, 2));
+        assert_eq!(
+            m.addr_report(),
+            "addresses:\n  \
+             192.0.2.1:80   attempts: 1, success: 0, fail: 1, failure rate: 100.00%\n  \
+             192.0.2.22:80  attempts: 2, success: 2, fail: 0, failure rate: 0.00%, \
+             rtt min/avg/max = 10.000/10.000/10.000 ms"
+        );
+        assert!(
+            m.full_report()
+                .contains(&format!("\n\n{}\n", m.addr_report()))
+        );
+
+        let json = m.to_json();
+        assert_eq!(json.addrs().len(), 2);
+        assert_eq!(json.results()[0].addr(), Some(b));
+        assert_eq!(
+            json.results()[0].addrs()[0].failure_kind(),
+            Some(FailureKind::Refused)
+        );
+        let json = json.to_json_string().unwrap();
+        assert!(json.contains("\"addr\":\"192.0.2.22:80\",\"addrs\":[{\"addr\":\"192.0.2.1:80\""));
+        assert!(!json.contains("\"race\""));
+        assert!(!json.contains("\"races\""));
+        assert!(m.races().races() == 0);
+        assert!(!m.full_report().contains("happy eyeballs"));
+    }
+
+    #[test]
+    fn test_races() {
+        let (v6, v4): (SocketAddr, SocketAddr) = (
+            "[2001:db8::1]:80".parse().unwrap(),
+            "192.0.2.1:80".parse().unwrap(),
+        );
+        let dur = chrono::TimeDelta::try_milliseconds(10).unwrap();
+        let timeout = Error::new(SourceError::Io(ErrorKind::TimedOut.into()));
+
+        let mut m = Metrics::new(&Verbosity::Normal);
+        m.record_result(
+            MetricsResult::new(1, Local::now(), dur, Status::Success)
+                .with_addrs(vec![AddrResult::new(v6, dur, None)])
+                .with_race(),
+        );
+        m.record_result(
+            MetricsResult::new(2, Local::now(), dur, Status::Success)
+                .with_addrs(vec![AddrResult::new(v4, dur, None)])
+                .with_race(),
+        );
+        // Failed races have no winner.
+        m.record_result(
+            MetricsResult::new(3, Local::now(), dur, Status::Failure(Some(timeout)))
+                .with_addrs(vec![AddrResult::new(v6, dur, None)])
+                .with_race(),
+        );
+        assert!(m.result(1).unwrap().is_race());
+        assert_eq!(m.races().races(), 2);
+        assert_eq!(m.races().ipv6_won(), 1);
+        assert!(
+            m.full_report().contains(
+                "\n\nhappy eyeballs: 2 races, ipv6 won 1, ipv4 won 1, ipv6 lost 50.00%\n"
+            )
+        );
+
+        let json = m.to_json();
+        assert!(json.results()[0].race());
+        assert_eq!(json.races().unwrap().ipv4_won(), 1);
+        let json = json.to_json_string().unwrap();
+        assert!(json.contains("\"races\":{\"races\":2,\"ipv6_won\":1,\"ipv4_won\":1}"));
+    }
 }
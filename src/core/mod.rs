@@ -1,7 +1,14 @@
 pub use self::error::*;
 pub use self::metrics::Metrics;
 
+pub mod addr;
+#[cfg(feature = "tls")]
+pub mod cert;
 pub mod error;
 pub mod host;
 pub mod log;
 pub mod metrics;
+pub mod outage;
+pub mod prometheus;
+pub mod resolver;
+pub mod scan;
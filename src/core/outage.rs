@@ -0,0 +1,448 @@
+//! Outage detection over a sequence of attempts.
+//!
+//! An outage starts when a number of attempts in a row fail and ends at the first successful
+//! attempt after them. [`Outages`] tracks each [`Outage`] window as results are recorded into
+//! [`Metrics`], along with the longest outage and the total downtime.
+//!
+//! [`Metrics`]: crate::core::metrics::Metrics
+
+use crate::core::metrics::MetricsResult;
+use chrono::Local;
+use std::fmt::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of consecutive failed attempts that make an outage by default.
+pub const DEFAULT_OUTAGE_THRESHOLD: u32 = 3;
+
+/// A window of consecutive failed attempts.
+///
+/// An outage starts at the start of its first failed attempt and ends at the start of the first
+/// successful attempt after it. An outage that has not ended is ongoing and its duration runs to
+/// the end of its last failed attempt.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Outage {
+    start: chrono::DateTime<Local>,
+    end: Option<chrono::DateTime<Local>>,
+    /// End of the most recent failed attempt.
+    last_failure: chrono::DateTime<Local>,
+    failures: u32,
+}
+
+impl Outage {
+    /// Returns the start of the first failed attempt.
+    pub fn start(&self) -> chrono::DateTime<Local> {
+        self.start
+    }
+
+    /// Returns the start of the successful attempt that ended the outage, or `None` if the outage
+    /// is ongoing.
+    pub fn end(&self) -> Option<chrono::DateTime<Local>> {
+        self.end
+    }
+
+    /// Returns `true` if no attempt has succeeded since the outage started.
+    pub fn is_ongoing(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Returns the number of failed attempts in the outage.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Returns the length of the outage. An ongoing outage runs to the end of its last failed
+    /// attempt.
+    pub fn duration(&self) -> chrono::TimeDelta {
+        self.end.unwrap_or(self.last_failure) - self.start
+    }
+
+    /// Returns the outage as `"<start> to <end>, <duration>, N failed attempts"`.
+    pub fn report(&self) -> String {
+        let end = match self.end {
+            Some(end) => end.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => "ongoing".to_string(),
+        };
+        format!(
+            "{} to {}, {}, {} failed attempts",
+            self.start.format("%Y-%m-%d %H:%M:%S"),
+            end,
+            format_duration(self.duration()),
+            self.failures
+        )
+    }
+}
+
+/// Tracks [`Outage`]s across recorded attempts.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeDelta};
+/// use port_tester::core::metrics::{MetricsResult, Status};
+/// use port_tester::core::outage::Outages;
+///
+/// let mut outages = Outages::new().set_threshold(2);
+/// let start = Local::now();
+/// let dur = TimeDelta::try_milliseconds(100).unwrap();
+/// for (seq, ok) in [(1, true), (2, false), (3, false), (4, false), (5, true)] {
+///     let at = start + TimeDelta::try_seconds(seq as i64).unwrap();
+///     outages.record(&MetricsResult::new(seq, at, dur, Status::new(ok, None)));
+/// }
+///
+/// let outage = outages.iter().next().unwrap();
+/// assert_eq!(outage.failures(), 3);
+/// assert_eq!(outage.duration(), TimeDelta::try_seconds(3).unwrap());
+/// assert_eq!(outages.downtime(), outage.duration());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Outages {
+    threshold: u32,
+    outages: Vec<Outage>,
+    /// Failed attempts in a row so far, which become an outage once they reach the threshold.
+    streak: Option<Outage>,
+}
+
+/// Defaults to an outage after [`DEFAULT_OUTAGE_THRESHOLD`] consecutive failures.
+impl Default for Outages {
+    fn default() -> Self {
+        Outages {
+            threshold: DEFAULT_OUTAGE_THRESHOLD,
+            outages: Vec::new(),
+            streak: None,
+        }
+    }
+}
+
+impl Outages {
+    /// Create a new [`Outages`] tracker. See [`Outages::default`].
+    pub fn new() -> Self {
+        Outages::default()
+    }
+
+    /// Set the number of consecutive failed attempts that make an outage. A threshold of 0 is
+    /// treated as 1.
+    pub fn set_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    /// Returns the number of consecutive failed attempts that make an outage.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Update the outages with the result of the next attempt.
+    pub fn record(&mut self, result: &MetricsResult) {
+        if !result.is_err() {
+            if let Some(outage) = self.outages.last_mut() {
+                if outage.is_ongoing() {
+                    outage.end = Some(result.timestamp());
+                }
+            }
+            self.streak = None;
+            return;
+        }
+
+        let end = result.timestamp() + result.duration();
+        match self.outages.last_mut() {
+            // The failures continue an ongoing outage.
+            Some(outage) if outage.is_ongoing() => {
+                outage.failures += 1;
+                outage.last_failure = end;
+                return;
+            }
+            _ => {}
+        }
+
+        let streak = self.streak.get_or_insert(Outage {
+            start: result.timestamp(),
+            end: None,
+            last_failure: end,
+            failures: 0,
+        });
+        streak.failures += 1;
+        streak.last_failure = end;
+        if streak.failures >= self.threshold {
+            self.outages.push(*streak);
+            self.streak = None;
+        }
+    }
+
+    /// Returns the number of outages, including any that are ongoing.
+    pub fn len(&self) -> usize {
+        self.outages.len()
+    }
+
+    /// Returns `true` if there have been no outages.
+    pub fn is_empty(&self) -> bool {
+        self.outages.is_empty()
+    }
+
+    /// Returns an iterator over the outages in the order they started.
+    pub fn iter(&self) -> impl Iterator<Item = &Outage> {
+        self.outages.iter()
+    }
+
+    /// Returns the longest outage, or `None` if there have been no outages.
+    pub fn longest(&self) -> Option<&Outage> {
+        self.outages.iter().max_by_key(|o| o.duration())
+    }
+
+    /// Returns the total duration of all outages.
+    pub fn downtime(&self) -> chrono::TimeDelta {
+        self.outages
+            .iter()
+            .fold(chrono::TimeDelta::zero(), |total, o| total + o.duration())
+    }
+
+    /// Returns a summary line followed by a line for each outage.
+    ///
+    /// Output format: `"outages: N, longest: <duration>, total downtime: <duration>"`
+    pub fn report(&self) -> String {
+        let longest = self
+            .longest()
+            .map(|o| o.duration())
+            .unwrap_or_else(chrono::TimeDelta::zero);
+        let mut report = format!(
+            "outages: {}, longest: {}, total downtime: {}",
+            self.len(),
+            format_duration(longest),
+            format_duration(self.downtime())
+        );
+        for outage in &self.outages {
+            let _ = write!(report, "\n  {}", outage.report());
+        }
+        report
+    }
+
+    /// Returns an owned [`OutagesJSON`] snapshot of the outages.
+    pub fn to_json(&self) -> OutagesJSON {
+        OutagesJSON {
+            threshold: self.threshold,
+            longest_ms: self
+                .longest()
+                .map_or(0, |o| o.duration().num_milliseconds()),
+            downtime_ms: self.downtime().num_milliseconds(),
+            windows: self.outages.iter().map(OutageJSON::from).collect(),
+        }
+    }
+}
+
+/// Serializable representation of an [`Outage`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct OutageJSON {
+    /// RFC 3339 formatted start time.
+    start: String,
+    /// RFC 3339 formatted end time, or `None` if the outage is ongoing.
+    end: Option<String>,
+    /// Length of the outage in milliseconds.
+    duration_ms: i64,
+    /// Number of failed attempts in the outage.
+    failures: u32,
+}
+
+impl OutageJSON {
+    /// Returns the RFC 3339 start time.
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    /// Returns the RFC 3339 end time, or `None` if the outage is ongoing.
+    pub fn end(&self) -> Option<&str> {
+        self.end.as_deref()
+    }
+
+    /// Returns the length of the outage in milliseconds.
+    pub fn duration_ms(&self) -> i64 {
+        self.duration_ms
+    }
+
+    /// Returns the number of failed attempts in the outage.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+}
+
+impl From<&Outage> for OutageJSON {
+    fn from(o: &Outage) -> Self {
+        OutageJSON {
+            start: o.start.to_rfc3339(),
+            end: o.end.map(|e| e.to_rfc3339()),
+            duration_ms: o.duration().num_milliseconds(),
+            failures: o.failures,
+        }
+    }
+}
+
+/// Serializable representation of [`Outages`]. Produced by [`Outages::to_json`].
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct OutagesJSON {
+    /// Number of consecutive failed attempts that make an outage.
+    threshold: u32,
+    /// Length of the longest outage in milliseconds.
+    longest_ms: i64,
+    /// Total length of all outages in milliseconds.
+    downtime_ms: i64,
+    /// Each outage in the order they started.
+    windows: Vec<OutageJSON>,
+}
+
+impl OutagesJSON {
+    /// Returns the number of consecutive failed attempts that make an outage.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Returns the length of the longest outage in milliseconds.
+    pub fn longest_ms(&self) -> i64 {
+        self.longest_ms
+    }
+
+    /// Returns the total length of all outages in milliseconds.
+    pub fn downtime_ms(&self) -> i64 {
+        self.downtime_ms
+    }
+
+    /// Returns each outage in the order they started.
+    pub fn windows(&self) -> &[OutageJSON] {
+        &self.windows
+    }
+}
+
+/// Format a duration as milliseconds below a second, otherwise as hours, minutes and seconds,
+/// like `850ms` or `1h2m3s`.
+fn format_duration(d: chrono::TimeDelta) -> String {
+    let secs = d.num_seconds();
+    if secs < 1 {
+        return format!("{}ms", d.num_milliseconds());
+    }
+
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (h, m) {
+        (0, 0) => format!("{}s", s),
+        (0, _) => format!("{}m{}s", m, s),
+        _ => format!("{}h{}m{}s", h, m, s),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::metrics::Status;
+
+    /// Record an attempt for each of `results` one second apart, each taking 100ms.
+    fn track(outages: &mut Outages, start: chrono::DateTime<Local>, results: &[bool]) {
+        let dur = chrono::TimeDelta::try_milliseconds(100).unwrap();
+        for (i, ok) in results.iter().enumerate() {
+            let at = start + chrono::TimeDelta::try_seconds(i as i64).unwrap();
+            outages.record(&MetricsResult::new(
+                i as u32 + 1,
+                at,
+                dur,
+                Status::new(*ok, None),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_outages() {
+        let start = Local::now();
+        let secs = |s: i64| chrono::TimeDelta::try_seconds(s).unwrap();
+        let mut outages = Outages::new();
+        assert_eq!(outages.threshold(), DEFAULT_OUTAGE_THRESHOLD);
+
+        // Two failures in a row are below the threshold. Five are one outage from attempt 4 that
+        // ends when attempt 9 succeeds.
+        track(
+            &mut outages,
+            start,
+            &[
+                true, false, false, true, false, false, false, false, false, true,
+            ],
+        );
+        assert_eq!(outages.len(), 1);
+        let o = outages.iter().next().unwrap();
+        assert_eq!(o.start(), start + secs(4));
+        assert_eq!(o.end(), Some(start + secs(9)));
+        assert_eq!(o.failures(), 5);
+        assert_eq!(o.duration(), secs(5));
+        assert!(!o.is_ongoing());
+
+        // An ongoing outage runs to the end of its last failure.
+        let mut outages = Outages::new().set_threshold(1);
+        track(&mut outages, start, &[false, true, false, false]);
+        assert_eq!(outages.len(), 2);
+        let o = outages.iter().last().unwrap();
+        assert!(o.is_ongoing());
+        assert_eq!(o.failures(), 2);
+        assert_eq!(
+            o.duration(),
+            secs(1) + chrono::TimeDelta::try_milliseconds(100).unwrap()
+        );
+        assert_eq!(outages.longest(), Some(o));
+        assert_eq!(
+            outages.downtime(),
+            secs(2) + chrono::TimeDelta::try_milliseconds(100).unwrap()
+        );
+
+        assert_eq!(Outages::new().set_threshold(0).threshold(), 1);
+        assert!(Outages::new().longest().is_none());
+    }
+
+    #[test]
+    fn test_report() {
+        let start = Local::now();
+        let mut outages = Outages::new().set_threshold(2);
+        assert_eq!(
+            outages.report(),
+            "outages: 0, longest: 0ms, total downtime: 0ms"
+        );
+
+        track(
+            &mut outages,
+            start,
+            &[false, false, true, false, false, false],
+        );
+        let lines: Vec<String> = outages.report().lines().map(String::from).collect();
+        assert_eq!(lines[0], "outages: 2, longest: 2s, total downtime: 4s");
+        assert_eq!(
+            lines[1],
+            format!(
+                "  {} to {}, 2s, 2 failed attempts",
+                start.format("%Y-%m-%d %H:%M:%S"),
+                (start + chrono::TimeDelta::try_seconds(2).unwrap()).format("%Y-%m-%d %H:%M:%S")
+            )
+        );
+        assert!(
+            lines[2].ends_with(" to ongoing, 2s, 3 failed attempts"),
+            "{}",
+            lines[2]
+        );
+
+        let json = outages.to_json();
+        assert_eq!(json.threshold(), 2);
+        assert_eq!(json.windows().len(), 2);
+        assert_eq!(json.windows()[0].duration_ms(), 2000);
+        assert_eq!(json.windows()[0].start(), start.to_rfc3339());
+        assert!(json.windows()[1].end().is_none());
+        assert_eq!(json.longest_ms(), 2100);
+        assert_eq!(json.downtime_ms(), 4100);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let ms = |v: i64| chrono::TimeDelta::try_milliseconds(v).unwrap();
+        assert_eq!(format_duration(ms(850)), "850ms");
+        assert_eq!(format_duration(ms(59_999)), "59s");
+        assert_eq!(format_duration(ms(61_000)), "1m1s");
+        assert_eq!(format_duration(ms(3_723_000)), "1h2m3s");
+    }
+}
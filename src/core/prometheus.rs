@@ -0,0 +1,587 @@
+//! Metrics in the Prometheus text exposition format.
+//!
+//! [`render`] formats the [`Metrics`] of a set of [`Host`]s as Prometheus metrics, labeled by the
+//! target's `host` and `port` and, where an attempt has one, the `addr` that answered it.
+//! [`Exporter`] serves them over HTTP on `/metrics` while the hosts are probed, and
+//! [`write_textfile`] writes them to a file for the node exporter's textfile collector.
+//!
+//! A probe holds its host's lock for the whole attempt, so the exporter doesn't render the hosts
+//! itself. It serves the last [`Snapshot`] published between attempts instead, and a scrape never
+//! waits on an attempt in progress.
+//!
+//! | Metric | Type | Labels |
+//! |---|---|---|
+//! | `pt_success_total` | counter | host, port, addr |
+//! | `pt_failure_total` | counter | host, port, addr |
+//! | `pt_attempt_duration_seconds` | histogram | host, port |
+//! | `pt_attempt_duration_quantile_seconds` | gauge | host, port, quantile |
+//! | `pt_last_success` | gauge | host, port, addr |
+//! | `pt_last_attempt_timestamp_seconds` | gauge | host, port, addr |
+//!
+//! [`Metrics`]: crate::core::metrics::Metrics
+
+use crate::Host;
+use crate::core::error::*;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Content type of the text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Path the metrics are served on by [`Exporter`].
+pub const METRICS_PATH: &str = "/metrics";
+/// Time to wait for a scraper to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Snapshot of the metrics of a single host, taken with the host locked once.
+struct Sample {
+    labels: String,
+    /// Successful and failed attempts by the labels of the address that answered them.
+    counts: BTreeMap<String, (u32, u32)>,
+    /// Cumulative counts of the successful attempts at or below each bucket edge in seconds.
+    buckets: Vec<(f64, u32)>,
+    success: u32,
+    sum: f64,
+    /// The 0.5, 0.9, 0.95 and 0.99 quantiles of the successful attempts in seconds, if any.
+    quantiles: Option<[(&'static str, f64); 4]>,
+    /// Labels of the address, success and start in seconds of the last attempt.
+    last: Option<(String, bool, f64)>,
+}
+
+impl Sample {
+    fn new(host: &Host) -> Self {
+        let labels = format!("host=\"{}\",port=\"{}\"", escape(host.name()), host.port());
+        let addr_labels = |addr: Option<SocketAddr>| {
+            let addr = addr.map(|a| a.to_string()).unwrap_or_default();
+            format!("{},addr=\"{}\"", labels, escape(&addr))
+        };
+
+        let m = host.metrics();
+        let mut counts: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        for mr in m.iter() {
+            let c = counts.entry(addr_labels(mr.addr())).or_default();
+            match mr.is_err() {
+                false => c.0 += 1,
+                true => c.1 += 1,
+            }
+        }
+
+        let histogram = m.histogram();
+        let mut total = 0;
+        let buckets = histogram
+            .edges_ms()
+            .iter()
+            .zip(histogram.counts())
+            .map(|(edge, count)| {
+                total += count;
+                (edge / 1000.0, total)
+            })
+            .collect();
+        let success = m.success();
+        let sum = m.latency_sum_ms() / 1000.0;
+        let quantiles = m.latency().map(|l| {
+            [
+                ("0.5", l.p50_ms() / 1000.0),
+                ("0.9", l.p90_ms() / 1000.0),
+                ("0.95", l.p95_ms() / 1000.0),
+                ("0.99", l.p99_ms() / 1000.0),
+            ]
+        });
+
+        let last = m.iter().last().map(|mr| {
+            let start = mr.timestamp().timestamp_micros() as f64 / 1_000_000.0;
+            (addr_labels(mr.addr()), !mr.is_err(), start)
+        });
+
+        Sample {
+            labels,
+            counts,
+            buckets,
+            success,
+            sum,
+            quantiles,
+            last,
+        }
+    }
+}
+
+/// Returns the metrics of `hosts` in the Prometheus text exposition format. See the
+/// [module documentation](self) for the metrics.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Local;
+/// use port_tester::Host;
+/// use port_tester::core::metrics::Status;
+/// use port_tester::core::prometheus::render;
+/// use std::sync::Mutex;
+///
+/// let mut host = Host::new("127.0.0.1", 22).unwrap();
+/// host.record(1, Local::now(), chrono::TimeDelta::milliseconds(3), Status::Success);
+/// let text = render(&[Mutex::new(host)]);
+/// assert!(text.contains("pt_success_total{host=\"127.0.0.1\",port=\"22\",addr=\"\"} 1"));
+/// ```
+pub fn render(hosts: &[Mutex<Host>]) -> String {
+    let samples: Vec<Sample> = hosts
+        .iter()
+        .map(|h| Sample::new(&h.lock().unwrap()))
+        .collect();
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "pt_success_total",
+        "counter",
+        "Successful attempts by the address that answered.",
+    );
+    for s in &samples {
+        for (labels, (success, _)) in &s.counts {
+            let _ = writeln!(out, "pt_success_total{{{}}} {}", labels, success);
+        }
+    }
+    header(
+        &mut out,
+        "pt_failure_total",
+        "counter",
+        "Failed attempts by the last address tried.",
+    );
+    for s in &samples {
+        for (labels, (_, failure)) in &s.counts {
+            let _ = writeln!(out, "pt_failure_total{{{}}} {}", labels, failure);
+        }
+    }
+
+    header(
+        &mut out,
+        "pt_attempt_duration_seconds",
+        "histogram",
+        "Duration of the successful attempts, the connect time for the port_open probe.",
+    );
+    for s in &samples {
+        for (edge, count) in &s.buckets {
+            let _ = writeln!(
+                out,
+                "pt_attempt_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                s.labels, edge, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "pt_attempt_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            s.labels, s.success
+        );
+        let _ = writeln!(
+            out,
+            "pt_attempt_duration_seconds_sum{{{}}} {}",
+            s.labels, s.sum
+        );
+        let _ = writeln!(
+            out,
+            "pt_attempt_duration_seconds_count{{{}}} {}",
+            s.labels, s.success
+        );
+    }
+
+    header(
+        &mut out,
+        "pt_attempt_duration_quantile_seconds",
+        "gauge",
+        "Quantiles of the duration of the successful attempts.",
+    );
+    for s in &samples {
+        for (quantile, value) in s.quantiles.iter().flatten() {
+            let _ = writeln!(
+                out,
+                "pt_attempt_duration_quantile_seconds{{{},quantile=\"{}\"}} {}",
+                s.labels, quantile, value
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "pt_last_success",
+        "gauge",
+        "Whether the last attempt succeeded.",
+    );
+    for (labels, success, _) in samples.iter().filter_map(|s| s.last.as_ref()) {
+        let _ = writeln!(out, "pt_last_success{{{}}} {}", labels, *success as u8);
+    }
+    header(
+        &mut out,
+        "pt_last_attempt_timestamp_seconds",
+        "gauge",
+        "Start of the last attempt in seconds since the Unix epoch.",
+    );
+    for (labels, _, start) in samples.iter().filter_map(|s| s.last.as_ref()) {
+        let _ = writeln!(
+            out,
+            "pt_last_attempt_timestamp_seconds{{{}}} {}",
+            labels, start
+        );
+    }
+    out
+}
+
+/// Write the metrics of `hosts` to `path` for the node exporter's textfile collector, replacing
+/// the file atomically. The metrics are written to a temporary file next to `path` first, which
+/// is then renamed over it, so the collector never reads a partial file. `path` should end in
+/// `.prom` to be collected.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_RUNTIME_ERROR`] if the temporary file can't be written or
+/// renamed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::core::prometheus::write_textfile;
+/// use std::path::Path;
+/// use std::sync::Mutex;
+///
+/// let hosts = vec![Mutex::new(Host::new("127.0.0.1", 22).unwrap())];
+/// write_textfile(Path::new("/var/lib/node_exporter/textfile/pt.prom"), &hosts).unwrap();
+/// ```
+pub fn write_textfile(path: &Path, hosts: &[Mutex<Host>]) -> Result<()> {
+    let err = |e: std::io::Error, action: &str, p: &Path| {
+        Error::new(SourceError::Io(e))
+            .set_context(&format!("Failed to {} {}", action, p.display()))
+            .set_code(CODE_RUNTIME_ERROR)
+    };
+
+    // Collectors only read files ending in .prom, so the temporary file is never collected.
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(name);
+    std::fs::write(&tmp, render(hosts)).map_err(|e| err(e, "write", &tmp))?;
+    std::fs::rename(&tmp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        err(e, "replace", path)
+    })
+}
+
+/// Metrics of a set of hosts rendered by [`render`] and shared with an [`Exporter`].
+///
+/// Publish the hosts after each attempt, when no probe holds their locks. Clones share the same
+/// metrics.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::Host;
+/// use port_tester::core::prometheus::Snapshot;
+/// use std::sync::Mutex;
+///
+/// let hosts = vec![Mutex::new(Host::new("127.0.0.1", 22).unwrap())];
+/// let snapshot = Snapshot::new();
+/// assert!(snapshot.text().is_empty());
+/// snapshot.publish(&hosts);
+/// assert!(snapshot.text().starts_with("# HELP pt_success_total"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    text: Arc<Mutex<String>>,
+}
+
+impl Snapshot {
+    /// Create a new, empty [`Snapshot`].
+    pub fn new() -> Self {
+        Snapshot::default()
+    }
+
+    /// Replace the metrics with those of `hosts`, locking each host in turn.
+    pub fn publish(&self, hosts: &[Mutex<Host>]) {
+        let text = render(hosts);
+        *self.text.lock().unwrap() = text;
+    }
+
+    /// Returns the last published metrics, or an empty string if none were published.
+    pub fn text(&self) -> String {
+        self.text.lock().unwrap().clone()
+    }
+}
+
+/// Serves the metrics of a set of hosts over HTTP in the Prometheus text exposition format.
+///
+/// Requests for [`METRICS_PATH`] are answered with the last published [`Snapshot`], others with
+/// `404 Not Found`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::core::prometheus::{Exporter, Snapshot};
+/// use std::sync::Mutex;
+///
+/// let hosts = vec![Mutex::new(Host::new("127.0.0.1", 22).unwrap())];
+/// let snapshot = Snapshot::new();
+/// snapshot.publish(&hosts);
+/// let exporter = Exporter::bind("127.0.0.1:9100".parse().unwrap()).unwrap();
+/// exporter.spawn(snapshot.clone());
+/// // Probe the hosts, publishing them to the snapshot after each attempt.
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Exporter {
+    listener: TcpListener,
+}
+
+impl Exporter {
+    /// Listen on `addr`. Use port 0 for any free port and [`Exporter::local_addr`] to find it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`CODE_OPTIONS_ERROR`] if `addr` can't be listened on.
+    pub fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr).map_err(|e| {
+            Error::new(SourceError::Io(e))
+                .set_context(&format!("Failed to listen on {}", addr))
+                .set_code(CODE_OPTIONS_ERROR)
+        })?;
+        Ok(Exporter { listener })
+    }
+
+    /// Returns the address the exporter listens on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address of the socket can't be read.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(|e| Error::new(SourceError::Io(e)))
+    }
+
+    /// Serve the metrics published to `snapshot` on a background thread until the process exits.
+    /// Requests are answered one at a time.
+    pub fn spawn(self, snapshot: Snapshot) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            for stream in self.listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = handle(stream, &snapshot) {
+                            log::debug!("metrics request failed: {}", e);
+                        }
+                    }
+                    Err(e) => log::debug!("metrics connection failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Answer a single HTTP request on `stream`.
+fn handle(stream: TcpStream, snapshot: &Snapshot) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Read the headers up to the blank line so the client sees a clean close.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, path) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let path = path.split('?').next().unwrap_or_default();
+    let (status, body) = match (method, path) {
+        ("GET", METRICS_PATH) => ("200 OK", snapshot.text()),
+        ("GET", _) => ("404 Not Found", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method Not Allowed\n".to_string()),
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Write the `HELP` and `TYPE` lines of a metric.
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::connectors::Connector;
+    use crate::core::addr::AddrResult;
+    use crate::core::metrics::{MetricsResult, Status};
+    use crate::core::scan::Scan;
+    use chrono::{Local, TimeDelta};
+    use std::io::Read;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Instant;
+
+    fn hosts() -> Vec<Mutex<Host>> {
+        let addr: SocketAddr = "127.0.0.1:22".parse().unwrap();
+        let mut host = Host::from_addrs("db.example.com", vec![addr]).unwrap();
+        let ms = TimeDelta::milliseconds;
+        for (seq, ok) in [(1, true), (2, true), (3, false)] {
+            let status = Status::new(ok, None);
+            let mr = MetricsResult::new(seq, Local::now(), ms(3), status)
+                .with_addrs(vec![AddrResult::new(addr, ms(3), None)]);
+            host.record_result(mr);
+        }
+        vec![Mutex::new(host), Mutex::new(Host::new("::1", 443).unwrap())]
+    }
+
+    #[test]
+    fn test_render() {
+        let text = render(&hosts());
+        let labels = "host=\"db.example.com\",port=\"22\"";
+        for line in [
+            format!("pt_success_total{{{},addr=\"127.0.0.1:22\"}} 2", labels),
+            format!("pt_failure_total{{{},addr=\"127.0.0.1:22\"}} 1", labels),
+            format!(
+                "pt_attempt_duration_seconds_bucket{{{},le=\"0.005\"}} 2",
+                labels
+            ),
+            format!(
+                "pt_attempt_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            format!("pt_attempt_duration_seconds_sum{{{}}} 0.006", labels),
+            format!("pt_attempt_duration_seconds_count{{{}}} 2", labels),
+            format!(
+                "pt_attempt_duration_quantile_seconds{{{},quantile=\"0.99\"}} 0.003",
+                labels
+            ),
+            format!("pt_last_success{{{},addr=\"127.0.0.1:22\"}} 0", labels),
+            "# TYPE pt_attempt_duration_seconds histogram".to_string(),
+            "pt_attempt_duration_seconds_count{host=\"::1\",port=\"443\"} 0".to_string(),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} missing from\n{}",
+                line,
+                text
+            );
+        }
+        // A host without attempts has no last attempt.
+        assert!(!text.contains("pt_last_success{host=\"::1\""));
+        assert_eq!(text.matches("# TYPE pt_success_total counter").count(), 1);
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_write_textfile() {
+        let dir = std::env::temp_dir().join(format!("pt-test-textfile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pt.prom");
+        std::fs::write(&path, "stale").unwrap();
+
+        let hosts = hosts();
+        write_textfile(&path, &hosts).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        let missing = write_textfile(&dir.join("missing").join("pt.prom"), &hosts);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(text, render(&hosts));
+        // The temporary file is gone after the rename.
+        assert_eq!(files, 1);
+        assert_eq!(missing.unwrap_err().code(), Some(CODE_RUNTIME_ERROR));
+    }
+
+    /// Send a GET request for `path` to `addr` and return the response.
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).unwrap();
+        resp
+    }
+
+    #[test]
+    fn test_exporter() {
+        let exporter = Exporter::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = exporter.local_addr().unwrap();
+        let snapshot = Snapshot::new();
+        snapshot.publish(&hosts());
+        exporter.spawn(snapshot);
+
+        let resp = get(addr, "/metrics");
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(resp.contains(&format!("Content-Type: {}\r\n", CONTENT_TYPE)));
+        assert!(resp.contains("pt_success_total{host=\"db.example.com\""));
+        assert!(get(addr, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    /// Connector that holds the host for a while before recording a success.
+    struct Slow(AtomicBool);
+
+    impl Connector for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn connect(&self, seq: u32, host: &mut Host, _timeout: u64) {
+            self.0.store(true, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_secs(2));
+            host.record(seq, Local::now(), TimeDelta::zero(), Status::Success);
+        }
+    }
+
+    #[test]
+    fn test_exporter_during_attempt() {
+        let exporter = Exporter::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = exporter.local_addr().unwrap();
+        let hosts = Arc::new(hosts());
+        let snapshot = Snapshot::new();
+        snapshot.publish(&hosts);
+        exporter.spawn(snapshot.clone());
+
+        let slow = Arc::new(Slow(AtomicBool::new(false)));
+        let attempt = {
+            let (hosts, slow) = (Arc::clone(&hosts), Arc::clone(&slow));
+            std::thread::spawn(move || Scan::new().attempt(slow.as_ref(), 4, &hosts, 1))
+        };
+        while !slow.0.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(hosts[0].try_lock().is_err());
+
+        // The scrape is answered from the snapshot without waiting on the locked host.
+        let start = Instant::now();
+        let resp = get(addr, "/metrics");
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(resp.contains(
+            "pt_success_total{host=\"db.example.com\",port=\"22\",addr=\"127.0.0.1:22\"} 2\n"
+        ));
+
+        attempt.join().unwrap();
+        snapshot.publish(&hosts);
+        let resp = get(addr, "/metrics");
+        assert!(resp.contains(
+            "pt_attempt_duration_seconds_count{host=\"db.example.com\",port=\"22\"} 3\n"
+        ));
+    }
+}
@@ -0,0 +1,631 @@
+//! Name resolution that can bypass the system resolver.
+//!
+//! A [`Resolver`] turns the name of a [`Host`] into its addresses. By default it uses the
+//! system resolver like [`resolve_addresses`]. Static [`Override`]s, like curl's `--resolve`,
+//! pin a name and port to fixed addresses, and a DNS server set with [`Resolver::set_server`] is
+//! queried directly for `A` and `AAAA` records. Either way only the addresses of the resolver's
+//! [`AddrFamily`] are kept.
+//!
+//! Services like LDAP, Kerberos, SIP and XMPP are found with SRV records. A name like
+//! `_ldap._tcp.example.com` is looked up with [`Resolver::lookup_srv`] into [`Srv`] records, each
+//! naming a target and port. See [`Host::from_srv`].
+//!
+//! [`Host`]: crate::Host
+//! [`Host::from_srv`]: crate::Host::from_srv
+
+use crate::connectors::dns::{Dns, RecordType};
+use crate::core::error::*;
+use crate::core::host::{AddrFamily, filter_family, resolve_addresses};
+use crate::core::metrics::Timings;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Port DNS servers listen on when none is given.
+pub const DEFAULT_DNS_PORT: u16 = 53;
+/// Seconds to wait for a DNS server to answer by default.
+pub const DEFAULT_TIMEOUT: u64 = 5;
+/// File the system resolver reads its DNS servers from.
+pub const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// A static override of the addresses of a name and port, like curl's `--resolve`.
+///
+/// Parses from `HOST:PORT:ADDR[,ADDR...]`, where IPv6 addresses may be given in brackets.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::resolver::Override;
+///
+/// let o: Override = "example.com:443:192.0.2.1,[2001:db8::1]".parse().unwrap();
+/// assert_eq!(o.host(), "example.com");
+/// assert_eq!(o.port(), 443);
+/// assert_eq!(o.addrs().len(), 2);
+/// assert!("example.com:443".parse::<Override>().is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Override {
+    host: String,
+    port: u16,
+    addrs: Vec<IpAddr>,
+}
+
+impl FromStr for Override {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            Error::new(SourceError::Msg(format!(
+                "Invalid resolve override: {} (expected HOST:PORT:ADDR[,ADDR...])",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        };
+
+        let mut parts = s.splitn(3, ':');
+        let (Some(host), Some(port), Some(addrs)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        let addrs = addrs
+            .split(',')
+            .map(|a| a.trim_start_matches('[').trim_end_matches(']').parse())
+            .collect::<std::result::Result<Vec<IpAddr>, _>>()
+            .map_err(|_| invalid())?;
+
+        Ok(Override {
+            host: host.to_owned(),
+            port,
+            addrs,
+        })
+    }
+}
+
+impl Override {
+    /// Returns the name the override applies to.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the port the override applies to.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the addresses the name and port resolve to.
+    pub fn addrs(&self) -> &[IpAddr] {
+        &self.addrs
+    }
+
+    /// Returns `true` if the override applies to `host` on `port`. Names are compared without
+    /// regard to case.
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        self.port == port && self.host.eq_ignore_ascii_case(host)
+    }
+}
+
+/// Resolves names to addresses with static overrides, a DNS server queried directly or the
+/// system resolver, in that order. See the [module documentation](self).
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::host::AddrFamily;
+/// use port_tester::core::resolver::Resolver;
+///
+/// let resolver = Resolver::new()
+///     .set_family(AddrFamily::Ipv4)
+///     .set_override("db.example.com:5432:192.0.2.10,2001:db8::10".parse().unwrap());
+/// let addrs = resolver.resolve("db.example.com", 5432).unwrap();
+/// assert_eq!(addrs, vec!["192.0.2.10:5432".parse().unwrap()]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Resolver {
+    family: AddrFamily,
+    overrides: Vec<Override>,
+    server: Option<SocketAddr>,
+    timeout: u64,
+}
+
+/// Defaults to the system resolver for addresses of any family, with no overrides.
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver {
+            family: AddrFamily::default(),
+            overrides: Vec::new(),
+            server: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl Resolver {
+    /// Create a new [`Resolver`]. See [`Resolver::default`].
+    pub fn new() -> Self {
+        Resolver::default()
+    }
+
+    /// Keep only the addresses of `family`.
+    pub fn set_family(mut self, family: AddrFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Add a static override. The first override that matches a name and port is used.
+    pub fn set_override(mut self, o: Override) -> Self {
+        self.overrides.push(o);
+        self
+    }
+
+    /// Query the DNS server at `server` for names that have no override instead of using the
+    /// system resolver.
+    pub fn set_server(mut self, server: SocketAddr) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// Set the time in seconds to wait for the DNS server to answer.
+    pub fn set_timeout(mut self, timeout: u64) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the [`AddrFamily`] of the addresses kept.
+    pub fn family(&self) -> AddrFamily {
+        self.family
+    }
+
+    /// Returns the static overrides.
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+
+    /// Returns the DNS server queried directly, if set.
+    pub fn server(&self) -> Option<SocketAddr> {
+        self.server
+    }
+
+    /// Returns the addresses of the first override that matches `host` on `port`, without
+    /// filtering them by family.
+    pub fn lookup_override(&self, host: &str, port: u16) -> Option<Vec<SocketAddr>> {
+        self.overrides
+            .iter()
+            .find(|o| o.matches(host, port))
+            .map(|o| {
+                o.addrs
+                    .iter()
+                    .map(|ip| SocketAddr::new(*ip, port))
+                    .collect()
+            })
+    }
+
+    /// Resolve `host` on `port` to the addresses of the resolver's family.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lookup fails, the DNS server does not answer or none of the
+    /// addresses are of the resolver's family.
+    pub fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let addrs = match (self.lookup_override(host, port), self.server) {
+            (Some(addrs), _) => addrs,
+            (None, Some(server)) if host.parse::<IpAddr>().is_err() => {
+                self.query(&server, host, port)?
+            }
+            (None, _) => resolve_addresses(host, port)?,
+        };
+        filter_family(host, addrs, self.family)
+    }
+
+    /// Look up the SRV records of `name`, like `_ldap._tcp.example.com`, ordered by priority
+    /// and then weight, heaviest first. The DNS server set with [`Resolver::set_server`] is
+    /// queried, or else the first server of the system, see [`system_server`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the DNS server does not answer or `name` has no SRV records. A single
+    /// record with the target `.` means the service is not available and is an error too.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use port_tester::core::resolver::Resolver;
+    ///
+    /// for srv in Resolver::new().lookup_srv("_ldap._tcp.example.com").unwrap() {
+    ///     println!("{}:{} {}", srv.target(), srv.port(), srv.report());
+    /// }
+    /// ```
+    pub fn lookup_srv(&self, name: &str) -> Result<Vec<Srv>> {
+        let server = match self.server {
+            Some(server) => server,
+            None => system_server()?,
+        };
+        let answers = Dns::new()
+            .set_name(name)
+            .set_record_type(RecordType::SRV)
+            .query(&server, self.timeout, &mut Timings::default())
+            .map_err(|e| {
+                e.set_context(&format!("SRV lookup failed: {} via {}", name, server))
+                    .set_code(CODE_OPTIONS_ERROR)
+            })?;
+
+        let mut records: Vec<Srv> = answers
+            .iter()
+            .filter_map(|a| Srv::from_answer(name, a))
+            .filter(|s| !s.target.is_empty())
+            .collect();
+        if records.is_empty() {
+            return Err(Error::new(SourceError::Msg(format!(
+                "No SRV records found for: {}",
+                name
+            )))
+            .set_code(CODE_OPTIONS_ERROR));
+        }
+        records.sort_by(|a, b| {
+            a.priority
+                .cmp(&b.priority)
+                .then(b.weight.cmp(&a.weight))
+                .then_with(|| a.target.cmp(&b.target))
+        });
+        Ok(records)
+    }
+
+    /// Query `server` for the `A` and `AAAA` records of `host` that the family allows.
+    fn query(&self, server: &SocketAddr, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let types = match self.family {
+            AddrFamily::Ipv4 => vec![RecordType::A],
+            AddrFamily::Ipv6 => vec![RecordType::AAAA],
+            _ => vec![RecordType::AAAA, RecordType::A],
+        };
+
+        let mut addrs = Vec::new();
+        for record_type in types {
+            let answers = Dns::new()
+                .set_name(host)
+                .set_record_type(record_type)
+                .query(server, self.timeout, &mut Timings::default())
+                .map_err(|e| {
+                    e.set_context(&format!("Hostname lookup failed: {} via {}", host, server))
+                        .set_code(CODE_OPTIONS_ERROR)
+                })?;
+            addrs.extend(
+                answers
+                    .iter()
+                    .filter_map(|a| a.parse::<IpAddr>().ok())
+                    .map(|ip| SocketAddr::new(ip, port)),
+            );
+        }
+        Ok(addrs)
+    }
+}
+
+/// An SRV record of a service, naming a target host and port for it.
+///
+/// Records are ordered by [`Resolver::lookup_srv`] as clients pick them: lowest priority first
+/// and, within a priority, heaviest weight first.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Srv {
+    service: String,
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: String,
+}
+
+impl Srv {
+    /// Create a new [`Srv`] record of `service`, like `_ldap._tcp.example.com`.
+    pub fn new(service: &str, priority: u16, weight: u16, port: u16, target: &str) -> Self {
+        Srv {
+            service: service.to_owned(),
+            priority,
+            weight,
+            port,
+            target: target.trim_end_matches('.').to_owned(),
+        }
+    }
+
+    /// Returns the name of the service the record was found for.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Returns the priority of the target. Lower priorities are tried first.
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    /// Returns the relative weight of the target among targets with the same priority.
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+
+    /// Returns the port the service listens on at the target.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the name of the target host.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns the record as a single line, like `srv _ldap._tcp.example.com priority 10 weight 60`.
+    pub fn report(&self) -> String {
+        format!(
+            "srv {} priority {} weight {}",
+            self.service, self.priority, self.weight
+        )
+    }
+
+    /// Parse an answer in the `<priority> <weight> <port> <target>` format of the dns probe.
+    fn from_answer(service: &str, answer: &str) -> Option<Self> {
+        let mut fields = answer.split_whitespace();
+        let priority = fields.next()?.parse().ok()?;
+        let weight = fields.next()?.parse().ok()?;
+        let port = fields.next()?.parse().ok()?;
+        let target = fields.next().unwrap_or_default();
+        Some(Srv::new(service, priority, weight, port, target))
+    }
+}
+
+/// Returns `true` if `name` is an SRV name, a service and protocol label followed by a domain,
+/// like `_ldap._tcp.example.com`.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::resolver::is_srv_name;
+///
+/// assert!(is_srv_name("_xmpp-client._tcp.example.com"));
+/// assert!(!is_srv_name("example.com"));
+/// assert!(!is_srv_name("_ldap._tcp"));
+/// ```
+pub fn is_srv_name(name: &str) -> bool {
+    let label = |l: &str| l.len() > 1 && l.starts_with('_');
+    let mut labels = name.splitn(3, '.');
+    match (labels.next(), labels.next(), labels.next()) {
+        (Some(service), Some(proto), Some(domain)) => {
+            label(service) && label(proto) && !domain.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the first DNS server listed in `/etc/resolv.conf`, used for lookups the system
+/// resolver can't do.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if the file can't be read or lists no servers.
+pub fn system_server() -> Result<SocketAddr> {
+    let conf = std::fs::read_to_string(RESOLV_CONF).map_err(|e| {
+        Error::new(SourceError::Io(e))
+            .set_context(&format!("Failed to read {}", RESOLV_CONF))
+            .set_code(CODE_OPTIONS_ERROR)
+    })?;
+    conf.lines()
+        .filter_map(|l| l.strip_prefix("nameserver"))
+        .find_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, DEFAULT_DNS_PORT))
+        .ok_or_else(|| {
+            Error::new(SourceError::Msg(format!(
+                "No nameserver found in {}",
+                RESOLV_CONF
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        })
+}
+
+/// Parses a DNS server address as an IP address with an optional port, like `192.0.2.53`,
+/// `192.0.2.53:5353`, `2001:db8::53` or `[2001:db8::53]:5353`. The port defaults to
+/// [`DEFAULT_DNS_PORT`].
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if `s` is not an address.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::resolver::parse_server;
+///
+/// assert_eq!(parse_server("192.0.2.53").unwrap(), "192.0.2.53:53".parse().unwrap());
+/// assert_eq!(parse_server("[::1]:5353").unwrap(), "[::1]:5353".parse().unwrap());
+/// assert!(parse_server("dns.example.com").is_err());
+/// ```
+pub fn parse_server(s: &str) -> Result<SocketAddr> {
+    s.parse::<SocketAddr>()
+        .or_else(|_| {
+            s.parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, DEFAULT_DNS_PORT))
+        })
+        .map_err(|_| {
+            Error::new(SourceError::Msg(format!(
+                "Invalid DNS server: {} (expected an IP address with an optional port)",
+                s
+            )))
+            .set_code(CODE_OPTIONS_ERROR)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simple_dns::rdata::{A, AAAA, RData, SRV};
+    use simple_dns::{CLASS, Packet, ResourceRecord, TYPE};
+    use std::net::UdpSocket;
+
+    /// Start a DNS server that answers `queries` queries for `A` with 192.0.2.7, for `AAAA`
+    /// with 2001:db8::7 and for `SRV` with three LDAP servers. Returns its address.
+    fn serve(queries: usize) -> SocketAddr {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0; 512];
+            for _ in 0..queries {
+                let (n, peer) = sock.recv_from(&mut buf).unwrap();
+                let q = Packet::parse(&buf[..n]).unwrap();
+                let mut r = Packet::new_reply(q.id());
+                let question = q.questions[0].clone();
+                let rdata = match question.qtype {
+                    simple_dns::QTYPE::TYPE(TYPE::A) => vec![RData::A(A {
+                        address: u32::from(std::net::Ipv4Addr::new(192, 0, 2, 7)),
+                    })],
+                    simple_dns::QTYPE::TYPE(TYPE::SRV) => [
+                        (20, 0, "ldap3.example.com"),
+                        (10, 40, "ldap2.example.com"),
+                        (10, 60, "ldap1.example.com"),
+                    ]
+                    .into_iter()
+                    .map(|(priority, weight, target)| {
+                        RData::SRV(SRV {
+                            priority,
+                            weight,
+                            port: 389,
+                            target: simple_dns::Name::new_unchecked(target),
+                        })
+                    })
+                    .collect(),
+                    _ => vec![RData::AAAA(AAAA {
+                        address: u128::from("2001:db8::7".parse::<std::net::Ipv6Addr>().unwrap()),
+                    })],
+                };
+                for rdata in rdata {
+                    r.answers.push(ResourceRecord::new(
+                        question.qname.clone(),
+                        CLASS::IN,
+                        60,
+                        rdata,
+                    ));
+                }
+                r.questions.push(question);
+                let _ = sock.send_to(&r.build_bytes_vec().unwrap(), peer);
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_override() {
+        let o: Override = "Example.com:443:[2001:db8::1]".parse().unwrap();
+        assert!(o.matches("example.com", 443));
+        assert!(!o.matches("example.com", 80));
+        assert_eq!(o.addrs(), &["2001:db8::1".parse::<IpAddr>().unwrap()]);
+
+        for s in [
+            "",
+            "example.com",
+            ":443:192.0.2.1",
+            "example.com:x:192.0.2.1",
+            "a:1:b",
+        ] {
+            let e = s.parse::<Override>().unwrap_err();
+            assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+        }
+
+        let r = Resolver::new().set_override(o);
+        assert_eq!(
+            r.resolve("example.com", 443).unwrap(),
+            vec!["[2001:db8::1]:443".parse().unwrap()]
+        );
+        // Other ports fall through to the system resolver.
+        assert!(r.lookup_override("example.com", 80).is_none());
+        let e = r.set_family(AddrFamily::Ipv4).resolve("example.com", 443);
+        assert_eq!(e.unwrap_err().code(), Some(CODE_OPTIONS_ERROR));
+    }
+
+    #[test]
+    fn test_server() {
+        let r = Resolver::new().set_server(serve(2)).set_timeout(2);
+        assert_eq!(
+            r.resolve("db.internal", 5432).unwrap(),
+            vec![
+                "[2001:db8::7]:5432".parse().unwrap(),
+                "192.0.2.7:5432".parse().unwrap()
+            ]
+        );
+
+        let r = r.set_server(serve(1)).set_family(AddrFamily::Ipv4);
+        assert_eq!(
+            r.resolve("db.internal", 5432).unwrap(),
+            vec!["192.0.2.7:5432".parse().unwrap()]
+        );
+
+        // IP addresses are not looked up.
+        assert_eq!(
+            r.resolve("127.0.0.1", 22).unwrap(),
+            vec!["127.0.0.1:22".parse().unwrap()]
+        );
+
+        // Nothing answers on the port of a closed socket.
+        let closed = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let e = Resolver::new()
+            .set_server(closed)
+            .set_timeout(1)
+            .resolve("db.internal", 5432)
+            .unwrap_err();
+        assert_eq!(e.code(), Some(CODE_OPTIONS_ERROR));
+    }
+
+    #[test]
+    fn test_srv() {
+        assert!(is_srv_name("_ldap._tcp.example.com"));
+        assert!(!is_srv_name("_._tcp.example.com"));
+        assert!(!is_srv_name("ldap._tcp.example.com"));
+
+        let r = Resolver::new().set_server(serve(1)).set_timeout(2);
+        let records = r.lookup_srv("_ldap._tcp.example.com").unwrap();
+        let targets: Vec<_> = records.iter().map(|s| s.target()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                "ldap1.example.com",
+                "ldap2.example.com",
+                "ldap3.example.com"
+            ]
+        );
+        assert_eq!(records[0].port(), 389);
+        assert_eq!(records[0].service(), "_ldap._tcp.example.com");
+        assert_eq!(
+            records[0].report(),
+            "srv _ldap._tcp.example.com priority 10 weight 60"
+        );
+
+        assert_eq!(
+            Srv::from_answer("_sip._udp.example.com", "0 5 5060 sip.example.com."),
+            Some(Srv::new(
+                "_sip._udp.example.com",
+                0,
+                5,
+                5060,
+                "sip.example.com"
+            ))
+        );
+        assert!(Srv::from_answer("_sip._udp.example.com", "0 5").is_none());
+
+        // Each target becomes a host on the port of its record.
+        let mut r = Resolver::new().set_server(serve(1)).set_timeout(2);
+        for (i, target) in targets.iter().enumerate() {
+            let o = format!("{}:389:127.0.0.{}", target, i + 1);
+            r = r.set_override(o.parse().unwrap());
+        }
+        let hosts = crate::Host::from_srv("_ldap._tcp.example.com", &r).unwrap();
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[1].name(), "ldap2.example.com");
+        assert_eq!(hosts[1].addr(), &"127.0.0.2:389".parse().unwrap());
+        assert_eq!(hosts[1].srv(), Some(&records[1]));
+        assert!(hosts[1].for_port(636).srv().is_none());
+    }
+}
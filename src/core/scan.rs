@@ -0,0 +1,436 @@
+//! Port lists and concurrent attempts against many targets.
+//!
+//! This module provides [`parse_ports`] for port lists and ranges like `22,80,8000-8100`,
+//! [`PortState`] for classifying an attempt as open, closed or filtered, and [`Scan`] for running
+//! an attempt against many [`Host`]s with a concurrency limit and a rate limit.
+
+use crate::Host;
+use crate::connectors::Connector;
+use crate::core::error::*;
+use crate::core::metrics::{FailureKind, MetricsResult, Status};
+use log::debug;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Number of attempts run at the same time by default.
+pub const DEFAULT_CONCURRENCY: usize = 64;
+
+/// Parse a comma separated list of ports and port ranges, like `22,80,443` or `8000-8100`.
+///
+/// Ports are returned in the order given with duplicates removed.
+///
+/// # Errors
+///
+/// Returns an error with [`CODE_OPTIONS_ERROR`] if `spec` is empty, contains a port outside of
+/// 1-65535 or contains a range whose start is greater than its end.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::scan::parse_ports;
+///
+/// assert_eq!(parse_ports("22,80,8000-8002").unwrap(), vec![22, 80, 8000, 8001, 8002]);
+/// assert!(parse_ports("443-80").is_err());
+/// ```
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>> {
+    let err = |msg: String| Error::new(SourceError::Msg(msg)).set_code(CODE_OPTIONS_ERROR);
+    let port = |p: &str| match p.trim().parse::<u16>() {
+        Ok(p) if p > 0 => Ok(p),
+        _ => Err(err(format!("Invalid port: {}", p))),
+    };
+
+    let mut ports = Vec::new();
+    let mut seen = HashSet::new();
+    for part in spec.split(',') {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (port(start)?, port(end)?),
+            None => {
+                let p = port(part)?;
+                (p, p)
+            }
+        };
+        if start > end {
+            return Err(err(format!("Invalid port range: {}", part)));
+        }
+        for p in start..=end {
+            if seen.insert(p) {
+                ports.push(p);
+            }
+        }
+    }
+    Ok(ports)
+}
+
+/// Returns `true` if `s` looks like a port list or range rather than a host.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::scan::is_port_spec;
+///
+/// assert!(is_port_spec("22,80,8000-8100"));
+/// assert!(!is_port_spec("10.0.0.1"));
+/// ```
+pub fn is_port_spec(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_digit() || b == b',' || b == b'-')
+}
+
+/// Reachability of a port as seen by an attempt, classified from its [`FailureKind`].
+///
+/// Only a timeout or an unreachable host or network is treated as [`PortState::Filtered`], since
+/// a dropped packet and a timeout look the same from the outside. A port that accepted the
+/// connection or answered is [`PortState::Open`] even if the probe's exchange with it failed
+/// afterwards, see [`FailureKind::Protocol`]. Use `PortState::from(&MetricsResult)` to take that
+/// into account, as a [`Status`] alone can't tell.
+///
+/// # Examples
+///
+/// ```
+/// use port_tester::core::metrics::Status;
+/// use port_tester::core::scan::PortState;
+/// use port_tester::{Error, SourceError};
+///
+/// let timeout = Error::new(SourceError::Io(std::io::ErrorKind::TimedOut.into()));
+/// assert_eq!(PortState::from(&Status::Success), PortState::Open);
+/// assert_eq!(PortState::from(&Status::Failure(Some(timeout))), PortState::Filtered);
+/// assert_eq!(PortState::from(&Status::Failure(None)), PortState::Unknown);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum PortState {
+    /// The attempt succeeded.
+    Open,
+    /// The host actively refused the connection.
+    Closed,
+    /// The attempt timed out or the host or its network was unreachable.
+    #[default]
+    Filtered,
+    /// The attempt failed before reaching the port, like a failed name lookup.
+    Unknown,
+}
+
+impl std::fmt::Display for PortState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<&Status> for PortState {
+    fn from(status: &Status) -> Self {
+        PortState::from_failure_kind(status.failure_kind())
+    }
+}
+
+impl From<&MetricsResult> for PortState {
+    fn from(result: &MetricsResult) -> Self {
+        PortState::from_failure_kind(result.failure_kind())
+    }
+}
+
+impl PortState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::Unknown => "unknown",
+        }
+    }
+
+    /// Returns the state of a port whose attempt failed with `kind`, or succeeded if `None`.
+    /// A DNS server answering with an error is open.
+    fn from_failure_kind(kind: Option<FailureKind>) -> Self {
+        match kind {
+            None | Some(FailureKind::Protocol) | Some(FailureKind::Dns) => PortState::Open,
+            Some(FailureKind::Refused) => PortState::Closed,
+            Some(
+                FailureKind::Timeout
+                | FailureKind::HostUnreachable
+                | FailureKind::NetworkUnreachable,
+            ) => PortState::Filtered,
+            Some(_) => PortState::Unknown,
+        }
+    }
+}
+
+/// Runs an attempt against many [`Host`]s at once.
+///
+/// At most [`Scan::set_concurrency`] attempts run at the same time and, when a rate is set with
+/// [`Scan::set_rate`], attempts are started no faster than that many per second. The rate is
+/// kept across calls to [`Scan::attempt`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use port_tester::Host;
+/// use port_tester::connectors::port_open::PortOpen;
+/// use port_tester::core::scan::{PortState, Scan};
+/// use std::sync::Mutex;
+///
+/// let hosts: Vec<Mutex<Host>> = [22, 80, 443]
+///     .iter()
+///     .map(|p| Mutex::new(Host::new("127.0.0.1", *p).unwrap()))
+///     .collect();
+/// let scan = Scan::new().set_concurrency(2).set_rate(10);
+/// scan.attempt(&PortOpen, 1, &hosts, 2);
+/// for host in &hosts {
+///     let h = host.lock().unwrap();
+///     println!("{} {}", h.port(), PortState::from(h.metrics().result(1).unwrap()));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Scan {
+    concurrency: usize,
+    rate: u32,
+    /// Earliest time the next attempt may start.
+    next: Mutex<Instant>,
+}
+
+/// Defaults to [`DEFAULT_CONCURRENCY`] attempts at a time with no rate limit.
+impl Default for Scan {
+    fn default() -> Self {
+        Scan {
+            concurrency: DEFAULT_CONCURRENCY,
+            rate: 0,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Scan {
+    /// Create a new [`Scan`]. See [`Scan::default`].
+    pub fn new() -> Self {
+        Scan::default()
+    }
+
+    /// Set the maximum number of attempts to run at the same time. A limit of 0 is treated as 1.
+    pub fn set_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the maximum number of attempts to start per second. 0 for no limit.
+    pub fn set_rate(mut self, rate: u32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Run attempt `seq` of `connector` against every host and wait for them all to finish.
+    /// `timeout` is in seconds. Hosts that already have a result for `seq`, like a failed
+    /// resolution recorded before the attempt, are skipped.
+    pub fn attempt(
+        &self,
+        connector: &dyn Connector,
+        seq: u32,
+        hosts: &[Mutex<Host>],
+        timeout: u64,
+    ) {
+        let next = AtomicUsize::new(0);
+        let run = || {
+            while let Some(host) = hosts.get(next.fetch_add(1, Ordering::Relaxed)) {
+                if host.lock().unwrap().metrics().result(seq).is_some() {
+                    continue;
+                }
+                self.wait();
+                let mut h = host.lock().unwrap();
+                debug!(
+                    "attempt: {}, ip: {}, port: {}, timeout: {}",
+                    seq,
+                    h.ip(),
+                    h.port(),
+                    timeout
+                );
+                connector.connect(seq, &mut h, timeout);
+            }
+        };
+
+        match self.concurrency.min(hosts.len()) {
+            0 | 1 => run(),
+            workers => std::thread::scope(|s| {
+                for _ in 0..workers {
+                    s.spawn(run);
+                }
+            }),
+        }
+    }
+
+    /// Wait until the rate limit allows another attempt to start.
+    fn wait(&self) {
+        if self.rate == 0 {
+            return;
+        }
+
+        let start = {
+            let mut next = self.next.lock().unwrap();
+            let start = (*next).max(Instant::now());
+            *next = start + Duration::from_secs(1) / self.rate;
+            start
+        };
+        std::thread::sleep(start.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::connectors::port_open::PortOpen;
+    use std::io::ErrorKind;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_ports() {
+        assert_eq!(parse_ports("443").unwrap(), vec![443]);
+        assert_eq!(parse_ports("22, 80,443").unwrap(), vec![22, 80, 443]);
+        assert_eq!(parse_ports("3-5,1,4").unwrap(), vec![3, 4, 5, 1]);
+        assert_eq!(parse_ports("65535-65535").unwrap(), vec![65535]);
+        assert_eq!(parse_ports("1-65535").unwrap().len(), 65535);
+        assert!(parse_ports("").is_err());
+        assert!(parse_ports("0").is_err());
+        assert!(parse_ports("80,").is_err());
+        assert!(parse_ports("65536").is_err());
+        assert!(parse_ports("100-10").is_err());
+        assert!(parse_ports("1-2-3").is_err());
+        assert_eq!(
+            parse_ports("http").err().unwrap().code(),
+            Some(CODE_OPTIONS_ERROR)
+        );
+
+        assert!(is_port_spec("80"));
+        assert!(!is_port_spec(""));
+        assert!(!is_port_spec("::1"));
+        assert!(!is_port_spec("example.com"));
+    }
+
+    #[test]
+    fn test_port_state() {
+        let refused = Error::new(SourceError::Io(std::io::Error::from(
+            ErrorKind::ConnectionRefused,
+        )));
+        let timeout = Error::new(SourceError::Io(std::io::Error::from(ErrorKind::TimedOut)));
+        let msg = Error::new(SourceError::Msg("no".to_string()));
+
+        assert_eq!(PortState::from(&Status::Success), PortState::Open);
+        assert_eq!(
+            PortState::from(&Status::Failure(Some(refused))),
+            PortState::Closed
+        );
+        assert_eq!(
+            PortState::from(&Status::Failure(Some(timeout))),
+            PortState::Filtered
+        );
+        assert_eq!(
+            PortState::from(&Status::Failure(Some(msg))),
+            PortState::Unknown
+        );
+        assert_eq!(PortState::Closed.to_string(), "closed");
+    }
+
+    #[test]
+    fn test_port_state_protocol() {
+        use crate::connectors::banner::Banner;
+        use std::io::Write;
+
+        // The port accepts the connection but sends a banner the probe doesn't expect.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            sock.write_all(b"220 smtp.example.com ESMTP\r\n").unwrap();
+        });
+
+        let hosts = vec![Mutex::new(Host::new("127.0.0.1", port).unwrap())];
+        let banner = Banner::new().set_expect(regex::Regex::new("^SSH-").unwrap());
+        Scan::new().attempt(&banner, 1, &hosts, 1);
+        server.join().unwrap();
+
+        let h = hosts[0].lock().unwrap();
+        let mr = h.metrics().result(1).unwrap();
+        assert!(mr.is_err());
+        assert_eq!(mr.failure_kind(), Some(FailureKind::Protocol));
+        assert_eq!(PortState::from(mr), PortState::Open);
+    }
+
+    #[test]
+    fn test_attempt() {
+        let listeners: Vec<TcpListener> = (0..3)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        // Bind and drop a listener to find a port with nothing listening.
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut hosts: Vec<Mutex<Host>> = listeners
+            .iter()
+            .map(|l| Mutex::new(Host::new("127.0.0.1", l.local_addr().unwrap().port()).unwrap()))
+            .collect();
+        hosts.push(Mutex::new(Host::new("127.0.0.1", closed).unwrap()));
+
+        let scan = Scan::new().set_concurrency(2);
+        scan.attempt(&PortOpen, 1, &hosts, 1);
+        scan.attempt(&PortOpen, 2, &hosts, 1);
+
+        let states: Vec<PortState> = hosts
+            .iter()
+            .map(|h| {
+                let h = h.lock().unwrap();
+                assert_eq!(h.metrics().attempts(), 2);
+                PortState::from(h.metrics().result(2).unwrap())
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                PortState::Open,
+                PortState::Open,
+                PortState::Open,
+                PortState::Closed
+            ]
+        );
+
+        // A host with a result for the attempt already is not probed again.
+        let failed = Status::Failure(Some(Error::new(SourceError::from("resolve failed"))));
+        hosts[0]
+            .lock()
+            .unwrap()
+            .record(3, chrono::Local::now(), chrono::TimeDelta::zero(), failed);
+        scan.attempt(&PortOpen, 3, &hosts, 1);
+        let h = hosts[0].lock().unwrap();
+        assert_eq!(h.metrics().attempts(), 3);
+        assert!(h.metrics().result(3).unwrap().is_err());
+        assert!(
+            !hosts[1]
+                .lock()
+                .unwrap()
+                .metrics()
+                .result(3)
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_attempt_rate() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hosts: Vec<Mutex<Host>> = (0..5)
+            .map(|_| Mutex::new(Host::new("127.0.0.1", port).unwrap()))
+            .collect();
+
+        // Five attempts at 20 per second start 50ms apart, so the last starts 200ms in.
+        let start = Instant::now();
+        Scan::new().set_rate(20).attempt(&PortOpen, 1, &hosts, 1);
+        assert!(start.elapsed() >= Duration::from_millis(190));
+        assert!(
+            hosts
+                .iter()
+                .all(|h| h.lock().unwrap().metrics().attempts() == 1)
+        );
+    }
+}